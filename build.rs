@@ -35,6 +35,21 @@ fn main() {
                     .to_str()
                     .expect("Could not convert file_name OsString to string slice")
                     .starts_with("meshlet_common")
+                && !f
+                    .file_name()
+                    .to_str()
+                    .expect("Could not convert file_name OsString to string slice")
+                    .starts_with("material_common")
+                && !f
+                    .file_name()
+                    .to_str()
+                    .expect("Could not convert file_name OsString to string slice")
+                    .starts_with("vertex_common")
+                && !f
+                    .file_name()
+                    .to_str()
+                    .expect("Could not convert file_name OsString to string slice")
+                    .starts_with("light_common")
         })
         .map(|file| file.as_ref().unwrap().path())
         .collect::<Vec<_>>();
@@ -2,12 +2,18 @@
 
 #extension GL_GOOGLE_include_directive : enable
 #include "descriptor_set_traditional.glsl"
+#include "material_common.glsl"
+#include "light_common.glsl"
 
 layout(location = 0) out vec4 out_color;
 
 layout(location = 0) in vec3 frag_pos;
 layout(location = 1) in vec3 frag_normal;
 layout(location = 2) in vec4 frag_pos_light_space;
+layout(location = 3) flat in uint frag_material_index;
+layout(location = 4) in vec4 frag_tx; // xy TEXCOORD_0, zw TEXCOORD_1
+layout(location = 5) in vec4 frag_tangent;
+layout(location = 6) in vec4 frag_color;
 
 float calc_shadow() {
     vec3 proj_coords = frag_pos_light_space.xyz / frag_pos_light_space.w;
@@ -24,15 +30,29 @@ void main() {
     vec3 light_diffuse = vec3(1.0, 1.0, 1.0);
     vec3 light_specular = vec3(1.0, 1.0, 1.0);
     vec3 viewPos = push_constants.camera.position.xyz;
-    float shininess = 64;
-    vec3 cube_color = vec3(1.0, 1.0, 1.0);
+    Material material = push_constants.materials.materials[frag_material_index];
+    vec4 base_color = material_base_color(material, frag_tx, frag_color);
+    if (material.alpha_mode == ALPHA_MODE_MASK && base_color.a < material.alpha_cutoff) {
+        discard;
+    }
+    vec3 orm = material_orm(material, frag_tx);
+    float occlusion = orm.x;
+    float roughness = orm.y;
+    float metallic = orm.z;
+    float shininess = blinn_phong_shininess(roughness);
+    vec3 cube_color = base_color.rgb * (1.0 - metallic);
+    vec3 specular_color = mix(vec3(0.04), base_color.rgb, metallic);
     vec3 light_color = vec3(1.0, 1.0, 1.0);
 
     // ambient
-    vec3 ambient = light_ambient * cube_color;
+    vec3 ambient = light_ambient * base_color.rgb * occlusion;
 
     // diffuse
     vec3 norm = normalize(frag_normal);
+    if (material.double_sided != 0 && !gl_FrontFacing) {
+        norm = -norm;
+    }
+    norm = material_normal(material, norm, frag_tangent, frag_pos, frag_tx);
     vec3 lightDir = normalize((-light.dir).xyz);
     float diff = max(dot(lightDir, norm), 0.0);
     vec3 diffuse = light_diffuse * diff * cube_color;
@@ -41,11 +61,13 @@ void main() {
     vec3 view_dir = normalize(viewPos - frag_pos);
     vec3 halfwayDir = normalize(lightDir + view_dir);
     float spec = pow(max(dot(norm, halfwayDir), 0.0), shininess);
-    vec3 specular = spec * light_specular;
+    vec3 specular = spec * light_specular * specular_color;
 
     float shadow = calc_shadow();
     shadow = 0.0;
-    vec3 result = (ambient * 0.0) + (1.0 - shadow) * (diffuse + specular);
+    vec3 punctual = shade_punctual_lights(push_constants.lights, push_constants.light_count, frag_pos, norm, view_dir, cube_color, specular_color, shininess);
+    vec3 result = (ambient * 0.0) + (1.0 - shadow) * (diffuse + specular) + punctual + material_emissive(material, frag_tx);
 
-    out_color = vec4(result, 1.0);
+    float alpha = material.alpha_mode == ALPHA_MODE_BLEND ? base_color.a : 1.0;
+    out_color = vec4(result, alpha);
 }
@@ -2,28 +2,58 @@
 
 #extension GL_GOOGLE_include_directive : enable
 #include "descriptor_set_traditional.glsl"
+#include "vertex_common.glsl"
 
-layout(location = 0) in vec3 pos;
-layout(location = 1) in vec3 normal;
-layout(location = 2) in vec2 tx;
+layout(location = VERTEX_POSITION_LOCATION) in vec3 pos;
+layout(location = VERTEX_NORMAL_LOCATION) in vec3 normal;
+layout(location = VERTEX_TEX_COORD_LOCATION) in vec2 tx;
+layout(location = VERTEX_TANGENT_LOCATION) in vec4 tangent;
+layout(location = VERTEX_TEX_COORD1_LOCATION) in vec2 tx1;
+layout(location = VERTEX_COLOR_LOCATION) in vec4 color;
+layout(location = VERTEX_JOINTS_LOCATION) in uvec4 joints;
+layout(location = VERTEX_WEIGHTS_LOCATION) in vec4 weights;
 
 layout(location = 0) out vec3 frag_pos;
 layout(location = 1) out vec3 frag_normal;
 layout(location = 2) out vec4 frag_pos_light_space;
+layout(location = 3) flat out uint frag_material_index;
+layout(location = 4) out vec4 frag_tx; // xy TEXCOORD_0, zw TEXCOORD_1
+layout(location = 5) out vec4 frag_tangent;
+layout(location = 6) out vec4 frag_color;
 
 void main()
 {
-    vec4 vertex = vec4(pos, 1.0);
-
     uint instance_index = gl_InstanceIndex + push_constants.instance_offsets.offset[gl_DrawID];
 
-    mat4 model_matrix = push_constants.instances.transforms[instance_index].model_matrix;
+    TraditionalInstance instance = push_constants.instances.instances[instance_index];
+
+    vec3 morphed_pos = pos;
+    vec3 morphed_normal = normal;
+    vec3 morphed_tangent = tangent.xyz;
+    // deltas are per primitive, the base vertex is where the primitive starts in the combined buffer
+    apply_morph_targets(
+        instance.morph_deltas,
+        instance.morph_weights,
+        instance.morph_target_count,
+        gl_VertexIndex - gl_BaseVertex,
+        morphed_pos,
+        morphed_normal,
+        morphed_tangent
+    );
+    vec4 vertex = vec4(morphed_pos, 1.0);
+
+    mat4 model_matrix = instance.transform.model_matrix
+        * skin_matrix(instance.joints, instance.joint_count, joints, weights);
+    frag_material_index = instance.material_index;
+    frag_tx = vec4(tx, tx1);
+    frag_color = color;
     frag_pos = vec3(model_matrix * vertex);
     // frag_pos = vertex.xyz;
     frag_pos_light_space = push_constants.dir_light_camera.projview * vec4(frag_pos, 1.0);
 
-    frag_normal = mat3(transpose(inverse(model_matrix))) * normal;
+    frag_normal = mat3(transpose(inverse(model_matrix))) * morphed_normal;
     // frag_normal = normal;
+    frag_tangent = vec4(mat3(model_matrix) * morphed_tangent, tangent.w);
 
     gl_Position = push_constants.camera.projview * vec4(frag_pos, 1.0);
 }
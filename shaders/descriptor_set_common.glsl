@@ -4,6 +4,7 @@
 
 layout(set = 0, binding = 0) uniform samplerCube skybox_tx[];
 layout(set = 0, binding = 1) uniform sampler2D depth_textures[];
+layout(set = 0, binding = 2) uniform sampler2D textures[];
 
 layout(buffer_reference) readonly buffer CameraDataBuf {
     vec4 position;
@@ -13,3 +14,69 @@ layout(buffer_reference) readonly buffer CameraDataBuf {
 layout(buffer_reference) readonly buffer TransformBuf {
     mat4 model_matrix;
 };
+
+// joint palette of a skinned instance, see assets/skin.rs
+layout(buffer_reference) readonly buffer JointBuf {
+    mat4 joint_matrices[];
+};
+
+// morph target displacements, mirrors assets::morph::MorphDelta
+struct MorphDelta {
+    float px, py, pz;
+    float nx, ny, nz;
+    float tx, ty, tz;
+};
+
+// [vertex * morph_target_count + target]
+layout(buffer_reference) readonly buffer MorphDeltaBuf {
+    MorphDelta deltas[];
+};
+
+// one per morph target of a morphed instance
+layout(buffer_reference) readonly buffer MorphWeightBuf {
+    float weights[];
+};
+
+#define LIGHT_TYPE_DIRECTIONAL 0
+#define LIGHT_TYPE_POINT 1
+#define LIGHT_TYPE_SPOT 2
+
+// KHR_lights_punctual light in world space, mirrors GPUPunctualLight in assets/lights.rs
+struct PunctualLight {
+    vec4 position;  // w range, 0 if unlimited
+    vec4 direction; // w LIGHT_TYPE_*
+    vec4 color;     // premultiplied with the intensity
+    vec4 spot;      // x cos(outer cone angle), y 1 / (cos(inner) - cos(outer))
+};
+
+layout(buffer_reference) readonly buffer PunctualLightBuf {
+    PunctualLight lights[];
+};
+
+#define ALPHA_MODE_OPAQUE 0
+#define ALPHA_MODE_MASK 1
+#define ALPHA_MODE_BLEND 2
+
+// glTF metallic-roughness material, mirrors GPUMaterial in assets/material.rs
+struct Material {
+    vec4 base_color_factor;
+    vec4 emissive_factor; // w unused
+    float metallic_factor;
+    float roughness_factor;
+    float alpha_cutoff;
+    uint alpha_mode;
+    uint double_sided;
+    float normal_scale;
+    float occlusion_strength;
+    uint tex_coord_sets; // bit N set - texture N samples TEXCOORD_1
+    // bindless slots in textures[], -1 if absent
+    int base_color_texture;
+    int metallic_roughness_texture;
+    int normal_texture;
+    int occlusion_texture;
+    int emissive_texture;
+};
+
+layout(buffer_reference) readonly buffer MaterialBuf {
+    Material materials[];
+};
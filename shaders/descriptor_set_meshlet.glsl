@@ -1,14 +1,26 @@
 #extension GL_GOOGLE_include_directive : enable
 #include "descriptor_set_common.glsl"
+#include "vertex_common.glsl"
 
-struct Vertex {
-    float vx, vy, vz;
-    float nx, ny, nz;
-    float tx, ty;
+// Mirrors assets::meshlet_vertex::PackedVertex, its position is packed per meshlet vertex
+struct PackedVertex {
+    uint normal; // octahedral snorm16x2
+    uint tangent; // octahedral snorm16x2
+    uint tex_coord; // half2
+    uint tex_coord1; // half2
+    uint color; // unorm8x4
+    uint joints_xy, joints_zw; // uint16x2
+    uint weights_xy, weights_zw; // unorm16x2
 };
 
 layout(buffer_reference) readonly buffer VertexBuf {
-    Vertex vertices[];
+    PackedVertex vertices[];
+};
+
+// Mirrors assets::meshlet_vertex::PackedPosition, parallel to VertexIndexBuf: uint16 xyz grid steps
+// from the meshlet's origin, snorm16 w the bitangent sign
+layout(buffer_reference) readonly buffer MeshletPositionBuf {
+    uvec2 positions[];
 };
 
 layout(buffer_reference) readonly buffer TriangleIndexBuf {
@@ -19,22 +31,33 @@ layout(buffer_reference) readonly buffer VertexIndexBuf {
     uint meshlet_vertices[];
 };
 
+// Mirrors assets::meshlet::MeshletBounds
 struct MeshletBounds {
-    vec3 center;
+    float center_x, center_y, center_z;
     float radius;
-    vec3 cone_apex;
-    float cone_cutoff;
-    vec3 cone_axis;
-    // TODO its actually signed int - I wanted to avoid another glsl extension
-    float remaining_cone_data;
-    // uint8_t cone_axis_s8;
-    // uint8_t cone_cutoff_s8;
+    uint cone; // snorm8x4, axis xyz and cutoff
+    uint origin_x, origin_y, origin_z; // grid point the positions are offsets from
 };
 
 layout(buffer_reference) readonly buffer MeshletBoundsBuf {
     MeshletBounds bounds[];
 };
 
+// LOD bounds of a cluster of the hierarchy, object space. The parent is the group replacing it.
+struct ClusterLodBounds {
+    vec3 center;
+    float radius;
+    vec3 parent_center;
+    float parent_radius;
+    float error;
+    float parent_error;
+    vec2 _padding;
+};
+
+layout(buffer_reference) readonly buffer ClusterLodBoundsBuf {
+    ClusterLodBounds bounds[];
+};
+
 struct Meshlet {
     uint vertex_offset;
     uint triangle_offset;
@@ -51,19 +74,69 @@ struct MeshletDraw {
     TransformBuf transform; // per-instance model matrix
     MeshletBuf meshlets;
     VertexBuf vertices;
+    MeshletPositionBuf positions;
     VertexIndexBuf vertex_indices;
     TriangleIndexBuf tri_indices;
     MeshletBoundsBuf bounds;
+    JointBuf joints; // unused if joint_count is 0
+    MorphDeltaBuf morph_deltas; // unused if morph_target_count is 0
+    MorphWeightBuf morph_weights;
+    ClusterLodBoundsBuf lod_bounds; // unused if lod_error_scale is 0
     uint meshlets_count;
+    uint material_index; // index into push_constants.materials
+    uint joint_count;
+    uint morph_target_count;
+    float lod_error_scale; // object space error over distance to multiples of the threshold
+    float grid_base_x, grid_base_y, grid_base_z, grid_step; // assets::meshlet_vertex::QuantizationGrid
+    uint _padding;
 };
 
 layout(buffer_reference) readonly buffer MeshletDrawBuf {
     MeshletDraw draws[];
 };
 
+// A vertex of the mesh shader path
+struct Vertex {
+    vec3 position;
+    vec3 normal;
+    vec4 tangent; // w == 0 - no tangent
+    vec4 tex_coord; // xy TEXCOORD_0, zw TEXCOORD_1
+    vec4 color;
+    uvec4 joints;
+    vec4 weights;
+};
+
+vec3 decode_octahedral(uint encoded) {
+    vec2 folded = unpackSnorm2x16(encoded);
+    vec3 v = vec3(folded, 1.0 - abs(folded.x) - abs(folded.y));
+    float fold = max(-v.z, 0.0);
+    v.x += v.x >= 0.0 ? -fold : fold;
+    v.y += v.y >= 0.0 ? -fold : fold;
+    return normalize(v);
+}
+
+// Mirrors assets::meshlet_vertex::unpack
+Vertex unpack_vertex(PackedVertex packed, uvec2 position, MeshletBounds bounds, MeshletDraw draw) {
+    // integer, the same grid point in every meshlet
+    uvec3 point = uvec3(bounds.origin_x, bounds.origin_y, bounds.origin_z)
+        + uvec3(position.x & 0xffffu, position.x >> 16, position.y & 0xffffu);
+    Vertex v;
+    v.position = vec3(draw.grid_base_x, draw.grid_base_y, draw.grid_base_z) + vec3(point) * draw.grid_step;
+    v.normal = decode_octahedral(packed.normal);
+    v.tangent = vec4(decode_octahedral(packed.tangent), unpackSnorm2x16(position.y).y);
+    v.tex_coord = vec4(unpackHalf2x16(packed.tex_coord), unpackHalf2x16(packed.tex_coord1));
+    v.color = unpackUnorm4x8(packed.color);
+    v.joints = uvec4(packed.joints_xy & 0xffffu, packed.joints_xy >> 16, packed.joints_zw & 0xffffu, packed.joints_zw >> 16);
+    v.weights = vec4(unpackUnorm2x16(packed.weights_xy), unpackUnorm2x16(packed.weights_zw));
+    return v;
+}
+
 layout(push_constant) uniform constants
 {
     CameraDataBuf camera;      // view camera: vertex transform
     CameraDataBuf cull_camera; // cull camera: cone/frustum culling
     MeshletDrawBuf meshlet_draws;
+    MaterialBuf materials;
+    PunctualLightBuf lights;
+    uint light_count;
 } push_constants;
@@ -14,9 +14,20 @@ layout(buffer_reference) readonly buffer SkyboxBuf {
     uint current_texture_id;
 };
 
-// Array of per-instance transforms, indexed by instance index.
+struct TraditionalInstance {
+    TransformBuf transform;
+    JointBuf joints; // unused if joint_count is 0
+    MorphDeltaBuf morph_deltas; // unused if morph_target_count is 0
+    MorphWeightBuf morph_weights;
+    uint material_index; // index into push_constants.materials
+    uint joint_count;
+    uint morph_target_count;
+    uint _padding;
+};
+
+// Array of per-instance data, indexed by instance index.
 layout(buffer_reference) readonly buffer TraditionalInstanceBuf {
-    TransformBuf transforms[];
+    TraditionalInstance instances[];
 };
 
 // Per-draw-call offset into the instance buffer, indexed by gl_DrawID.
@@ -33,5 +44,8 @@ layout(push_constant) uniform constants
     SkyboxBuf skybox;
     TraditionalInstanceBuf instances;
     TraditionalOffsetBuf instance_offsets;
+    MaterialBuf materials;
+    PunctualLightBuf lights;
     uint depth_sampler_index;
+    uint light_count;
 } push_constants;
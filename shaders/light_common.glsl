@@ -0,0 +1,52 @@
+// Shading helpers for KHR_lights_punctual lights, include after the descriptor set
+
+// rough blinn-phong approximation of the metallic-roughness model
+float blinn_phong_shininess(float roughness) {
+    return clamp(2.0 / max(pow(roughness, 4.0), 0.0001) - 2.0, 1.0, 1024.0);
+}
+
+// Light reaching `world_pos` and the direction towards the light. Range and cone falloff are
+// the ones recommended by the extension.
+vec3 punctual_light_radiance(PunctualLight light, vec3 world_pos, out vec3 light_dir) {
+    uint light_type = uint(light.direction.w);
+    if (light_type == LIGHT_TYPE_DIRECTIONAL) {
+        light_dir = -light.direction.xyz;
+        return light.color.rgb;
+    }
+
+    vec3 to_light = light.position.xyz - world_pos;
+    float distance_sq = max(dot(to_light, to_light), 0.0001);
+    light_dir = to_light * inversesqrt(distance_sq);
+
+    float attenuation = 1.0 / distance_sq;
+    float range = light.position.w;
+    if (range > 0.0) {
+        // (distance / range)^4
+        float ratio = distance_sq / (range * range);
+        attenuation *= clamp(1.0 - ratio * ratio, 0.0, 1.0);
+    }
+    if (light_type == LIGHT_TYPE_SPOT) {
+        float cos_angle = dot(light.direction.xyz, -light_dir);
+        float cone = clamp((cos_angle - light.spot.x) * light.spot.y, 0.0, 1.0);
+        attenuation *= cone * cone;
+    }
+    return light.color.rgb * attenuation;
+}
+
+// diffuse + specular of every punctual light
+vec3 shade_punctual_lights(PunctualLightBuf lights, uint light_count, vec3 world_pos, vec3 normal,
+                           vec3 view_dir, vec3 diffuse_color, vec3 specular_color, float shininess) {
+    vec3 result = vec3(0.0);
+    for (uint i = 0; i < light_count; i++) {
+        vec3 light_dir;
+        vec3 radiance = punctual_light_radiance(lights.lights[i], world_pos, light_dir);
+        float n_dot_l = max(dot(normal, light_dir), 0.0);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        vec3 halfway_dir = normalize(light_dir + view_dir);
+        float spec = pow(max(dot(normal, halfway_dir), 0.0), shininess);
+        result += radiance * (n_dot_l * diffuse_color + spec * specular_color);
+    }
+    return result;
+}
@@ -0,0 +1,87 @@
+// Material texture sampling, fragment stage only (implicit lod, derivatives).
+// Expects descriptor_set_common.glsl to be included first.
+// uvs holds both UV sets - xy TEXCOORD_0, zw TEXCOORD_1.
+
+// texture order of Material, bit positions in tex_coord_sets
+const uint BASE_COLOR_TEXTURE = 0u;
+const uint METALLIC_ROUGHNESS_TEXTURE = 1u;
+const uint NORMAL_TEXTURE = 2u;
+const uint OCCLUSION_TEXTURE = 3u;
+const uint EMISSIVE_TEXTURE = 4u;
+
+vec2 material_uv(Material material, uint texture_bit, vec4 uvs) {
+    return (material.tex_coord_sets & (1u << texture_bit)) != 0u ? uvs.zw : uvs.xy;
+}
+
+// vertex_color is COLOR_0, white if the primitive has none
+vec4 material_base_color(Material material, vec4 uvs, vec4 vertex_color) {
+    vec4 color = material.base_color_factor * vertex_color;
+    if (material.base_color_texture >= 0) {
+        vec2 uv = material_uv(material, BASE_COLOR_TEXTURE, uvs);
+        color *= texture(textures[nonuniformEXT(material.base_color_texture)], uv);
+    }
+    return color;
+}
+
+// x - occlusion, y - roughness, z - metallic
+vec3 material_orm(Material material, vec4 uvs) {
+    vec3 orm = vec3(1.0, material.roughness_factor, material.metallic_factor);
+    if (material.metallic_roughness_texture >= 0) {
+        vec2 uv = material_uv(material, METALLIC_ROUGHNESS_TEXTURE, uvs);
+        vec4 mr = texture(textures[nonuniformEXT(material.metallic_roughness_texture)], uv);
+        orm.y *= mr.g;
+        orm.z *= mr.b;
+    }
+    if (material.occlusion_texture >= 0) {
+        vec2 uv = material_uv(material, OCCLUSION_TEXTURE, uvs);
+        float occlusion = texture(textures[nonuniformEXT(material.occlusion_texture)], uv).r;
+        orm.x = 1.0 + material.occlusion_strength * (occlusion - 1.0);
+    }
+    return orm;
+}
+
+vec3 material_emissive(Material material, vec4 uvs) {
+    vec3 emissive = material.emissive_factor.rgb;
+    if (material.emissive_texture >= 0) {
+        vec2 uv = material_uv(material, EMISSIVE_TEXTURE, uvs);
+        emissive *= texture(textures[nonuniformEXT(material.emissive_texture)], uv).rgb;
+    }
+    return emissive;
+}
+
+// tangent.w is the bitangent sign, 0 if the vertex has no tangent - then the tangent frame is
+// built from screen space derivatives, http://www.thetenthplanet.de/archives/1180
+vec3 material_normal(Material material, vec3 normal, vec4 tangent, vec3 pos, vec4 uvs) {
+    if (material.normal_texture < 0) {
+        return normal;
+    }
+
+    vec2 uv = material_uv(material, NORMAL_TEXTURE, uvs);
+
+    vec3 tangent_normal = texture(textures[nonuniformEXT(material.normal_texture)], uv).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= material.normal_scale;
+
+    if (tangent.w != 0.0) {
+        vec3 t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+        vec3 b = cross(normal, t) * tangent.w;
+        return normalize(mat3(t, b, normal) * tangent_normal);
+    }
+
+    vec3 dp1 = dFdx(pos);
+    vec3 dp2 = dFdy(pos);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 t = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 b = dp2perp * duv1.y + dp1perp * duv2.y;
+
+    float max_len = max(dot(t, t), dot(b, b));
+    if (max_len == 0.0) {
+        return normal;
+    }
+    float inv_max = inversesqrt(max_len);
+
+    return normalize(mat3(t * inv_max, b * inv_max, normal) * normalize(tangent_normal));
+}
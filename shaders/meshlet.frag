@@ -1,9 +1,44 @@
 #version 450
 
+#extension GL_GOOGLE_include_directive : enable
+#include "descriptor_set_meshlet.glsl"
+#include "material_common.glsl"
+#include "light_common.glsl"
+
 layout(location = 0) out vec4 out_color;
 layout(location = 0) in vec4 in_color;
+layout(location = 1) flat in uint in_material_index;
+layout(location = 2) in vec3 in_world_pos;
+layout(location = 3) in vec3 in_world_normal;
+layout(location = 4) in vec4 in_tex_coord; // xy TEXCOORD_0, zw TEXCOORD_1
+layout(location = 5) in vec4 in_world_tangent;
+layout(location = 6) in vec4 in_vertex_color;
 
 void main()
 {
-  out_color = in_color;
+  Material material = push_constants.materials.materials[in_material_index];
+  vec4 base_color = material_base_color(material, in_tex_coord, in_vertex_color);
+  if (material.alpha_mode == ALPHA_MODE_MASK && base_color.a < material.alpha_cutoff) {
+    discard;
+  }
+
+  vec3 normal = normalize(in_world_normal);
+  if (material.double_sided != 0 && !gl_FrontFacing) {
+    normal = -normal;
+  }
+  normal = material_normal(material, normal, in_world_tangent, in_world_pos, in_tex_coord);
+
+  // simple headlight shading, plus the scene's punctual lights
+  vec3 view_dir = normalize(push_constants.camera.position.xyz - in_world_pos);
+  vec3 orm = material_orm(material, in_tex_coord);
+  float occlusion = orm.x;
+  vec3 shaded = base_color.rgb * (0.2 + 0.8 * max(dot(normal, view_dir), 0.0)) * occlusion;
+
+  float metallic = orm.z;
+  vec3 diffuse_color = base_color.rgb * (1.0 - metallic);
+  vec3 specular_color = mix(vec3(0.04), base_color.rgb, metallic);
+  shaded += shade_punctual_lights(push_constants.lights, push_constants.light_count, in_world_pos, normal,
+                                  view_dir, diffuse_color, specular_color, blinn_phong_shininess(orm.y));
+
+  out_color = in_color * vec4(shaded + material_emissive(material, in_tex_coord), 1.0);
 }
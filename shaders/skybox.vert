@@ -2,10 +2,11 @@
 
 #extension GL_GOOGLE_include_directive : enable
 #include "descriptor_set_traditional.glsl"
+#include "vertex_common.glsl"
 
-layout(location = 0) in vec3 pos;
-layout(location = 1) in vec3 normal;
-layout(location = 2) in vec2 tx;
+layout(location = VERTEX_POSITION_LOCATION) in vec3 pos;
+layout(location = VERTEX_NORMAL_LOCATION) in vec3 normal;
+layout(location = VERTEX_TEX_COORD_LOCATION) in vec2 tx;
 
 layout(location = 0) out vec3 out_uvw;
 
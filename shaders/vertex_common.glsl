@@ -0,0 +1,45 @@
+// Mirrors assets::vertex::Vertex, keep in sync.
+
+#define VERTEX_POSITION_LOCATION 0
+#define VERTEX_NORMAL_LOCATION 1
+#define VERTEX_TEX_COORD_LOCATION 2
+#define VERTEX_TANGENT_LOCATION 3
+#define VERTEX_TEX_COORD1_LOCATION 4
+#define VERTEX_COLOR_LOCATION 5
+#define VERTEX_JOINTS_LOCATION 6
+#define VERTEX_WEIGHTS_LOCATION 7
+
+// Adds the weighted morph target displacements of a vertex, a no-op for target_count 0.
+// Tangent w, the bitangent sign, is never morphed.
+void apply_morph_targets(
+    MorphDeltaBuf deltas,
+    MorphWeightBuf weights,
+    uint target_count,
+    uint vertex_index,
+    inout vec3 position,
+    inout vec3 normal,
+    inout vec3 tangent
+) {
+    for (uint target = 0u; target < target_count; target++) {
+        float weight = weights.weights[target];
+        if (weight == 0.0) {
+            continue;
+        }
+        MorphDelta delta = deltas.deltas[vertex_index * target_count + target];
+        position += weight * vec3(delta.px, delta.py, delta.pz);
+        normal += weight * vec3(delta.nx, delta.ny, delta.nz);
+        tangent += weight * vec3(delta.tx, delta.ty, delta.tz);
+    }
+}
+
+// Weighted sum of the joint matrices, identity for unskinned instances and vertices.
+// Mirrors skin::skin_matrix.
+mat4 skin_matrix(JointBuf joints, uint joint_count, uvec4 joint_indices, vec4 weights) {
+    if (joint_count == 0u || dot(weights, vec4(1.0)) == 0.0) {
+        return mat4(1.0);
+    }
+    return weights.x * joints.joint_matrices[joint_indices.x]
+         + weights.y * joints.joint_matrices[joint_indices.y]
+         + weights.z * joints.joint_matrices[joint_indices.z]
+         + weights.w * joints.joint_matrices[joint_indices.w];
+}
@@ -5,6 +5,7 @@ use winit::keyboard::KeyCode;
 use winit::keyboard::PhysicalKey;
 
 use crate::camera;
+use crate::config;
 use crate::fps_window;
 use crate::gui;
 use crate::gui_scene_node::GuiCameraNode;
@@ -15,6 +16,10 @@ use crate::vkutils;
 const NUM_CAMERAS: usize = 2;
 
 pub struct App {
+    config: config::Config,
+    // where the GUI saves the scene, and how that went
+    scene_file: std::string::String,
+    scene_file_status: std::string::String,
     cameras: [Option<camera::Camera>; NUM_CAMERAS],
     current_view_camera_index: usize,
     current_control_camera_index: usize,
@@ -33,8 +38,15 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(config: config::Config) -> App {
+        let scene_file = config
+            .file
+            .as_ref()
+            .map_or("scene.json".to_string(), |file| file.display().to_string());
         Self {
+            config,
+            scene_file,
+            scene_file_status: std::string::String::new(),
             gui: Option::None,
             current_view_camera_index: 0,
             current_control_camera_index: 0,
@@ -56,8 +68,9 @@ impl App {
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        let window_attrs = winit::window::WindowAttributes::default()
-            .with_inner_size(winit::dpi::PhysicalSize::new(1440, 900));
+        let window_attrs = winit::window::WindowAttributes::default().with_inner_size(
+            winit::dpi::PhysicalSize::new(self.config.window_size[0], self.config.window_size[1]),
+        );
         let window = std::rc::Rc::new(
             event_loop
                 .create_window(window_attrs)
@@ -67,14 +80,19 @@ impl ApplicationHandler for App {
         window.set_cursor_visible(self.cursor_visible);
         let _ = window.set_cursor_grab(winit::window::CursorGrabMode::Confined);
         let mut vkctx = vkutils::context::VulkanContext::new(&window);
-        let renderer = renderer::Renderer::new(&mut vkctx);
+        let renderer = renderer::Renderer::new(&mut vkctx, &self.config);
         for camera in &mut self.cameras {
-            camera
-                .insert(camera::Camera::new(
-                    vkctx.swapchain.extent.width as f32,
-                    vkctx.swapchain.extent.height as f32,
-                ))
-                .look_around(0.0, 0.0);
+            let camera = camera.insert(camera::Camera::new(
+                vkctx.swapchain.extent.width as f32,
+                vkctx.swapchain.extent.height as f32,
+            ));
+            if let Some(position) = self.config.camera_position {
+                camera.set_position(position);
+            }
+            if let Some(direction) = self.config.camera_direction {
+                camera.set_direction(direction);
+            }
+            camera.look_around(0.0, 0.0);
         }
         // TODO I don't quite like this dependency gui->renderer->gui
         // i.e. first gui gets nodes from renderer, and then renderer uses gui to render imgui,
@@ -126,6 +144,10 @@ impl ApplicationHandler for App {
             vkctx.swapchain.acquire_next_image(!0, vk::Fence::null())
         };
 
+        let current_timestamp = std::time::Instant::now();
+        let cpu_duration = current_timestamp - self.previous_frame_timestamp;
+        self.previous_frame_timestamp = current_timestamp;
+
         let (
             shadow_map_render_duration,
             scene_render_duration,
@@ -133,6 +155,13 @@ impl ApplicationHandler for App {
             ui_render_duration,
         ) = {
             let renderer = self.renderer.as_mut().unwrap();
+            // the previous frame finished in device_wait_idle, passes can be re-recorded
+            renderer.update_scenes(self.vkctx.as_ref().unwrap());
+            let loaded_scene_nodes = renderer.update_loading(self.vkctx.as_ref().unwrap());
+            self.gui
+                .as_mut()
+                .unwrap()
+                .add_scene_nodes(loaded_scene_nodes);
             renderer
                 .camera_data_buffer
                 .update_contents(&[camera::GPUCameraData {
@@ -145,6 +174,9 @@ impl ApplicationHandler for App {
                     pos: cull_camera_pos,
                     projview: cull_camera_projview,
                 }]);
+            renderer.update_animations(cpu_duration);
+            let viewport_height = self.vkctx.as_ref().unwrap().swapchain.extent.height as f32;
+            renderer.update_lods(&camera_projview, viewport_height);
 
             if self.frame_number == 0 {
                 (
@@ -158,10 +190,6 @@ impl ApplicationHandler for App {
             }
         };
 
-        let current_timestamp = std::time::Instant::now();
-        let cpu_duration = current_timestamp - self.previous_frame_timestamp;
-        self.previous_frame_timestamp = current_timestamp;
-
         // Take gui so self has no live borrows - is that a smell?
         let mut gui = self.gui.take().unwrap();
         gui.prepare_frame(
@@ -371,6 +399,46 @@ impl GuiSceneNode for App {
             }
         }
 
+        // moves the controlled camera to a glTF camera, works like a menu
+        let presets = &self.renderer.as_ref().unwrap().camera_presets;
+        if !presets.is_empty() {
+            let mut preset_names = vec!["-".to_string()];
+            preset_names.extend(presets.iter().map(|preset| preset.name.clone()));
+            let mut selected = 0;
+            if ui.combo_simple_string("Go to preset", &mut selected, &preset_names) && selected > 0
+            {
+                let extent = self.vkctx.as_ref().unwrap().swapchain.extent;
+                self.cameras[self.current_control_camera_index] =
+                    Some(camera::Camera::from_preset(
+                        extent.width as f32,
+                        extent.height as f32,
+                        &presets[selected - 1],
+                    ));
+            }
+        }
+
+        // everything but the camera the scene file describes, viewed like right now
+        ui.input_text("Scene file", &mut self.scene_file).build();
+        if ui.button("Save scene") {
+            let mut config = self.config.clone();
+            self.renderer.as_ref().unwrap().store_settings(&mut config);
+            let camera = self.cameras[self.current_view_camera_index]
+                .as_ref()
+                .unwrap();
+            config.camera_position = Some(camera.pos().xyz());
+            config.camera_direction = Some(camera.look_direction());
+            let extent = self.vkctx.as_ref().unwrap().swapchain.extent;
+            config.window_size = [extent.width, extent.height];
+            self.scene_file_status = match config.save(std::path::Path::new(&self.scene_file)) {
+                Ok(()) => format!("Saved {}", self.scene_file),
+                Err(err) => err.to_string(),
+            };
+        }
+        if !self.scene_file_status.is_empty() {
+            ui.same_line();
+            ui.text(&self.scene_file_status);
+        }
+
         self.cameras
             .iter_mut()
             .nth(self.current_control_camera_index)
@@ -0,0 +1,293 @@
+use super::gltf_asset::Node;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    // morph target weights
+    Weights,
+}
+
+#[derive(Clone)]
+pub struct Sampler {
+    pub interpolation: Interpolation,
+    // keyframe times in seconds, increasing
+    pub inputs: std::vec::Vec<f32>,
+    // every keyframe value flattened, CUBICSPLINE keyframes are (in-tangent, value, out-tangent)
+    pub outputs: std::vec::Vec<f32>,
+}
+
+#[derive(Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    pub sampler: usize,
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    pub name: Option<std::string::String>,
+    pub channels: std::vec::Vec<Channel>,
+    pub samplers: std::vec::Vec<Sampler>,
+    // last keyframe of any sampler
+    pub duration: f32,
+}
+
+// What an asset is posed with on top of its rest pose
+pub struct Pose<'a> {
+    pub animation: Option<&'a Animation>,
+    pub time: f32,
+    // (node, weights) replacing the rest weights, animated weights still win
+    pub morph_weights: &'a [(usize, std::vec::Vec<f32>)],
+}
+
+impl Sampler {
+    // floats per keyframe value, e.g. 4 for rotations or the morph target count for weights
+    pub fn components(&self) -> usize {
+        let values_per_keyframe = match self.interpolation {
+            Interpolation::CubicSpline => 3,
+            _ => 1,
+        };
+        self.outputs.len() / (self.inputs.len() * values_per_keyframe).max(1)
+    }
+
+    // Value at `time`, clamped to the first/last keyframe. Rotations are normalized quaternions,
+    // LINEAR rotations use slerp - glTF 2.0 Appendix C.
+    pub fn sample(&self, property: Property, time: f32) -> std::vec::Vec<f32> {
+        let components = self.components();
+        let keyframe_count = self.inputs.len();
+        if keyframe_count == 0 || components == 0 {
+            return vec![];
+        }
+
+        let value = |keyframe: usize| -> &[f32] {
+            match self.interpolation {
+                Interpolation::CubicSpline => {
+                    let start = (keyframe * 3 + 1) * components;
+                    &self.outputs[start..start + components]
+                }
+                _ => &self.outputs[keyframe * components..(keyframe + 1) * components],
+            }
+        };
+
+        // first keyframe after `time`
+        let next = self.inputs.partition_point(|&input| input <= time);
+        if next == 0 {
+            return value(0).to_vec();
+        }
+        if next == keyframe_count {
+            return value(keyframe_count - 1).to_vec();
+        }
+
+        let previous = next - 1;
+        let delta = self.inputs[next] - self.inputs[previous];
+        let t = if delta > 0.0 {
+            (time - self.inputs[previous]) / delta
+        } else {
+            0.0
+        };
+
+        let sampled = match self.interpolation {
+            Interpolation::Step => value(previous).to_vec(),
+            Interpolation::Linear if property == Property::Rotation => {
+                slerp(value(previous), value(next), t).to_vec()
+            }
+            Interpolation::Linear => std::iter::zip(value(previous), value(next))
+                .map(|(a, b)| a + (b - a) * t)
+                .collect(),
+            Interpolation::CubicSpline => {
+                let out_tangent = (previous * 3 + 2) * components;
+                let in_tangent = next * 3 * components;
+                let (t2, t3) = (t * t, t * t * t);
+                (0..components)
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * value(previous)[i]
+                            + delta * (t3 - 2.0 * t2 + t) * self.outputs[out_tangent + i]
+                            + (-2.0 * t3 + 3.0 * t2) * value(next)[i]
+                            + delta * (t3 - t2) * self.outputs[in_tangent + i]
+                    })
+                    .collect()
+            }
+        };
+
+        if property == Property::Rotation {
+            normalize(&sampled)
+        } else {
+            sampled
+        }
+    }
+}
+
+impl Animation {
+    // Writes the pose at `time` into the targeted nodes, untargeted properties are left alone
+    pub fn apply(&self, nodes: &mut [Node], time: f32) {
+        for channel in &self.channels {
+            let value = self.samplers[channel.sampler].sample(channel.property, time);
+            let node = &mut nodes[channel.node];
+            match channel.property {
+                Property::Translation => node.translation = glm::make_vec3(&value),
+                Property::Rotation => {
+                    node.rotation = glm::quat(value[0], value[1], value[2], value[3])
+                }
+                Property::Scale => node.scale = glm::make_vec3(&value),
+                // the weight count is fixed by the mesh's morph targets
+                Property::Weights => {
+                    for (weight, value) in std::iter::zip(&mut node.weights, value) {
+                        *weight = value;
+                    }
+                }
+            }
+            node.update_matrix();
+        }
+    }
+}
+
+fn normalize(q: &[f32]) -> std::vec::Vec<f32> {
+    let length = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length > 0.0 {
+        q.iter().map(|c| c / length).collect()
+    } else {
+        vec![0.0, 0.0, 0.0, 1.0]
+    }
+}
+
+// Shortest path, falls back to lerp for nearly identical rotations where the slerp weights blow up
+fn slerp(a: &[f32], b: &[f32], t: f32) -> [f32; 4] {
+    let mut dot: f32 = std::iter::zip(a, b).map(|(a, b)| a * b).sum();
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+    dot *= sign;
+
+    let (wa, wb) = if dot > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let theta = dot.acos();
+        let sin_theta = theta.sin();
+        (
+            ((1.0 - t) * theta).sin() / sin_theta,
+            (t * theta).sin() / sin_theta,
+        )
+    };
+
+    std::array::from_fn(|i| wa * a[i] + wb * sign * b[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(interpolation: Interpolation, inputs: &[f32], outputs: &[f32]) -> Sampler {
+        Sampler {
+            interpolation,
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        }
+    }
+
+    fn assert_near(sampled: &[f32], expected: &[f32]) {
+        assert_eq!(sampled.len(), expected.len());
+        for (a, b) in std::iter::zip(sampled, expected) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", sampled, expected);
+        }
+    }
+
+    // about +Z, as glTF's x, y, z, w
+    fn rotation_z(angle: f32) -> [f32; 4] {
+        [0.0, 0.0, (angle / 2.0).sin(), (angle / 2.0).cos()]
+    }
+
+    // 0 to 4 over 2 seconds
+    fn sampler_line() -> Sampler {
+        sampler(
+            Interpolation::CubicSpline,
+            &[0.0, 2.0],
+            &[2.0, 0.0, 2.0, 2.0, 4.0, 2.0],
+        )
+    }
+
+    #[test]
+    fn step_holds_the_previous_keyframe() {
+        let sampler = sampler(Interpolation::Step, &[0.0, 1.0, 2.0], &[0.0, 10.0, 20.0]);
+        assert_eq!(sampler.sample(Property::Weights, 0.5), [0.0]);
+        assert_eq!(sampler.sample(Property::Weights, 1.0), [10.0]);
+        assert_eq!(sampler.sample(Property::Weights, 1.99), [10.0]);
+    }
+
+    #[test]
+    fn linear_interpolates_every_component() {
+        let sampler = sampler(
+            Interpolation::Linear,
+            &[1.0, 3.0],
+            &[0.0, 2.0, -4.0, 4.0, 2.0, 4.0],
+        );
+        assert_eq!(sampler.components(), 3);
+        assert_near(
+            &sampler.sample(Property::Translation, 1.5),
+            &[1.0, 2.0, -2.0],
+        );
+    }
+
+    #[test]
+    fn linear_rotations_slerp() {
+        let outputs = [rotation_z(0.0), rotation_z(std::f32::consts::FRAC_PI_2)].concat();
+        let sampler = sampler(Interpolation::Linear, &[0.0, 1.0], &outputs);
+        // constant angular speed, a lerp would be at 0.5 only halfway through
+        assert_near(
+            &sampler.sample(Property::Rotation, 0.25),
+            &rotation_z(std::f32::consts::FRAC_PI_8),
+        );
+    }
+
+    #[test]
+    fn linear_rotations_take_the_shortest_path() {
+        // the same rotation as +90 degrees, on the other side of the hypersphere
+        let flipped = rotation_z(std::f32::consts::FRAC_PI_2).map(|c| -c);
+        let outputs = [rotation_z(0.0), flipped].concat();
+        let sampler = sampler(Interpolation::Linear, &[0.0, 1.0], &outputs);
+        // not the long way around through 225 degrees
+        assert_near(
+            &sampler.sample(Property::Rotation, 0.5),
+            &rotation_z(std::f32::consts::FRAC_PI_4),
+        );
+    }
+
+    #[test]
+    fn cubic_spline_tangents_scale_with_keyframe_spacing() {
+        // (in-tangent, value, out-tangent) of two keyframes 2 seconds apart
+        let sampler = sampler(
+            Interpolation::CubicSpline,
+            &[1.0, 3.0],
+            &[0.0, 0.0, 1.0, 0.0, 0.0, 0.0],
+        );
+        assert_eq!(sampler.components(), 1);
+        // h10(0.5) * delta * out-tangent = 0.125 * 2 * 1
+        assert_near(&sampler.sample(Property::Translation, 2.0), &[0.25]);
+
+        // tangents matching the slope give a straight line
+        let line = sampler_line();
+        for (time, expected) in [(0.5, 1.0), (1.0, 2.0), (1.5, 3.0)] {
+            assert_near(&line.sample(Property::Translation, time), &[expected]);
+        }
+    }
+
+    #[test]
+    fn times_outside_the_keyframes_clamp() {
+        let linear = sampler(Interpolation::Linear, &[1.0, 2.0], &[5.0, 7.0]);
+        assert_eq!(linear.sample(Property::Translation, 0.0), [5.0]);
+        assert_eq!(linear.sample(Property::Translation, -1.0), [5.0]);
+        assert_eq!(linear.sample(Property::Translation, 2.0), [7.0]);
+        assert_eq!(linear.sample(Property::Translation, 10.0), [7.0]);
+
+        // the values, not the tangents around them
+        let cubic = sampler_line();
+        assert_eq!(cubic.sample(Property::Translation, -1.0), [0.0]);
+        assert_eq!(cubic.sample(Property::Translation, 3.0), [4.0]);
+    }
+}
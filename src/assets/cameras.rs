@@ -0,0 +1,76 @@
+use super::gltf_asset::{Node, Scene};
+use super::scene_nodes::global_transforms;
+use crate::camera::{self, projection};
+
+// glTF camera, placed in the world by the nodes referencing it
+#[derive(Clone)]
+pub struct Camera {
+    pub name: Option<std::string::String>,
+    pub projection: projection::Projection,
+}
+
+impl Camera {
+    pub fn from_gltf(camera: &gltf::Camera) -> Self {
+        let projection = match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => {
+                // infinite projections get the far plane of the default camera, the aspect
+                // ratio is replaced by the viewport's when a preset is applied
+                projection::Projection::Perspective(projection::perspective::Properties::new(
+                    perspective.aspect_ratio().unwrap_or(1.0),
+                    1.0,
+                    perspective.yfov().to_degrees(),
+                    perspective.znear(),
+                    perspective.zfar().unwrap_or(500.0),
+                ))
+            }
+            gltf::camera::Projection::Orthographic(orthographic) => {
+                projection::Projection::Orthographic(
+                    projection::orthtographic::Properties::from_extents(
+                        orthographic.xmag(),
+                        orthographic.ymag(),
+                        orthographic.znear(),
+                        orthographic.zfar(),
+                    ),
+                )
+            }
+        };
+
+        Self {
+            name: camera.name().map(|s| s.to_string()),
+            projection,
+        }
+    }
+}
+
+// One preset per camera node of the scene, glTF cameras look down their local -Z
+pub fn scene_presets(
+    nodes: &[Node],
+    cameras: &[Camera],
+    scene: &Scene,
+    root_transform: &glm::Mat4,
+) -> std::vec::Vec<camera::Preset> {
+    let globals = global_transforms(nodes, root_transform);
+    let mut presets = vec![];
+    let mut stack: std::vec::Vec<usize> = scene.nodes.iter().rev().copied().collect();
+    while let Some(node_index) = stack.pop() {
+        let node = &nodes[node_index];
+        if let Some(camera_index) = node.camera_index {
+            let camera = &cameras[camera_index];
+            let transform = &globals[node_index];
+            presets.push(camera::Preset {
+                name: camera
+                    .name
+                    .clone()
+                    .or_else(|| node._name.clone())
+                    .unwrap_or_else(|| format!("Camera {}", camera_index)),
+                position: transform.column(3).xyz(),
+                direction: (transform * glm::vec4(0.0, 0.0, -1.0, 0.0))
+                    .xyz()
+                    .normalize(),
+                projection: camera.projection.clone(),
+            });
+        }
+        stack.extend(node.children.iter().rev());
+    }
+    presets
+}
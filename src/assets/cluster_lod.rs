@@ -0,0 +1,308 @@
+// Cluster hierarchy for continuous LOD on the meshlet path. Meshlets of the full resolution mesh
+// are grouped with their neighbors, each group is simplified to about half with its border
+// locked and split into meshlets again, and so on until nothing simplifies any further. Every
+// cluster knows the error it was simplified with and the error of the group its parents replace
+// it with, both only grow towards the roots, so the task shader can pick a consistent cut per
+// view: a cluster is drawn when its own error is small enough on screen and its parents' isn't.
+// Groups swap all their clusters at once along locked borders, the cut never cracks.
+
+use super::lod::BoundingSphere;
+use super::meshlet::{finish_meshlets, MeshletBounds, MESHLET_PARAMS};
+use super::vertex::Vertex;
+
+// clusters simplified together, more locks fewer borders but simplifies coarser steps
+const GROUP_SIZE: usize = 4;
+// a group that keeps more of its triangles than this is done, its clusters become roots
+const MIN_REDUCTION: f32 = 0.85;
+
+// LOD bounds of a cluster, parallel to its MeshletBounds. Object space.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ClusterLodBounds {
+    center: glm::Vec3,
+    radius: f32,
+    parent_center: glm::Vec3,
+    parent_radius: f32,
+    // 0 for clusters of the full resolution mesh
+    error: f32,
+    // f32::MAX for roots, nothing replaces them
+    parent_error: f32,
+    _padding: [f32; 2],
+}
+
+struct Cluster {
+    // triangle list indexing the primitive's vertices
+    indices: std::vec::Vec<u32>,
+    // shared by every cluster split from the same group
+    bounds: BoundingSphere,
+    error: f32,
+    // of the group replacing this cluster, None for roots
+    parent: Option<(BoundingSphere, f32)>,
+}
+
+// Every cluster of every level in one meshlet list, with culling and LOD bounds
+pub fn build_cluster_hierarchy(
+    vertices: &[Vertex],
+    indices: &[u32],
+) -> (
+    meshopt::Meshlets,
+    std::vec::Vec<MeshletBounds>,
+    std::vec::Vec<ClusterLodBounds>,
+) {
+    let adapter = meshopt::VertexDataAdapter::new(
+        meshopt::typed_to_bytes(vertices),
+        Vertex::STRIDE,
+        std::mem::offset_of!(Vertex, position),
+    )
+    .expect("Failed to create vertex adapter");
+
+    let mut clusters: std::vec::Vec<Cluster> = split(&adapter, indices)
+        .into_iter()
+        .map(|indices| {
+            let bounds = meshopt::compute_cluster_bounds(&indices, &adapter);
+            Cluster {
+                indices,
+                bounds: BoundingSphere {
+                    center: glm::make_vec3(&bounds.center),
+                    radius: bounds.radius,
+                },
+                error: 0.0,
+                parent: None,
+            }
+        })
+        .collect();
+
+    let mut level: std::vec::Vec<usize> = (0..clusters.len()).collect();
+    while level.len() > 1 {
+        let mut next_level = vec![];
+        for group in group_clusters(&clusters, &level) {
+            if group.len() == 1 {
+                continue;
+            }
+            let merged: std::vec::Vec<u32> = group
+                .iter()
+                .flat_map(|&cluster| clusters[cluster].indices.iter().copied())
+                .collect();
+            let mut simplify_error = 0.0;
+            let simplified = meshopt::simplify(
+                &merged,
+                &adapter,
+                merged.len() / 6 * 3,
+                f32::MAX,
+                meshopt::SimplifyOptions::LockBorder
+                    | meshopt::SimplifyOptions::Sparse
+                    | meshopt::SimplifyOptions::ErrorAbsolute,
+                Some(&mut simplify_error),
+            );
+            if simplified.len() as f32 > merged.len() as f32 * MIN_REDUCTION {
+                continue;
+            }
+
+            let bounds = enclosing_sphere(group.iter().map(|&cluster| &clusters[cluster].bounds));
+            // on top of what the children already lost, so errors grow towards the roots
+            let error = simplify_error
+                + group
+                    .iter()
+                    .map(|&cluster| clusters[cluster].error)
+                    .fold(0.0, f32::max);
+            for &cluster in &group {
+                clusters[cluster].parent = Some((bounds, error));
+            }
+            for indices in split(&adapter, &simplified) {
+                next_level.push(clusters.len());
+                clusters.push(Cluster {
+                    indices,
+                    bounds,
+                    error,
+                    parent: None,
+                });
+            }
+        }
+        level = next_level;
+    }
+
+    let mut meshlets = meshopt::Meshlets {
+        meshlets: vec![],
+        vertices: vec![],
+        triangles: vec![],
+    };
+    let mut lod_bounds = vec![];
+    for cluster in &clusters {
+        push_meshlet(&mut meshlets, &cluster.indices);
+        let (parent_bounds, parent_error) = cluster.parent.unwrap_or((cluster.bounds, f32::MAX));
+        lod_bounds.push(ClusterLodBounds {
+            center: cluster.bounds.center,
+            radius: cluster.bounds.radius,
+            parent_center: parent_bounds.center,
+            parent_radius: parent_bounds.radius,
+            error: cluster.error,
+            parent_error,
+            _padding: [0.0; 2],
+        });
+    }
+    let (meshlets, bounds) = finish_meshlets(&adapter, meshlets);
+    (meshlets, bounds, lod_bounds)
+}
+
+// Meshlet-sized triangle lists
+fn split(
+    adapter: &meshopt::VertexDataAdapter,
+    indices: &[u32],
+) -> std::vec::Vec<std::vec::Vec<u32>> {
+    let meshlets = meshopt::build_meshlets(
+        indices,
+        adapter,
+        MESHLET_PARAMS.max_vertices as usize,
+        MESHLET_PARAMS.max_triangles as usize,
+        MESHLET_PARAMS.cone_weight,
+    );
+    meshlets
+        .iter()
+        .map(|meshlet| {
+            meshlet
+                .triangles
+                .iter()
+                .map(|&index| meshlet.vertices[index as usize])
+                .collect()
+        })
+        .collect()
+}
+
+// Groups of up to GROUP_SIZE clusters of a level, each grown from a seed by the cluster sharing
+// the most vertices with it so far. Shared vertices are borders the group's simplification may
+// collapse. Clusters without ungrouped neighbors end up in smaller groups.
+fn group_clusters(clusters: &[Cluster], level: &[usize]) -> std::vec::Vec<std::vec::Vec<usize>> {
+    // vertex to the positions in `level` of the clusters using it
+    let mut users: std::collections::HashMap<u32, std::vec::Vec<usize>> =
+        std::collections::HashMap::new();
+    for (position, &cluster) in level.iter().enumerate() {
+        let mut vertices = clusters[cluster].indices.clone();
+        vertices.sort_unstable();
+        vertices.dedup();
+        for vertex in vertices {
+            users.entry(vertex).or_default().push(position);
+        }
+    }
+
+    let mut grouped = vec![false; level.len()];
+    let mut groups = vec![];
+    for seed in 0..level.len() {
+        if grouped[seed] {
+            continue;
+        }
+        grouped[seed] = true;
+        let mut group = vec![seed];
+        // candidate to the vertices it shares with the group
+        let mut shared: std::collections::HashMap<usize, usize> = std::collections::HashMap::new();
+        let add_neighbors = |position: usize, shared: &mut std::collections::HashMap<_, _>| {
+            let mut vertices = clusters[level[position]].indices.clone();
+            vertices.sort_unstable();
+            vertices.dedup();
+            for vertex in vertices {
+                for &user in &users[&vertex] {
+                    *shared.entry(user).or_insert(0) += 1;
+                }
+            }
+        };
+        add_neighbors(seed, &mut shared);
+
+        while group.len() < GROUP_SIZE {
+            // ties go to the earlier cluster, the hierarchy doesn't depend on hash order
+            let best = shared
+                .iter()
+                .filter(|(candidate, _)| !grouped[**candidate])
+                .max_by_key(|(candidate, count)| (**count, std::cmp::Reverse(**candidate)))
+                .map(|(candidate, _)| *candidate);
+            let Some(best) = best else {
+                break;
+            };
+            grouped[best] = true;
+            group.push(best);
+            add_neighbors(best, &mut shared);
+        }
+        groups.push(group.into_iter().map(|position| level[position]).collect());
+    }
+    groups
+}
+
+fn enclosing_sphere<'a>(
+    spheres: impl Iterator<Item = &'a BoundingSphere> + Clone,
+) -> BoundingSphere {
+    let (min, max) = spheres.clone().fold(
+        (
+            glm::vec3(f32::MAX, f32::MAX, f32::MAX),
+            glm::vec3(f32::MIN, f32::MIN, f32::MIN),
+        ),
+        |(min, max), sphere| {
+            let extent = glm::vec3(sphere.radius, sphere.radius, sphere.radius);
+            (
+                glm::min2(&min, &(sphere.center - extent)),
+                glm::max2(&max, &(sphere.center + extent)),
+            )
+        },
+    );
+    let center = (min + max) * 0.5;
+    let radius = spheres
+        .map(|sphere| glm::distance(&center, &sphere.center) + sphere.radius)
+        .fold(0.0, f32::max);
+    BoundingSphere { center, radius }
+}
+
+// Appends a triangle list that fits MESHLET_PARAMS as one meshlet
+fn push_meshlet(meshlets: &mut meshopt::Meshlets, indices: &[u32]) {
+    let vertex_offset = meshlets.vertices.len();
+    let triangle_offset = meshlets.triangles.len();
+    for &index in indices {
+        let local = match meshlets.vertices[vertex_offset..]
+            .iter()
+            .position(|&vertex| vertex == index)
+        {
+            Some(local) => local,
+            None => {
+                meshlets.vertices.push(index);
+                meshlets.vertices.len() - vertex_offset - 1
+            }
+        };
+        meshlets.triangles.push(local as u8);
+    }
+    meshlets.meshlets.push(meshopt::ffi::meshopt_Meshlet {
+        vertex_offset: vertex_offset as u32,
+        triangle_offset: triangle_offset as u32,
+        vertex_count: (meshlets.vertices.len() - vertex_offset) as u32,
+        triangle_count: (indices.len() / 3) as u32,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::wavy_grid;
+    use super::*;
+
+    #[test]
+    fn parents_bound_their_children() {
+        let (vertices, indices) = wavy_grid(48);
+        let (meshlets, bounds, lod_bounds) = build_cluster_hierarchy(&vertices, &indices);
+        assert_eq!(lod_bounds.len(), bounds.len());
+        assert!(meshlets.meshlets.len() >= bounds.len());
+        // more than the full resolution level
+        let parented = lod_bounds
+            .iter()
+            .filter(|lod| lod.parent_error < f32::MAX)
+            .count();
+        assert!(parented > 0 && parented < lod_bounds.len());
+        assert!(lod_bounds.iter().any(|lod| lod.error > 0.0));
+
+        for lod in &lod_bounds {
+            assert!(lod.error <= lod.parent_error);
+            if lod.parent_error < f32::MAX {
+                let reach = glm::distance(&lod.center, &lod.parent_center) + lod.radius;
+                assert!(
+                    reach <= lod.parent_radius * (1.0 + 1e-5),
+                    "{} > {}",
+                    reach,
+                    lod.parent_radius
+                );
+            }
+        }
+    }
+}
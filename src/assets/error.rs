@@ -0,0 +1,172 @@
+use gltf::accessor::DataType;
+
+#[derive(Debug)]
+pub enum AccessorError {
+    // accessor (or its sparse indices/values) reaches past the end of its buffer
+    OutOfBounds {
+        buffer: usize,
+        end: usize,
+        buffer_length: usize,
+    },
+    // accessor (or its sparse indices/values) reaches past the end of its buffer view
+    OutsideView {
+        view: usize,
+        end: usize,
+        view_length: usize,
+    },
+    SparseIndexOutOfRange {
+        index: usize,
+        count: usize,
+    },
+    // index buffers must be unsigned integers
+    InvalidIndexType(DataType),
+    IndexOutOfRange {
+        index: usize,
+        vertex_count: usize,
+    },
+}
+
+impl std::fmt::Display for AccessorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessorError::OutOfBounds {
+                buffer,
+                end,
+                buffer_length,
+            } => write!(
+                f,
+                "reads up to byte {} of buffer {} which is {} bytes long",
+                end, buffer, buffer_length
+            ),
+            AccessorError::OutsideView {
+                view,
+                end,
+                view_length,
+            } => write!(
+                f,
+                "reads up to byte {} of buffer view {} which is {} bytes long",
+                end, view, view_length
+            ),
+            AccessorError::SparseIndexOutOfRange { index, count } => write!(
+                f,
+                "sparse index {} out of range, accessor has {} elements",
+                index, count
+            ),
+            AccessorError::InvalidIndexType(data_type) => {
+                write!(f, "{:?} is not a valid index type", data_type)
+            }
+            AccessorError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "index {} out of range, primitive has {} vertices",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Open {
+        path: std::path::PathBuf,
+        source: gltf::Error,
+    },
+    Buffers(gltf::Error),
+    Image {
+        image: usize,
+        reason: std::string::String,
+    },
+    MissingAttribute {
+        mesh: usize,
+        primitive: usize,
+        semantic: gltf::Semantic,
+    },
+    AttributeCountMismatch {
+        mesh: usize,
+        primitive: usize,
+        semantic: gltf::Semantic,
+        expected: usize,
+        found: usize,
+    },
+    Accessor {
+        mesh: usize,
+        primitive: usize,
+        accessor: usize,
+        source: AccessorError,
+    },
+    Skin {
+        skin: usize,
+        reason: std::string::String,
+    },
+    Animation {
+        animation: usize,
+        reason: std::string::String,
+    },
+    // the bindless texture array has no free range this long
+    TextureSlots {
+        requested: usize,
+    },
+}
+
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::Open { path, source } => {
+                write!(f, "failed to open {}: {}", path.display(), source)
+            }
+            AssetError::Buffers(source) => write!(f, "failed to import buffers: {}", source),
+            AssetError::Image { image, reason } => write!(f, "image {}: {}", image, reason),
+            AssetError::MissingAttribute {
+                mesh,
+                primitive,
+                semantic,
+            } => write!(
+                f,
+                "mesh {} primitive {}: missing {:?} attribute",
+                mesh, primitive, semantic
+            ),
+            AssetError::AttributeCountMismatch {
+                mesh,
+                primitive,
+                semantic,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mesh {} primitive {}: {:?} has {} elements, expected {}",
+                mesh, primitive, semantic, found, expected
+            ),
+            AssetError::Accessor {
+                mesh,
+                primitive,
+                accessor,
+                source,
+            } => write!(
+                f,
+                "mesh {} primitive {} accessor {}: {}",
+                mesh, primitive, accessor, source
+            ),
+            AssetError::Skin { skin, reason } => write!(f, "skin {}: {}", skin, reason),
+            AssetError::Animation { animation, reason } => {
+                write!(f, "animation {}: {}", animation, reason)
+            }
+            AssetError::TextureSlots { requested } => write!(
+                f,
+                "no room for {} textures in the bindless texture array",
+                requested
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Open { source, .. } => Some(source),
+            AssetError::Buffers(source) => Some(source),
+            _ => None,
+        }
+    }
+}
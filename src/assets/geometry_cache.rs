@@ -0,0 +1,249 @@
+// On-disk cache of the processed primitives of a glTF asset, so unchanged assets skip normal and
+// tangent generation, welding, optimization and simplification on startup. The vertex buffers are
+// stored welded and optimized, i.e. with the vertex remap applied to them and to the morph deltas.
+// The traditional path concatenates them into its combined buffers as they are.
+//
+// One file per source hash next to the meshlet cache, after the header meshlet_cache::open checks:
+//   mesh count: u32
+//   per mesh: primitive count: u32
+//     per primitive: vertex count: u32 and the vertices, bounding sphere center and radius: 4 f32,
+//     index size (2 or 4): u32, index count: u32 and the indices, morph target count: u32 and the vertex-major morph deltas, LOD level count:
+//     u32, per level its error: f32, index count: u32 and the indices
+
+use super::gltf_asset::{IndexBufferType, Mesh, Primitive};
+use super::lod::{BoundingSphere, Lod};
+use super::meshlet_cache::{self, invalid_data, Reader};
+use std::io::prelude::*;
+
+const MAGIC: [u8; 4] = *b"GRGC";
+
+pub fn path(dir: &std::path::Path, source_hash: u64) -> std::path::PathBuf {
+    meshlet_cache::cache_path(dir, source_hash, "geometry")
+}
+
+// Primitives per mesh, `primitive_counts` has the primitive count of every mesh of the source.
+// Material indices are left at 0, the loader assigns them.
+pub fn load(
+    path: &std::path::Path,
+    source_hash: u64,
+    primitive_counts: &[usize],
+) -> std::io::Result<std::vec::Vec<std::vec::Vec<Primitive>>> {
+    let mut reader = meshlet_cache::open(path, MAGIC, source_hash)?;
+    if reader.read_u32()? as usize != primitive_counts.len() {
+        return Err(invalid_data("mesh count mismatch"));
+    }
+    let mut meshes = vec![];
+    for &primitive_count in primitive_counts {
+        if reader.read_u32()? as usize != primitive_count {
+            return Err(invalid_data("primitive count mismatch"));
+        }
+        let mut primitives = vec![];
+        for _ in 0..primitive_count {
+            primitives.push(read_primitive(&mut reader)?);
+        }
+        meshes.push(primitives);
+    }
+    Ok(meshes)
+}
+
+pub fn save(path: &std::path::Path, source_hash: u64, meshes: &[Mesh]) -> std::io::Result<()> {
+    meshlet_cache::write_atomically(path, MAGIC, source_hash, |writer| {
+        writer.write_all(&(meshes.len() as u32).to_ne_bytes())?;
+        for mesh in meshes {
+            writer.write_all(&(mesh.primitives.len() as u32).to_ne_bytes())?;
+            for primitive in &mesh.primitives {
+                write_primitive(writer, primitive)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn read_primitive(reader: &mut Reader) -> std::io::Result<Primitive> {
+    let vertex_count = reader.read_u32()? as usize;
+    let vertex_buffer = reader.read_array(vertex_count)?;
+    let sphere = reader.read_array::<f32>(4)?;
+    let index_size = reader.read_u32()?;
+    let index_count = reader.read_u32()? as usize;
+    let index_buffer = match index_size {
+        2 => IndexBufferType::U16(reader.read_array(index_count)?),
+        4 => IndexBufferType::U32(reader.read_array(index_count)?),
+        _ => return Err(invalid_data(format!("{}-byte indices", index_size))),
+    };
+    let morph_target_count = reader.read_u32()? as usize;
+    let morph_deltas = reader.read_array(vertex_count * morph_target_count)?;
+    let lod_count = reader.read_u32()? as usize;
+    let mut lods = vec![];
+    for _ in 0..lod_count {
+        let error = reader.read_array::<f32>(1)?[0];
+        let index_count = reader.read_u32()? as usize;
+        lods.push(Lod {
+            indices: reader.read_array(index_count)?,
+            error,
+        });
+    }
+
+    // the index buffers are drawn and turned into meshlets as they are
+    let max_index = match &index_buffer {
+        IndexBufferType::U16(items) => items.iter().map(|&i| i as u32).max(),
+        IndexBufferType::U32(items) => items.iter().copied().max(),
+    };
+    let lod_max_index = lods
+        .iter()
+        .filter_map(|lod| lod.indices.iter().copied().max());
+    if max_index
+        .into_iter()
+        .chain(lod_max_index)
+        .any(|index| index as usize >= vertex_count)
+    {
+        return Err(invalid_data("indices reach out of bounds"));
+    }
+
+    Ok(Primitive {
+        bounding_sphere: BoundingSphere {
+            center: glm::make_vec3(&sphere[..3]),
+            radius: sphere[3],
+        },
+        vertex_buffer,
+        index_buffer,
+        material_index: 0,
+        morph_target_count,
+        morph_deltas,
+        lods,
+    })
+}
+
+fn write_primitive(writer: &mut impl Write, primitive: &Primitive) -> std::io::Result<()> {
+    writer.write_all(&(primitive.vertex_buffer.len() as u32).to_ne_bytes())?;
+    writer.write_all(meshopt::typed_to_bytes(&primitive.vertex_buffer))?;
+    let sphere = &primitive.bounding_sphere;
+    for value in [
+        sphere.center.x,
+        sphere.center.y,
+        sphere.center.z,
+        sphere.radius,
+    ] {
+        writer.write_all(&value.to_ne_bytes())?;
+    }
+    let (index_size, index_count, indices) = match &primitive.index_buffer {
+        IndexBufferType::U16(items) => (2u32, items.len(), meshopt::typed_to_bytes(items)),
+        IndexBufferType::U32(items) => (4u32, items.len(), meshopt::typed_to_bytes(items)),
+    };
+    writer.write_all(&index_size.to_ne_bytes())?;
+    writer.write_all(&(index_count as u32).to_ne_bytes())?;
+    writer.write_all(indices)?;
+    writer.write_all(&(primitive.morph_target_count as u32).to_ne_bytes())?;
+    writer.write_all(meshopt::typed_to_bytes(&primitive.morph_deltas))?;
+    writer.write_all(&(primitive.lods.len() as u32).to_ne_bytes())?;
+    for lod in &primitive.lods {
+        writer.write_all(&lod.error.to_ne_bytes())?;
+        writer.write_all(&(lod.indices.len() as u32).to_ne_bytes())?;
+        writer.write_all(meshopt::typed_to_bytes(&lod.indices))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::morph::MorphDelta;
+    use super::super::testing::temp_file;
+    use super::super::vertex::Vertex;
+    use super::*;
+
+    const SOURCE_HASH: u64 = 0xfedc_ba98_7654_3210;
+
+    // a morphed quad with a one triangle LOD, and an empty primitive
+    fn meshes() -> std::vec::Vec<Mesh> {
+        let vertex_buffer: std::vec::Vec<Vertex> = (0..4)
+            .map(|i| Vertex {
+                position: [(i & 1) as f32, (i >> 1) as f32, 0.0],
+                normal: [0.0, 0.0, 1.0],
+                joints: [i, 0, 0, 0],
+                ..Default::default()
+            })
+            .collect();
+        let morph_deltas = (0..8)
+            .map(|i| MorphDelta {
+                position: [i as f32, 0.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        let quad = Primitive {
+            bounding_sphere: BoundingSphere::of(&vertex_buffer),
+            vertex_buffer,
+            index_buffer: IndexBufferType::U16(vec![0, 1, 2, 2, 1, 3]),
+            material_index: 0,
+            morph_target_count: 2,
+            morph_deltas,
+            lods: vec![Lod {
+                indices: vec![0, 1, 3],
+                error: 0.5,
+            }],
+        };
+        let empty = Primitive {
+            vertex_buffer: vec![],
+            index_buffer: IndexBufferType::U32(vec![]),
+            material_index: 0,
+            morph_target_count: 0,
+            morph_deltas: vec![],
+            lods: vec![],
+            bounding_sphere: BoundingSphere::of(&[]),
+        };
+        vec![
+            Mesh {
+                _name: None,
+                primitives: vec![quad],
+            },
+            Mesh {
+                _name: None,
+                primitives: vec![empty],
+            },
+        ]
+    }
+
+    fn primitive_bytes(primitive: &Primitive) -> std::vec::Vec<u8> {
+        let mut bytes = vec![];
+        write_primitive(&mut bytes, primitive).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn saved_primitives_load_the_same() {
+        let meshes = meshes();
+        let (_dir, path) = temp_file("round_trip.geometry");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        let loaded = load(&path, SOURCE_HASH, &[1, 1]).unwrap();
+
+        for (mesh, loaded) in std::iter::zip(&meshes, &loaded) {
+            assert_eq!(loaded.len(), 1);
+            assert_eq!(
+                primitive_bytes(&loaded[0]),
+                primitive_bytes(&mesh.primitives[0])
+            );
+        }
+        assert!(matches!(loaded[0][0].index_buffer, IndexBufferType::U16(_)));
+        assert!(load(&path, SOURCE_HASH + 1, &[1, 1]).is_err());
+        assert!(load(&path, SOURCE_HASH, &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn indices_past_the_vertices_are_rejected() {
+        let mut meshes = meshes();
+        meshes[0].primitives[0].lods[0].indices[2] = 4;
+        let (_dir, path) = temp_file("corrupt_lod.geometry");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        let loaded = load(&path, SOURCE_HASH, &[1, 1]);
+        assert_eq!(
+            loaded.err().map(|err| err.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+
+        // and cut anywhere
+        save(&path, SOURCE_HASH, &self::meshes()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        for length in 0..bytes.len() {
+            std::fs::write(&path, &bytes[..length]).unwrap();
+            assert!(load(&path, SOURCE_HASH, &[1, 1]).is_err(), "{}", length);
+        }
+    }
+}
@@ -1,3 +1,20 @@
+use super::animation::{self, Animation};
+use super::cameras::Camera;
+use super::error::{AccessorError, AssetError};
+use super::geometry_cache;
+use super::lights::Light;
+use super::lod::{self, BoundingSphere, Lod, LodLevel};
+use super::material::Material;
+use super::meshlet_cache;
+use super::morph::{self, MorphDelta};
+use super::normals::{self, NormalGeneration};
+use super::optimize::{self, MeshStats};
+use super::skin::Skin;
+use super::tangents;
+use super::texture::{self, ImageData, Texture};
+use super::vertex::Vertex;
+use std::hash::{Hash, Hasher};
+
 pub enum IndexBufferType {
     U16(std::vec::Vec<u16>),
     U32(std::vec::Vec<u32>),
@@ -7,13 +24,36 @@ pub enum IndexBufferType {
 pub struct Node {
     pub _name: Option<std::string::String>,
     pub children: std::vec::Vec<usize>,
+    // local transform, kept in sync with translation/rotation/scale by update_matrix
     pub matrix: glm::Mat4,
+    // glTF matrix nodes are decomposed, animations only target TRS
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+    // one per morph target of the mesh, empty if it has none
+    pub weights: std::vec::Vec<f32>,
     pub mesh_index: Option<usize>,
+    pub skin_index: Option<usize>,
+    pub camera_index: Option<usize>,
+    pub light_index: Option<usize>,
+}
+
+impl Node {
+    pub fn update_matrix(&mut self) {
+        self.matrix = trs_matrix(&self.translation, &self.rotation, &self.scale);
+    }
 }
 
 pub struct Primitive {
-    pub vertex_buffer: std::vec::Vec<f32>,
+    pub vertex_buffer: std::vec::Vec<Vertex>,
     pub index_buffer: IndexBufferType,
+    pub material_index: usize,
+    pub morph_target_count: usize,
+    // vertex-major, see morph::MorphDelta
+    pub morph_deltas: std::vec::Vec<MorphDelta>,
+    // coarser levels after the full resolution one, with LoadOptions::lods
+    pub lods: std::vec::Vec<Lod>,
+    pub bounding_sphere: BoundingSphere,
 }
 
 pub struct Mesh {
@@ -27,173 +67,499 @@ pub struct Scene {
     pub nodes: std::vec::Vec<usize>,
 }
 
-// TODO can it be done better?
-macro_rules! extract_buffer {
-    ($type:ty, $document:ident, $buffer_data:ident, $accessor_index:ident) => {{
-        let accessor = $document
-            .accessors()
-            .find(|accessor| accessor.index() == $accessor_index)
-            .expect("Failed to find accessor");
-        let buffer_view = accessor.view().expect("Buffer view not found");
-
-        let accessor_view_offset = accessor.offset();
-        let accessor_elements_count = accessor.count();
-        let accesor_total_data_size = accessor_elements_count
-            * accessor_data_type_to_size(accessor.data_type())
-            * accessor_type_to_components_count(accessor.dimensions());
-
-        let buffer_index = buffer_view.buffer().index();
-        let buffer_offset = buffer_view.offset() + accessor_view_offset;
-        let buffer_size = accesor_total_data_size;
-        // let buffer_stride = buffer_view.stride();
-
-        // TODO make this return vectors, matrices etc.
-        let chunks = $buffer_data[buffer_index].0.as_slice()
-            [buffer_offset..buffer_offset + buffer_size]
-            .chunks_exact(std::mem::size_of::<$type>());
-        let mut buffer: std::vec::Vec<$type> = vec![];
-        for chunk in chunks {
-            let val =
-                <$type>::from_le_bytes(chunk[0..std::mem::size_of::<$type>()].try_into().unwrap());
-            buffer.push(val);
-        }
-
-        buffer
-    }};
-}
-
 pub struct GltfAssetData {
     pub meshes: std::vec::Vec<Mesh>,
+    pub materials: std::vec::Vec<Material>,
+    pub images: std::vec::Vec<ImageData>,
+    pub textures: std::vec::Vec<Texture>,
     pub nodes: std::vec::Vec<Node>,
+    pub skins: std::vec::Vec<Skin>,
+    pub animations: std::vec::Vec<Animation>,
+    pub cameras: std::vec::Vec<Camera>,
+    // KHR_lights_punctual
+    pub lights: std::vec::Vec<Light>,
     pub scenes: std::vec::Vec<Scene>,
-    pub _default_scene: Option<usize>,
+    pub default_scene: Option<usize>,
+    // the glTF file, its buffers and the load options, keys caches of data built from the meshes
+    pub source_hash: u64,
+    // the glTF file and the buffers and images it references by path, to watch for changes
+    pub sources: std::vec::Vec<std::path::PathBuf>,
+}
+
+#[derive(Clone, Default)]
+pub struct LoadOptions {
+    // used only by primitives without NORMAL
+    pub normals: NormalGeneration,
+    // meshopt vertex dedup, vertex cache, overdraw and vertex fetch optimization of every
+    // primitive, see optimize::optimize_primitive
+    pub optimize: bool,
+    // LOD chain simplified from every primitive, coarsest last, see lod::build_lods
+    pub lods: std::vec::Vec<LodLevel>,
+    // cluster hierarchy of every primitive for the meshlet path, see
+    // cluster_lod::build_cluster_hierarchy
+    pub cluster_lod: bool,
+    // where geometry_cache and meshlet_cache keep processed data, None processes every load from
+    // scratch and writes nothing
+    pub cache_dir: Option<std::path::PathBuf>,
+}
+
+// Everything that changes the processed data, the cache location doesn't
+impl Hash for LoadOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self {
+            normals,
+            optimize,
+            lods,
+            cluster_lod,
+            cache_dir: _,
+        } = self;
+        (normals, optimize, lods, cluster_lod).hash(state);
+    }
 }
 
 impl GltfAssetData {
-    pub fn new(path: &str) -> GltfAssetData {
-        let path = std::path::Path::new(path);
-        let dir = path.parent().unwrap();
+    pub fn new(path: impl AsRef<std::path::Path>) -> Result<GltfAssetData, AssetError> {
+        Self::with_options(path, LoadOptions::default())
+    }
 
-        let gltf = gltf::Gltf::open(path).expect("Failed to open gltf file");
-        let document = &gltf.document;
-        let buffer_data = gltf::import_buffers(&document, Some(dir), Option::None)
-            .expect("Failed to import gltf buffers.");
+    pub fn with_options(
+        path: impl AsRef<std::path::Path>,
+        options: LoadOptions,
+    ) -> Result<GltfAssetData, AssetError> {
+        Self::with_progress(path, &options, &mut || {})
+    }
+
+    // `on_mesh_parsed` is called after every mesh, loading may run on a worker thread
+    pub fn with_progress(
+        path: impl AsRef<std::path::Path>,
+        options: &LoadOptions,
+        on_mesh_parsed: &mut dyn FnMut(),
+    ) -> Result<GltfAssetData, AssetError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+
+        // handles both .gltf and .glb, the latter keeps its BIN chunk in `blob`
+        let gltf::Gltf { document, blob } =
+            gltf::Gltf::open(path).map_err(|source| AssetError::Open {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let document = &document;
+        // external files, base64 data URIs and the GLB blob
+        let buffer_data =
+            gltf::import_buffers(document, Some(dir), blob).map_err(AssetError::Buffers)?;
+
+        let source_hash = {
+            let mut hasher = Fnv1a::default();
+            let file = std::fs::read(path).map_err(|err| AssetError::Open {
+                path: path.to_path_buf(),
+                source: gltf::Error::Io(err),
+            })?;
+            hasher.write(&file);
+            for buffer in &buffer_data {
+                hasher.write(buffer);
+            }
+            options.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // processed primitives of an unchanged source, see geometry_cache
+        let cache_path = options
+            .cache_dir
+            .as_deref()
+            .map(|dir| geometry_cache::path(dir, source_hash));
+        let primitive_counts: std::vec::Vec<usize> = document
+            .meshes()
+            .map(|mesh| mesh.primitives().len())
+            .collect();
+        let mut cached_meshes = cache_path.as_deref().and_then(|cache_path| {
+            match geometry_cache::load(cache_path, source_hash, &primitive_counts) {
+                Ok(meshes) => Some(meshes.into_iter()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    println!("Reprocessing primitives, {}: {}", cache_path.display(), err);
+                    None
+                }
+            }
+        });
+        let loaded_from_cache = cached_meshes.is_some();
 
         let mut meshes = vec![];
         let mut nodes = vec![];
         let mut scenes = vec![];
+        let mut materials: std::vec::Vec<Material> = document
+            .materials()
+            .map(|m| Material::from_gltf(&m))
+            .collect();
 
+        let textures: std::vec::Vec<Texture> = document
+            .textures()
+            .map(|t| Texture::from_gltf(&t))
+            .collect();
+        // color textures are sRGB encoded, everything else (normal, ORM) is linear
+        let mut srgb_images = std::collections::HashSet::new();
+        for material in &materials {
+            for texture_ref in [material.base_color_texture, material.emissive_texture]
+                .iter()
+                .flatten()
+            {
+                srgb_images.insert(textures[texture_ref.texture_index].image_index);
+            }
+        }
+        let images = document
+            .images()
+            .map(|image| {
+                let encoded = texture::read_image_bytes(&image, dir, &buffer_data)?;
+                texture::decode_image(&encoded, srgb_images.contains(&image.index())).map_err(
+                    |reason| AssetError::Image {
+                        image: image.index(),
+                        reason,
+                    },
+                )
+            })
+            .collect::<Result<std::vec::Vec<ImageData>, AssetError>>()?;
+
+        // primitives without a material use the spec default, appended lazily at the end
+        let mut default_material_index: Option<usize> = None;
+
+        // of every primitive, with LoadOptions::optimize
+        let mut stats_before = MeshStats::default();
+        let mut stats_after = MeshStats::default();
         for mesh in document.meshes() {
             let mut primitives: std::vec::Vec<Primitive> = vec![];
+            let mut cached_primitives = cached_meshes
+                .as_mut()
+                .and_then(Iterator::next)
+                .map(std::vec::Vec::into_iter);
 
             for primitive in mesh.primitives() {
-                let mut position_accessor: Option<usize> = Option::None;
-                let mut normals_accessor: Option<usize> = Option::None;
-                let mut texture_coords_accessor: Option<usize> = Option::None;
-                let indices_accessor: Option<usize>;
+                let material_index = match primitive.material().index() {
+                    Some(index) => index,
+                    None => *default_material_index.get_or_insert_with(|| {
+                        materials.push(Material::default());
+                        materials.len() - 1
+                    }),
+                };
+                if let Some(cached) = cached_primitives.as_mut().and_then(Iterator::next) {
+                    primitives.push(Primitive {
+                        material_index,
+                        ..cached
+                    });
+                    continue;
+                }
+
+                let read_error = |accessor: &gltf::Accessor| {
+                    let (mesh, primitive, accessor) =
+                        (mesh.index(), primitive.index(), accessor.index());
+                    move |source| AssetError::Accessor {
+                        mesh,
+                        primitive,
+                        accessor,
+                        source,
+                    }
+                };
+                let missing_attribute = |semantic| AssetError::MissingAttribute {
+                    mesh: mesh.index(),
+                    primitive: primitive.index(),
+                    semantic,
+                };
+                let count_mismatch =
+                    |semantic, expected, found| AssetError::AttributeCountMismatch {
+                        mesh: mesh.index(),
+                        primitive: primitive.index(),
+                        semantic,
+                        expected,
+                        found,
+                    };
+
+                let mut position_accessor: Option<gltf::Accessor> = Option::None;
+                let mut normals_accessor: Option<gltf::Accessor> = Option::None;
+                let mut texture_coords_accessor: Option<gltf::Accessor> = Option::None;
+                let mut texture_coords1_accessor: Option<gltf::Accessor> = Option::None;
+                let mut colors_accessor: Option<gltf::Accessor> = Option::None;
+                let mut tangents_accessor: Option<gltf::Accessor> = Option::None;
+                let mut joints_accessor: Option<gltf::Accessor> = Option::None;
+                let mut weights_accessor: Option<gltf::Accessor> = Option::None;
 
                 for attribute in primitive.attributes() {
                     let (semantic, accessor) = attribute;
                     match semantic {
                         gltf::Semantic::Positions => {
-                            position_accessor = Some(accessor.index());
+                            position_accessor = Some(accessor);
                         }
                         gltf::Semantic::Normals => {
-                            normals_accessor = Some(accessor.index());
+                            normals_accessor = Some(accessor);
+                        }
+                        gltf::Semantic::Tangents => {
+                            tangents_accessor = Some(accessor);
+                        }
+                        gltf::Semantic::Colors(0) => {
+                            colors_accessor = Some(accessor);
                         }
-                        gltf::Semantic::Tangents => {}
                         gltf::Semantic::Colors(_) => {}
-                        gltf::Semantic::TexCoords(_) => {
-                            texture_coords_accessor = Some(accessor.index());
+                        gltf::Semantic::TexCoords(0) => {
+                            texture_coords_accessor = Some(accessor);
+                        }
+                        gltf::Semantic::TexCoords(1) => {
+                            texture_coords1_accessor = Some(accessor);
+                        }
+                        gltf::Semantic::TexCoords(_) => {}
+                        // a single set, i.e. up to 4 influences per vertex
+                        gltf::Semantic::Joints(0) => {
+                            joints_accessor = Some(accessor);
                         }
                         gltf::Semantic::Joints(_) => {}
+                        gltf::Semantic::Weights(0) => {
+                            weights_accessor = Some(accessor);
+                        }
                         gltf::Semantic::Weights(_) => {}
                     }
                 }
-                match primitive.indices() {
-                    Some(indices) => {
-                        indices_accessor = Some(indices.index());
+
+                let position_accessor = position_accessor
+                    .ok_or_else(|| missing_attribute(gltf::Semantic::Positions))?;
+                let position_buffer = read_vec3(&position_accessor, &buffer_data)
+                    .map_err(read_error(&position_accessor))?;
+                let vertex_count = position_buffer.len();
+                let expect_count = |semantic, found| match found == vertex_count {
+                    true => Ok(()),
+                    false => Err(count_mismatch(semantic, vertex_count, found)),
+                };
+
+                let index_buffer = match primitive.indices() {
+                    Some(accessor) => Some(
+                        read_index_buffer(&accessor, &buffer_data, vertex_count)
+                            .map_err(read_error(&accessor))?,
+                    ),
+                    None => None,
+                };
+
+                // per target displacements, indexed like the glTF vertices
+                let mut morph_targets = vec![];
+                for target in primitive.morph_targets() {
+                    let mut deltas = vec![MorphDelta::default(); vertex_count];
+                    if let Some(accessor) = target.positions() {
+                        let buffer =
+                            read_vec3(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                        expect_count(gltf::Semantic::Positions, buffer.len())?;
+                        for (delta, position) in deltas.iter_mut().zip(buffer) {
+                            delta.position = position.into();
+                        }
+                    }
+                    if let Some(accessor) = target.normals() {
+                        let buffer =
+                            read_vec3(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                        expect_count(gltf::Semantic::Normals, buffer.len())?;
+                        for (delta, normal) in deltas.iter_mut().zip(buffer) {
+                            delta.normal = normal.into();
+                        }
+                    }
+                    if let Some(accessor) = target.tangents() {
+                        let buffer =
+                            read_vec3(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                        expect_count(gltf::Semantic::Tangents, buffer.len())?;
+                        for (delta, tangent) in deltas.iter_mut().zip(buffer) {
+                            delta.tangent = tangent.into();
+                        }
+                    }
+                    morph_targets.push(deltas);
+                }
+                // a glTF vertex with the same morph deltas as every vertex, follows unwelding and
+                // welding so the deltas can be gathered at the end
+                let mut source_vertices =
+                    morph::first_identical_vertex(&morph_targets, vertex_count);
+
+                let mut vertices: std::vec::Vec<Vertex> = position_buffer
+                    .iter()
+                    .map(|pos| Vertex {
+                        position: (*pos).into(),
+                        color: [1.0, 1.0, 1.0, 1.0],
+                        tangent: tangents::NO_TANGENT,
+                        ..Default::default()
+                    })
+                    .collect();
+
+                if let Some(accessor) = texture_coords_accessor {
+                    let buffer =
+                        read_vec2(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                    expect_count(gltf::Semantic::TexCoords(0), buffer.len())?;
+                    for (vertex, tx) in vertices.iter_mut().zip(buffer) {
+                        vertex.tex_coord = tx.into();
                     }
-                    None => todo!(),
                 }
 
-                let position_buffer_component_type =
-                    get_data_type(&document, position_accessor.unwrap());
-                let index_buffer_component_type =
-                    get_data_type(&document, indices_accessor.unwrap());
-                let normals_buffer_component_type =
-                    get_data_type(&document, normals_accessor.unwrap());
-
-                assert!(position_buffer_component_type == DataType::F32);
-                assert!(normals_buffer_component_type == DataType::F32);
-
-                let position_accessor = position_accessor.unwrap();
-                // TODO extract with stride, use variants
-                let position_buffer: Vec<glm::Vec3> =
-                    extract_buffer!(f32, document, buffer_data, position_accessor)
-                        .chunks_exact(3)
-                        .map(glm::make_vec3)
-                        .collect();
-
-                let index_buffer = extract_index_buffer(
-                    index_buffer_component_type,
-                    &document,
-                    &buffer_data,
-                    indices_accessor.unwrap(),
-                );
-
-                let normals_accessor = normals_accessor.unwrap();
-                let normals_buffer: Vec<glm::Vec3> =
-                    extract_buffer!(f32, document, buffer_data, normals_accessor)
-                        .chunks_exact(3)
-                        .map(glm::make_vec3)
-                        .collect();
-
-                let texture_coords_buffer =
-                    if let Some(texture_coords_accessor) = texture_coords_accessor {
-                        extract_buffer!(f32, document, buffer_data, texture_coords_accessor)
-                            .chunks_exact(2)
-                            .map(glm::make_vec2)
-                            .collect()
-                    } else {
-                        // TODO o kurwa co tu sie dzieje, prosze nie rob tak
-                        let mut v: std::vec::Vec<glm::Vec2> = vec![];
-                        for _ in 0..position_buffer.len() {
-                            v.push(glm::make_vec2(&[0.0, 0.0]));
+                if let Some(accessor) = texture_coords1_accessor {
+                    let buffer =
+                        read_vec2(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                    expect_count(gltf::Semantic::TexCoords(1), buffer.len())?;
+                    for (vertex, tx) in vertices.iter_mut().zip(buffer) {
+                        vertex.tex_coord1 = tx.into();
+                    }
+                }
+
+                if let Some(accessor) = colors_accessor {
+                    let buffer =
+                        read_color(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                    expect_count(gltf::Semantic::Colors(0), buffer.len())?;
+                    for (vertex, color) in vertices.iter_mut().zip(buffer) {
+                        vertex.color = color.into();
+                    }
+                }
+
+                if let (Some(joints), Some(weights)) = (joints_accessor, weights_accessor) {
+                    let joints_buffer =
+                        read_u32(&joints, &buffer_data).map_err(read_error(&joints))?;
+                    let weights_buffer =
+                        read_vec4(&weights, &buffer_data).map_err(read_error(&weights))?;
+                    expect_count(gltf::Semantic::Joints(0), joints_buffer.len() / 4)?;
+                    expect_count(gltf::Semantic::Weights(0), weights_buffer.len())?;
+                    for ((vertex, joints), weights) in vertices
+                        .iter_mut()
+                        .zip(joints_buffer.chunks_exact(4))
+                        .zip(weights_buffer)
+                    {
+                        vertex.joints = joints.try_into().unwrap();
+                        // exporters don't always normalize, quantized weights never sum up exactly
+                        let sum = weights.sum();
+                        if sum > 0.0 {
+                            vertex.weights = (weights / sum).into();
                         }
-                        v
-                    };
+                    }
+                }
 
-                // assert!(texture_coords_buffer.len() == position_buffer.len());
-                assert!(position_buffer.len() == normals_buffer.len());
-
-                // TODO maybe map + collect
-                let mut combined_buffer: Vec<f32> = Vec::new();
-
-                for ((pos, norm), tx) in std::iter::zip(
-                    std::iter::zip(position_buffer, normals_buffer),
-                    texture_coords_buffer,
-                ) {
-                    combined_buffer.push(pos.x);
-                    combined_buffer.push(pos.y);
-                    combined_buffer.push(pos.z);
-                    combined_buffer.push(norm.x);
-                    combined_buffer.push(norm.y);
-                    combined_buffer.push(norm.z);
-                    combined_buffer.push(tx.x);
-                    combined_buffer.push(tx.y);
+                // glTF 2.0 3.7.2.1: provided tangents are ignored when normals are not specified
+                let mut has_tangents = false;
+                if let (Some(_), Some(accessor)) = (&normals_accessor, tangents_accessor) {
+                    let buffer =
+                        read_vec4(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                    expect_count(gltf::Semantic::Tangents, buffer.len())?;
+                    for (vertex, tangent) in vertices.iter_mut().zip(buffer) {
+                        vertex.tangent = tangent.into();
+                    }
+                    has_tangents = true;
                 }
 
-                primitives.push(Primitive {
-                    vertex_buffer: combined_buffer,
+                let (vertices, index_buffer) = match normals_accessor {
+                    Some(accessor) => {
+                        let buffer =
+                            read_vec3(&accessor, &buffer_data).map_err(read_error(&accessor))?;
+                        expect_count(gltf::Semantic::Normals, buffer.len())?;
+                        for (vertex, normal) in vertices.iter_mut().zip(buffer) {
+                            vertex.normal = normal.into();
+                        }
+                        (vertices, index_buffer)
+                    }
+                    None => {
+                        let indices = triangle_indices(&index_buffer, vertex_count);
+
+                        match options.normals {
+                            // every corner gets its own vertex, welded back below
+                            NormalGeneration::Flat => {
+                                let normals = normals::flat_normals(&position_buffer, &indices);
+                                let mut vertices = normals::unweld(&vertices, &indices);
+                                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                                    vertex.normal = normal.into();
+                                }
+                                source_vertices = normals::unweld(&source_vertices, &indices);
+                                (vertices, None)
+                            }
+                            NormalGeneration::Smooth => {
+                                let normals = normals::smooth_normals(&position_buffer, &indices);
+                                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                                    vertex.normal = normal.into();
+                                }
+                                (vertices, index_buffer)
+                            }
+                        }
+                    }
+                };
+
+                // tangents only matter for normal mapping, skip MikkTSpace for everything else
+                let has_normal_texture = primitive.material().normal_texture().is_some();
+                let (vertices, index_buffer) = match !has_tangents && has_normal_texture {
+                    true => {
+                        let indices = triangle_indices(&index_buffer, vertices.len());
+                        match tangents::generate_tangents(&vertices, &indices) {
+                            // tangents are per corner, every corner gets its own vertex, welded
+                            // back below
+                            Some(tangents) => {
+                                let mut vertices = normals::unweld(&vertices, &indices);
+                                for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                                    vertex.tangent = tangent.into();
+                                }
+                                source_vertices = normals::unweld(&source_vertices, &indices);
+                                (vertices, None)
+                            }
+                            None => (vertices, index_buffer),
+                        }
+                    }
+                    false => (vertices, index_buffer),
+                };
+
+                let (vertices, index_buffer) = match index_buffer {
+                    Some(index_buffer) => (vertices, index_buffer),
+                    None => weld_vertices(
+                        &vertices,
+                        (!morph_targets.is_empty()).then_some(&mut source_vertices),
+                    ),
+                };
+
+                let morph_deltas = source_vertices
+                    .iter()
+                    .flat_map(|&source| {
+                        morph_targets
+                            .iter()
+                            .map(move |deltas| deltas[source as usize])
+                    })
+                    .collect();
+
+                let bounding_sphere = BoundingSphere::of(&vertices);
+                let mut primitive_data = Primitive {
+                    vertex_buffer: vertices,
                     index_buffer,
-                });
+                    material_index,
+                    morph_target_count: morph_targets.len(),
+                    morph_deltas,
+                    lods: vec![],
+                    bounding_sphere,
+                };
+                if options.optimize {
+                    let (before, after) = optimize::optimize_primitive(&mut primitive_data);
+                    stats_before.add(&before);
+                    stats_after.add(&after);
+                }
+                // after optimizing, the levels index the final vertex buffer
+                if !options.lods.is_empty() {
+                    let indices: std::vec::Vec<u32> = match &primitive_data.index_buffer {
+                        IndexBufferType::U16(items) => items.iter().map(|i| *i as u32).collect(),
+                        IndexBufferType::U32(items) => items.clone(),
+                    };
+                    primitive_data.lods =
+                        lod::build_lods(&primitive_data.vertex_buffer, &indices, &options.lods);
+                }
+                primitives.push(primitive_data);
             }
 
             meshes.push(Mesh {
                 _name: mesh.name().map(|strslice| strslice.to_string()),
                 primitives,
             });
+            on_mesh_parsed();
+        }
+        if let (Some(cache_path), false) = (&cache_path, loaded_from_cache) {
+            meshlet_cache::save_best_effort(cache_path, || {
+                geometry_cache::save(cache_path, source_hash, &meshes)
+            });
+        }
+        if options.optimize && !loaded_from_cache {
+            println!(
+                "Optimized {}: ACMR {:.3} -> {:.3}, overdraw {:.3} -> {:.3}",
+                path.display(),
+                stats_before.acmr(),
+                stats_after.acmr(),
+                stats_before.overdraw(),
+                stats_after.overdraw()
+            );
         }
 
         for node in document.nodes() {
@@ -228,27 +594,117 @@ impl GltfAssetData {
                     translation,
                     rotation,
                     scale,
-                } => {
-                    let mut m = glm::Mat4::identity();
-                    let translation = [translation[0], translation[1], translation[2]];
-                    m = glm::translate(&m, &glm::make_vec3(&translation));
-                    let q = glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]);
-                    m = m * glm::quat_to_mat4(&q);
-                    m = glm::scale(&m, &glm::make_vec3(&scale));
-                    m
-                }
+                } => trs_matrix(
+                    &glm::make_vec3(&translation),
+                    &glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                    &glm::make_vec3(&scale),
+                ),
             };
+            let (translation, rotation, scale) = node.transform().decomposed();
 
             let mesh_index = node.mesh().map(|v| v.index());
+            // node weights override the mesh defaults, which default to 0
+            let morph_target_count = mesh_index.map_or(0, |mesh_index| {
+                meshes[mesh_index]
+                    .primitives
+                    .iter()
+                    .map(|primitive| primitive.morph_target_count)
+                    .max()
+                    .unwrap_or(0)
+            });
+            let mut weights = node
+                .weights()
+                .or_else(|| node.mesh().and_then(|mesh| mesh.weights()))
+                .unwrap_or_default()
+                .to_vec();
+            weights.resize(morph_target_count, 0.0);
 
             nodes.push(Node {
                 _name: node.name().map(|strslice| strslice.to_string()),
                 children,
                 matrix,
+                translation: glm::make_vec3(&translation),
+                rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                scale: glm::make_vec3(&scale),
+                weights,
                 mesh_index,
+                skin_index: node.skin().map(|skin| skin.index()),
+                camera_index: node.camera().map(|camera| camera.index()),
+                light_index: node.light().map(|light| light.index()),
+            });
+        }
+
+        let mut skins = vec![];
+        for skin in document.skins() {
+            let skin_error = |reason: std::string::String| AssetError::Skin {
+                skin: skin.index(),
+                reason,
+            };
+            let joints: std::vec::Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+            let inverse_bind_matrices = match skin.inverse_bind_matrices() {
+                Some(accessor) => {
+                    let matrices = read_mat4(&accessor, &buffer_data)
+                        .map_err(|e| skin_error(format!("accessor {}: {}", accessor.index(), e)))?;
+                    if matrices.len() < joints.len() {
+                        return Err(skin_error(format!(
+                            "{} inverse bind matrices for {} joints",
+                            matrices.len(),
+                            joints.len()
+                        )));
+                    }
+                    matrices
+                }
+                None => vec![glm::Mat4::identity(); joints.len()],
+            };
+
+            skins.push(Skin {
+                _name: skin.name().map(|s| s.to_string()),
+                joints,
+                inverse_bind_matrices,
             });
         }
 
+        // out of range joints would read past the joint palette on the GPU. Unused slots (zero
+        // weight) may hold anything, they are pointed at joint 0 instead.
+        for node in &nodes {
+            if let (Some(mesh_index), Some(skin_index)) = (node.mesh_index, node.skin_index) {
+                let joint_count = skins[skin_index].joints.len();
+                let vertices = meshes[mesh_index]
+                    .primitives
+                    .iter_mut()
+                    .flat_map(|primitive| &mut primitive.vertex_buffer);
+                for vertex in vertices {
+                    for (joint, &weight) in std::iter::zip(&mut vertex.joints, &vertex.weights) {
+                        if *joint as usize >= joint_count && weight == 0.0 {
+                            *joint = 0;
+                        } else if *joint as usize >= joint_count {
+                            return Err(AssetError::Skin {
+                                skin: skin_index,
+                                reason: format!(
+                                    "mesh {} references joint {}, skin has {}",
+                                    mesh_index, joint, joint_count
+                                ),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let animations = document
+            .animations()
+            .map(|animation| read_animation(&animation, &buffer_data))
+            .collect::<Result<std::vec::Vec<Animation>, AssetError>>()?;
+
+        let cameras = document
+            .cameras()
+            .map(|camera| Camera::from_gltf(&camera))
+            .collect();
+        let lights = document
+            .lights()
+            .map(|lights| lights.map(|light| Light::from_gltf(&light)).collect())
+            .unwrap_or_default();
+
         for scene in document.scenes() {
             scenes.push(Scene {
                 _name: scene.name().map(|s| s.to_string()),
@@ -256,15 +712,29 @@ impl GltfAssetData {
             });
         }
 
-        GltfAssetData {
+        Ok(GltfAssetData {
             meshes,
+            materials,
+            images,
+            textures,
             nodes,
+            skins,
+            animations,
+            cameras,
+            lights,
             scenes,
-            _default_scene: document.default_scene().map(|scene| scene.index()),
-        }
+            default_scene: document.default_scene().map(|scene| scene.index()),
+            source_hash,
+            sources: std::iter::once(path.to_path_buf())
+                .chain(external_files(document, dir))
+                .collect(),
+        })
     }
 }
-use gltf::{self, accessor::DataType};
+use gltf::{
+    self,
+    accessor::{DataType, Dimensions},
+};
 
 fn accessor_data_type_to_size(accessor_data_type: gltf::accessor::DataType) -> usize {
     match accessor_data_type {
@@ -277,42 +747,987 @@ fn accessor_data_type_to_size(accessor_data_type: gltf::accessor::DataType) -> u
     }
 }
 
-fn accessor_type_to_components_count(accessor_type: gltf::accessor::Dimensions) -> usize {
+// (columns, rows)
+fn accessor_type_to_shape(accessor_type: Dimensions) -> (usize, usize) {
     match accessor_type {
-        gltf::accessor::Dimensions::Scalar => 1,
-        gltf::accessor::Dimensions::Vec2 => 2,
-        gltf::accessor::Dimensions::Vec3 => 3,
-        gltf::accessor::Dimensions::Vec4 => 4,
-        gltf::accessor::Dimensions::Mat2 => 4,
-        gltf::accessor::Dimensions::Mat3 => 9,
-        gltf::accessor::Dimensions::Mat4 => 16,
+        Dimensions::Scalar => (1, 1),
+        Dimensions::Vec2 => (1, 2),
+        Dimensions::Vec3 => (1, 3),
+        Dimensions::Vec4 => (1, 4),
+        Dimensions::Mat2 => (2, 2),
+        Dimensions::Mat3 => (3, 3),
+        Dimensions::Mat4 => (4, 4),
     }
 }
 
-fn extract_index_buffer(
-    data_type: DataType,
-    document: &gltf::Document,
-    buffer_data: &Vec<gltf::buffer::Data>,
-    accessor_index: usize,
-) -> IndexBufferType {
-    return match data_type {
-        DataType::I8 => todo!(),
-        DataType::U8 => todo!(),
-        DataType::I16 => todo!(),
-        DataType::U16 => {
-            IndexBufferType::U16(extract_buffer!(u16, document, buffer_data, accessor_index))
-        }
-        DataType::U32 => {
-            IndexBufferType::U32(extract_buffer!(u32, document, buffer_data, accessor_index))
-        }
-        DataType::F32 => todo!(),
+// Byte size of a matrix column as stored in a buffer. Matrix columns start on 4-byte boundaries,
+// which pads mat2/mat3 of 1-byte and mat3 of 2-byte components.
+fn accessor_column_size(data_type: DataType, dimensions: Dimensions) -> usize {
+    let (columns, rows) = accessor_type_to_shape(dimensions);
+    let column_size = rows * accessor_data_type_to_size(data_type);
+    match columns {
+        1 => column_size,
+        _ => column_size.next_multiple_of(4),
+    }
+}
+
+fn accessor_element_size(data_type: DataType, dimensions: Dimensions) -> usize {
+    let (columns, _) = accessor_type_to_shape(dimensions);
+    columns * accessor_column_size(data_type, dimensions)
+}
+
+// Tightly packed bytes of every accessor element: byteStride is resolved and sparse substitutions
+// are applied. Accessors without a buffer view start zeroed, per spec.
+fn read_accessor_bytes(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<std::vec::Vec<u8>, AccessorError> {
+    let element_size = accessor_element_size(accessor.data_type(), accessor.dimensions());
+    let count = accessor.count();
+
+    let mut bytes = match accessor.view() {
+        Some(view) => {
+            let buffer = &buffer_data[view.buffer().index()].0;
+            let stride = view.stride().unwrap_or(element_size);
+            let start = view.offset() + accessor.offset();
+            check_bounds(view.buffer().index(), buffer, view.offset() + view.length())?;
+            if count > 0 {
+                check_view_bounds(
+                    &view,
+                    accessor.offset() + (count - 1) * stride + element_size,
+                )?;
+            }
+
+            let mut bytes = std::vec::Vec::with_capacity(count * element_size);
+            for i in 0..count {
+                let offset = start + i * stride;
+                bytes.extend_from_slice(&buffer[offset..offset + element_size]);
+            }
+            bytes
+        }
+        None => vec![0; count * element_size],
+    };
+
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let indices_view = indices.view();
+        let indices_buffer = &buffer_data[indices_view.buffer().index()].0;
+        let indices_start = indices_view.offset() + indices.offset();
+
+        let values = sparse.values();
+        let values_view = values.view();
+        let values_buffer = &buffer_data[values_view.buffer().index()].0;
+        let values_start = values_view.offset() + values.offset();
+
+        let index_size = match indices.index_type() {
+            gltf::accessor::sparse::IndexType::U8 => 1,
+            gltf::accessor::sparse::IndexType::U16 => 2,
+            gltf::accessor::sparse::IndexType::U32 => 4,
+        };
+        check_bounds(
+            indices_view.buffer().index(),
+            indices_buffer,
+            indices_view.offset() + indices_view.length(),
+        )?;
+        check_view_bounds(
+            &indices_view,
+            indices.offset() + sparse.count() * index_size,
+        )?;
+        check_bounds(
+            values_view.buffer().index(),
+            values_buffer,
+            values_view.offset() + values_view.length(),
+        )?;
+        check_view_bounds(
+            &values_view,
+            values.offset() + sparse.count() * element_size,
+        )?;
+
+        for i in 0..sparse.count() {
+            let target = match indices.index_type() {
+                gltf::accessor::sparse::IndexType::U8 => indices_buffer[indices_start + i] as usize,
+                gltf::accessor::sparse::IndexType::U16 => {
+                    let offset = indices_start + i * 2;
+                    u16::from_le_bytes(indices_buffer[offset..offset + 2].try_into().unwrap())
+                        as usize
+                }
+                gltf::accessor::sparse::IndexType::U32 => {
+                    let offset = indices_start + i * 4;
+                    u32::from_le_bytes(indices_buffer[offset..offset + 4].try_into().unwrap())
+                        as usize
+                }
+            };
+
+            if target >= count {
+                return Err(AccessorError::SparseIndexOutOfRange {
+                    index: target,
+                    count,
+                });
+            }
+
+            let value = values_start + i * element_size;
+            bytes[target * element_size..(target + 1) * element_size]
+                .copy_from_slice(&values_buffer[value..value + element_size]);
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn check_bounds(buffer_index: usize, buffer: &[u8], end: usize) -> Result<(), AccessorError> {
+    match end <= buffer.len() {
+        true => Ok(()),
+        false => Err(AccessorError::OutOfBounds {
+            buffer: buffer_index,
+            end,
+            buffer_length: buffer.len(),
+        }),
+    }
+}
+
+// `end` relative to the start of the view
+fn check_view_bounds(view: &gltf::buffer::View, end: usize) -> Result<(), AccessorError> {
+    match end <= view.length() {
+        true => Ok(()),
+        false => Err(AccessorError::OutsideView {
+            view: view.index(),
+            end,
+            view_length: view.length(),
+        }),
+    }
+}
+
+// Every component of every element, in order, with matrix column padding skipped.
+fn read_accessor_components<T>(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+    convert: impl Fn(&[u8]) -> T,
+) -> Result<std::vec::Vec<T>, AccessorError> {
+    let data_type = accessor.data_type();
+    let component_size = accessor_data_type_to_size(data_type);
+    let column_size = accessor_column_size(data_type, accessor.dimensions());
+    let (columns, rows) = accessor_type_to_shape(accessor.dimensions());
+
+    let bytes = read_accessor_bytes(accessor, buffer_data)?;
+    let element_size = columns * column_size;
+
+    let mut components = std::vec::Vec::with_capacity(accessor.count() * columns * rows);
+    for element in bytes.chunks_exact(element_size) {
+        for column in element.chunks_exact(column_size) {
+            for row in 0..rows {
+                let offset = row * component_size;
+                components.push(convert(&column[offset..offset + component_size]));
+            }
+        }
+    }
+
+    Ok(components)
+}
+
+// KHR_mesh_quantization / glTF 2.0 3.11: normalized integers map to [0, 1] or [-1, 1]
+fn component_to_f32(bytes: &[u8], data_type: DataType, normalized: bool) -> f32 {
+    match (data_type, normalized) {
+        (DataType::F32, _) => f32::from_le_bytes(bytes.try_into().unwrap()),
+        (DataType::I8, false) => bytes[0] as i8 as f32,
+        (DataType::I8, true) => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+        (DataType::U8, false) => bytes[0] as f32,
+        (DataType::U8, true) => bytes[0] as f32 / 255.0,
+        (DataType::I16, false) => i16::from_le_bytes(bytes.try_into().unwrap()) as f32,
+        (DataType::I16, true) => {
+            (i16::from_le_bytes(bytes.try_into().unwrap()) as f32 / 32767.0).max(-1.0)
+        }
+        (DataType::U16, false) => u16::from_le_bytes(bytes.try_into().unwrap()) as f32,
+        (DataType::U16, true) => u16::from_le_bytes(bytes.try_into().unwrap()) as f32 / 65535.0,
+        (DataType::U32, false) => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+        (DataType::U32, true) => {
+            u32::from_le_bytes(bytes.try_into().unwrap()) as f64 as f32 / u32::MAX as f32
+        }
+    }
+}
+
+// integer components as-is, for indices and joints
+fn component_to_u32(bytes: &[u8], data_type: DataType) -> u32 {
+    match data_type {
+        DataType::I8 => bytes[0] as i8 as u32,
+        DataType::U8 => bytes[0] as u32,
+        DataType::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as u32,
+        DataType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as u32,
+        DataType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()),
+        DataType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as u32,
+    }
+}
+
+fn read_f32(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<f32>, AccessorError> {
+    let data_type = accessor.data_type();
+    let normalized = accessor.normalized();
+    read_accessor_components(accessor, buffer_data, |bytes| {
+        component_to_f32(bytes, data_type, normalized)
+    })
+}
+
+fn read_u32(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<u32>, AccessorError> {
+    let data_type = accessor.data_type();
+    read_accessor_components(accessor, buffer_data, |bytes| {
+        component_to_u32(bytes, data_type)
+    })
+}
+
+fn read_vec2(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<glm::Vec2>, AccessorError> {
+    Ok(read_f32(accessor, buffer_data)?
+        .chunks_exact(2)
+        .map(glm::make_vec2)
+        .collect())
+}
+
+fn read_vec3(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<glm::Vec3>, AccessorError> {
+    Ok(read_f32(accessor, buffer_data)?
+        .chunks_exact(3)
+        .map(glm::make_vec3)
+        .collect())
+}
+
+// COLOR_n is RGB or RGBA, float or normalized u8/u16
+fn read_color(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<glm::Vec4>, AccessorError> {
+    let components = read_f32(accessor, buffer_data)?;
+    Ok(match accessor.dimensions() {
+        gltf::accessor::Dimensions::Vec3 => components
+            .chunks_exact(3)
+            .map(|rgb| glm::vec4(rgb[0], rgb[1], rgb[2], 1.0))
+            .collect(),
+        _ => components.chunks_exact(4).map(glm::make_vec4).collect(),
+    })
+}
+
+fn read_vec4(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<glm::Vec4>, AccessorError> {
+    Ok(read_f32(accessor, buffer_data)?
+        .chunks_exact(4)
+        .map(glm::make_vec4)
+        .collect())
+}
+
+fn read_mat4(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<glm::Mat4>, AccessorError> {
+    Ok(read_f32(accessor, buffer_data)?
+        .chunks_exact(16)
+        .map(glm::make_mat4)
+        .collect())
+}
+
+fn trs_matrix(translation: &glm::Vec3, rotation: &glm::Quat, scale: &glm::Vec3) -> glm::Mat4 {
+    glm::translation(translation) * glm::quat_to_mat4(rotation) * glm::scaling(scale)
+}
+
+fn read_animation(
+    animation: &gltf::Animation,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Animation, AssetError> {
+    let animation_error = |reason: std::string::String| AssetError::Animation {
+        animation: animation.index(),
+        reason,
     };
+
+    let mut samplers = vec![];
+    for sampler in animation.samplers() {
+        let read = |accessor: gltf::Accessor| {
+            read_f32(&accessor, buffer_data)
+                .map_err(|e| animation_error(format!("accessor {}: {}", accessor.index(), e)))
+        };
+        let interpolation = match sampler.interpolation() {
+            gltf::animation::Interpolation::Linear => animation::Interpolation::Linear,
+            gltf::animation::Interpolation::Step => animation::Interpolation::Step,
+            gltf::animation::Interpolation::CubicSpline => animation::Interpolation::CubicSpline,
+        };
+        let inputs = read(sampler.input())?;
+        let outputs = read(sampler.output())?;
+        let values_per_keyframe = match interpolation {
+            animation::Interpolation::CubicSpline => 3,
+            _ => 1,
+        };
+        if inputs.is_empty() || outputs.len() % (inputs.len() * values_per_keyframe) != 0 {
+            return Err(animation_error(format!(
+                "sampler {}: {} outputs for {} keyframes",
+                samplers.len(),
+                outputs.len(),
+                inputs.len()
+            )));
+        }
+        samplers.push(animation::Sampler {
+            interpolation,
+            inputs,
+            outputs,
+        });
+    }
+
+    let mut channels = vec![];
+    for channel in animation.channels() {
+        let property = match channel.target().property() {
+            gltf::animation::Property::Translation => animation::Property::Translation,
+            gltf::animation::Property::Rotation => animation::Property::Rotation,
+            gltf::animation::Property::Scale => animation::Property::Scale,
+            gltf::animation::Property::MorphTargetWeights => animation::Property::Weights,
+        };
+        let sampler = channel.sampler().index();
+        let expected_components = match property {
+            animation::Property::Translation | animation::Property::Scale => Some(3),
+            animation::Property::Rotation => Some(4),
+            animation::Property::Weights => None,
+        };
+        let components = samplers[sampler].components();
+        if expected_components.is_some_and(|expected| expected != components) {
+            return Err(animation_error(format!(
+                "channel {}: {:?} sampled with {} components",
+                channels.len(),
+                property,
+                components
+            )));
+        }
+        channels.push(animation::Channel {
+            node: channel.target().node().index(),
+            property,
+            sampler,
+        });
+    }
+
+    let duration = samplers
+        .iter()
+        .filter_map(|sampler| sampler.inputs.last().copied())
+        .fold(0.0, f32::max);
+
+    Ok(Animation {
+        name: animation.name().map(|s| s.to_string()),
+        channels,
+        samplers,
+        duration,
+    })
+}
+
+// Triangle list indices as u32, non-indexed primitives get 0..vertex_count
+fn triangle_indices(index_buffer: &Option<IndexBufferType>, vertex_count: usize) -> Vec<u32> {
+    match index_buffer {
+        Some(IndexBufferType::U16(items)) => items.iter().map(|i| *i as u32).collect(),
+        Some(IndexBufferType::U32(items)) => items.clone(),
+        None => (0..vertex_count as u32).collect(),
+    }
+}
+
+fn read_index_buffer(
+    accessor: &gltf::Accessor,
+    buffer_data: &[gltf::buffer::Data],
+    vertex_count: usize,
+) -> Result<IndexBufferType, AccessorError> {
+    let data_type = accessor.data_type();
+    if !matches!(data_type, DataType::U8 | DataType::U16 | DataType::U32) {
+        return Err(AccessorError::InvalidIndexType(data_type));
+    }
+
+    let indices = read_u32(accessor, buffer_data)?;
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+        return Err(AccessorError::IndexOutOfRange {
+            index: index as usize,
+            vertex_count,
+        });
+    }
+
+    Ok(match data_type {
+        // u8 is widened, there are no u8 index buffers without VK_EXT_index_type_uint8
+        DataType::U8 | DataType::U16 => {
+            IndexBufferType::U16(indices.into_iter().map(|i| i as u16).collect())
+        }
+        _ => IndexBufferType::U32(indices),
+    })
+}
+
+// Non-indexed (or unwelded for flat normals) primitives: merge bit-identical vertices and build
+// the index buffer referencing them, so meshlets and the post-transform cache get shared vertices.
+fn weld_vertices(
+    vertices: &[Vertex],
+    source_vertices: Option<&mut std::vec::Vec<u32>>,
+) -> (std::vec::Vec<Vertex>, IndexBufferType) {
+    // with source vertices, identical vertices of different sources are kept apart. They are
+    // remapped along.
+    let (unique_vertex_count, remap) = match &source_vertices {
+        Some(source_vertices) => meshopt::generate_vertex_remap_multi(
+            vertices.len(),
+            &[
+                meshopt::VertexStream::new(vertices.as_ptr()),
+                meshopt::VertexStream::new(source_vertices.as_ptr()),
+            ],
+            None,
+        ),
+        None => meshopt::generate_vertex_remap(vertices, None),
+    };
+    let indices = meshopt::remap_index_buffer(None, vertices.len(), &remap);
+    let welded = meshopt::remap_vertex_buffer(vertices, unique_vertex_count, &remap);
+    if let Some(source_vertices) = source_vertices {
+        *source_vertices =
+            meshopt::remap_vertex_buffer(source_vertices, unique_vertex_count, &remap);
+    }
+
+    (welded, IndexBufferType::U32(indices))
+}
+
+// Buffers and images read from files next to the glTF file, data URIs are embedded
+fn external_files(
+    document: &gltf::Document,
+    dir: &std::path::Path,
+) -> std::vec::Vec<std::path::PathBuf> {
+    let buffer_uris = document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => Some(uri),
+            gltf::buffer::Source::Bin => None,
+        });
+    let image_uris = document.images().filter_map(|image| match image.source() {
+        gltf::image::Source::Uri { uri, .. } => Some(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+    let mut files: std::vec::Vec<std::path::PathBuf> = vec![];
+    for uri in buffer_uris.chain(image_uris) {
+        if uri.starts_with("data:") {
+            continue;
+        }
+        if let Ok(uri) = urlencoding::decode(uri) {
+            let file = dir.join(uri.as_ref());
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    files
+}
+
+// FNV-1a, unlike DefaultHasher it's the same across Rust releases, so it can key files on disk
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl std::hash::Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
 }
 
-fn get_data_type(document: &gltf::Document, accessor_index: usize) -> gltf::accessor::DataType {
-    document
-        .accessors()
-        .find(|accessor| accessor.index() == accessor_index)
-        .expect("Failed to find accessor")
-        .data_type()
+#[cfg(test)]
+mod tests {
+    use super::super::testing::TestDir;
+    use super::*;
+    use serde_json::json;
+
+    // A glTF document with a single mesh and one binary buffer, built accessor by accessor
+    #[derive(Default)]
+    struct Fixture {
+        bin: std::vec::Vec<u8>,
+        buffer_views: std::vec::Vec<serde_json::Value>,
+        accessors: std::vec::Vec<serde_json::Value>,
+        primitives: std::vec::Vec<serde_json::Value>,
+    }
+
+    impl Fixture {
+        // `bytes` as a buffer view of their own, 4-byte aligned
+        fn view(&mut self, bytes: &[u8], stride: Option<usize>) -> usize {
+            self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+            let mut view = json!({
+                "buffer": 0,
+                "byteOffset": self.bin.len(),
+                "byteLength": bytes.len(),
+            });
+            if let Some(stride) = stride {
+                view["byteStride"] = stride.into();
+            }
+            self.bin.extend_from_slice(bytes);
+            self.buffer_views.push(view);
+            self.buffer_views.len() - 1
+        }
+
+        fn accessor(&mut self, accessor: serde_json::Value) -> usize {
+            self.accessors.push(accessor);
+            self.accessors.len() - 1
+        }
+
+        // tightly packed floats, with the min and max POSITION accessors need
+        fn floats(&mut self, values: &[f32], dimensions: &str, components: usize) -> usize {
+            let bytes: std::vec::Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            let view = self.view(&bytes, None);
+            let min: std::vec::Vec<f32> = (0..components)
+                .map(|c| {
+                    values
+                        .iter()
+                        .skip(c)
+                        .step_by(components)
+                        .copied()
+                        .fold(f32::MAX, f32::min)
+                })
+                .collect();
+            let max: std::vec::Vec<f32> = (0..components)
+                .map(|c| {
+                    values
+                        .iter()
+                        .skip(c)
+                        .step_by(components)
+                        .copied()
+                        .fold(f32::MIN, f32::max)
+                })
+                .collect();
+            self.accessor(json!({
+                "bufferView": view,
+                "componentType": 5126,
+                "count": values.len() / components,
+                "type": dimensions,
+                "min": min,
+                "max": max,
+            }))
+        }
+
+        // indices of `component_type`, 5121 u8, 5123 u16 or 5125 u32
+        fn indices(&mut self, indices: &[u32], component_type: u32) -> usize {
+            let bytes: std::vec::Vec<u8> = indices
+                .iter()
+                .flat_map(|&i| match component_type {
+                    5121 => vec![i as u8],
+                    5123 => (i as u16).to_le_bytes().to_vec(),
+                    _ => i.to_le_bytes().to_vec(),
+                })
+                .collect();
+            let view = self.view(&bytes, None);
+            self.accessor(json!({
+                "bufferView": view,
+                "componentType": component_type,
+                "count": indices.len(),
+                "type": "SCALAR",
+            }))
+        }
+
+        fn json(&self, buffer_uri: Option<std::string::String>) -> std::vec::Vec<u8> {
+            let mut buffer = json!({ "byteLength": self.bin.len() });
+            if let Some(uri) = buffer_uri {
+                buffer["uri"] = uri.into();
+            }
+            serde_json::to_vec(&json!({
+                "asset": { "version": "2.0" },
+                "buffers": [buffer],
+                "bufferViews": self.buffer_views,
+                "accessors": self.accessors,
+                "meshes": [{ "primitives": self.primitives }],
+                "nodes": [{ "mesh": 0 }],
+                "scenes": [{ "nodes": [0] }],
+                "scene": 0,
+            }))
+            .unwrap()
+        }
+
+        // parsed without touching the file system
+        fn document(&self) -> (gltf::Document, std::vec::Vec<gltf::buffer::Data>) {
+            let gltf = gltf::Gltf::from_slice(&self.json(None)).unwrap();
+            (gltf.document, vec![gltf::buffer::Data(self.bin.clone())])
+        }
+
+        // the buffer as a base64 data URI
+        fn write_embedded(&self, path: &std::path::Path) {
+            let uri = format!(
+                "data:application/octet-stream;base64,{}",
+                base64::encode(&self.bin)
+            );
+            std::fs::write(path, self.json(Some(uri))).unwrap();
+        }
+
+        // the buffer as a .bin file next to `path`
+        fn write_external(&self, path: &std::path::Path) {
+            let bin = path.with_extension("bin");
+            let uri = bin.file_name().unwrap().to_str().unwrap().to_owned();
+            std::fs::write(&bin, &self.bin).unwrap();
+            std::fs::write(path, self.json(Some(uri))).unwrap();
+        }
+
+        // binary glTF, the buffer in the BIN chunk
+        fn write_glb(&self, path: &std::path::Path) {
+            let mut json = self.json(None);
+            json.resize(json.len().next_multiple_of(4), b' ');
+            let mut bin = self.bin.clone();
+            bin.resize(bin.len().next_multiple_of(4), 0);
+            let length = 12 + 8 + json.len() + 8 + bin.len();
+
+            let mut glb = vec![];
+            glb.extend_from_slice(b"glTF");
+            glb.extend_from_slice(&2u32.to_le_bytes());
+            glb.extend_from_slice(&(length as u32).to_le_bytes());
+            glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+            glb.extend_from_slice(b"JSON");
+            glb.extend_from_slice(&json);
+            glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+            glb.extend_from_slice(b"BIN\0");
+            glb.extend_from_slice(&bin);
+            std::fs::write(path, glb).unwrap();
+        }
+
+        // without a cache, every load welds, widens and parses
+        fn load_embedded(&self, name: &str) -> GltfAssetData {
+            let dir = TestDir::new(name);
+            let path = dir.join(format!("{}.gltf", name));
+            self.write_embedded(&path);
+            GltfAssetData::with_options(&path, LoadOptions::default()).unwrap()
+        }
+    }
+
+    // a unit quad facing +Z, corners 0 1 2 3 in reading order from the bottom left
+    const QUAD_POSITIONS: [f32; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0];
+    const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 1, 3];
+
+    fn positions(asset: &GltfAssetData) -> std::vec::Vec<[f32; 3]> {
+        let primitive = &asset.meshes[0].primitives[0];
+        indices(primitive)
+            .iter()
+            .map(|&i| primitive.vertex_buffer[i as usize].position)
+            .collect()
+    }
+
+    fn indices(primitive: &Primitive) -> std::vec::Vec<u32> {
+        match &primitive.index_buffer {
+            IndexBufferType::U16(items) => items.iter().map(|i| *i as u32).collect(),
+            IndexBufferType::U32(items) => items.clone(),
+        }
+    }
+
+    #[test]
+    fn non_indexed_primitives_are_welded() {
+        let soup: std::vec::Vec<f32> = QUAD_INDICES
+            .iter()
+            .flat_map(|&i| QUAD_POSITIONS[i as usize * 3..i as usize * 3 + 3].to_vec())
+            .collect();
+        let normals: std::vec::Vec<f32> = [0.0, 0.0, 1.0].repeat(QUAD_INDICES.len());
+        let mut fixture = Fixture::default();
+        let position = fixture.floats(&soup, "VEC3", 3);
+        let normal = fixture.floats(&normals, "VEC3", 3);
+        fixture.primitives.push(json!({
+            "attributes": { "POSITION": position, "NORMAL": normal },
+        }));
+
+        let asset = fixture.load_embedded("soup");
+        let primitive = &asset.meshes[0].primitives[0];
+        assert_eq!(primitive.vertex_buffer.len(), 4);
+        // the corners in order of their first use
+        assert!(matches!(
+            &primitive.index_buffer,
+            IndexBufferType::U32(items) if items[..] == QUAD_INDICES
+        ));
+        // every corner still has its position
+        let expected: std::vec::Vec<[f32; 3]> =
+            soup.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+        assert_eq!(positions(&asset), expected);
+    }
+
+    #[test]
+    fn index_types_load_the_same() {
+        let loaded: std::vec::Vec<GltfAssetData> = [5121, 5123, 5125]
+            .into_iter()
+            .map(|component_type| {
+                let mut fixture = Fixture::default();
+                let position = fixture.floats(&QUAD_POSITIONS, "VEC3", 3);
+                let normal = fixture.floats(&[0.0, 0.0, 1.0].repeat(4), "VEC3", 3);
+                let indices = fixture.indices(&QUAD_INDICES, component_type);
+                fixture.primitives.push(json!({
+                    "attributes": { "POSITION": position, "NORMAL": normal },
+                    "indices": indices,
+                }));
+                fixture.load_embedded(&format!("indices_{}", component_type))
+            })
+            .collect();
+
+        for asset in &loaded {
+            let primitive = &asset.meshes[0].primitives[0];
+            assert_eq!(positions(asset), positions(&loaded[0]));
+            assert_eq!(
+                meshopt::typed_to_bytes(&primitive.vertex_buffer),
+                meshopt::typed_to_bytes(&loaded[0].meshes[0].primitives[0].vertex_buffer)
+            );
+        }
+        // u8 is widened to u16, u16 and u32 are kept
+        let expected_u16: std::vec::Vec<u16> = QUAD_INDICES.iter().map(|&i| i as u16).collect();
+        for asset in &loaded[..2] {
+            assert!(matches!(
+                &asset.meshes[0].primitives[0].index_buffer,
+                IndexBufferType::U16(items) if *items == expected_u16
+            ));
+        }
+        assert!(matches!(
+            &loaded[2].meshes[0].primitives[0].index_buffer,
+            IndexBufferType::U32(items) if items[..] == QUAD_INDICES
+        ));
+    }
+
+    #[test]
+    fn containers_load_the_same() {
+        let mut fixture = Fixture::default();
+        let position = fixture.floats(&QUAD_POSITIONS, "VEC3", 3);
+        let tex_coord = fixture.floats(&[0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0], "VEC2", 2);
+        let normal = fixture.floats(&[0.0, 0.0, 1.0].repeat(4), "VEC3", 3);
+        let index = fixture.indices(&QUAD_INDICES, 5123);
+        fixture.primitives.push(json!({
+            "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": tex_coord },
+            "indices": index,
+        }));
+        let dir = TestDir::new("containers");
+        let external = dir.join("external.gltf");
+        let embedded = dir.join("embedded.gltf");
+        let binary = dir.join("binary.glb");
+        fixture.write_external(&external);
+        fixture.write_embedded(&embedded);
+        fixture.write_glb(&binary);
+
+        // without a cache, so every container goes through its own buffer import
+        let loaded: std::vec::Vec<GltfAssetData> = [external, embedded, binary]
+            .iter()
+            .map(|path| GltfAssetData::with_options(path, LoadOptions::default()).unwrap())
+            .collect();
+        let quad: std::vec::Vec<[f32; 3]> = QUAD_INDICES
+            .iter()
+            .map(|&i| {
+                let i = i as usize * 3;
+                [
+                    QUAD_POSITIONS[i],
+                    QUAD_POSITIONS[i + 1],
+                    QUAD_POSITIONS[i + 2],
+                ]
+            })
+            .collect();
+        let expected = &loaded[0].meshes[0].primitives[0];
+        assert_eq!(expected.vertex_buffer.len(), 4);
+        for asset in &loaded {
+            assert_eq!(positions(asset), quad);
+            let primitive = &asset.meshes[0].primitives[0];
+            assert_eq!(
+                meshopt::typed_to_bytes(&primitive.vertex_buffer),
+                meshopt::typed_to_bytes(&expected.vertex_buffer)
+            );
+            assert_eq!(indices(primitive), indices(expected));
+        }
+        // only the external buffer is a file of its own
+        assert_eq!(loaded[0].sources.len(), 2);
+        assert_eq!(loaded[1].sources.len(), 1);
+        assert_eq!(loaded[2].sources.len(), 1);
+    }
+
+    #[test]
+    fn primitives_are_cached_only_in_a_cache_dir() {
+        let mut fixture = Fixture::default();
+        let position = fixture.floats(&QUAD_POSITIONS, "VEC3", 3);
+        let index = fixture.indices(&QUAD_INDICES, 5123);
+        fixture.primitives.push(json!({
+            "attributes": { "POSITION": position },
+            "indices": index,
+        }));
+        let dir = TestDir::new("cached");
+        let path = dir.join("cached.gltf");
+        fixture.write_embedded(&path);
+        let cache_dir = dir.join("cache");
+        let options = LoadOptions {
+            cache_dir: Some(cache_dir.clone()),
+            ..Default::default()
+        };
+
+        let uncached = GltfAssetData::new(&path).unwrap();
+        let cache_path = geometry_cache::path(&cache_dir, uncached.source_hash);
+        assert!(!cache_path.exists());
+        let built = GltfAssetData::with_options(&path, options.clone()).unwrap();
+        // the location is not part of the source
+        assert_eq!(built.source_hash, uncached.source_hash);
+        assert!(cache_path.exists());
+
+        let cached = GltfAssetData::with_options(&path, options).unwrap();
+        let (built, cached) = (
+            &built.meshes[0].primitives[0],
+            &cached.meshes[0].primitives[0],
+        );
+        assert_eq!(
+            meshopt::typed_to_bytes(&cached.vertex_buffer),
+            meshopt::typed_to_bytes(&built.vertex_buffer)
+        );
+        assert_eq!(indices(cached), indices(built));
+    }
+
+    fn read_fixture_f32(
+        fixture: &Fixture,
+        accessor: usize,
+    ) -> Result<std::vec::Vec<f32>, AccessorError> {
+        let (document, buffer_data) = fixture.document();
+        let accessor = document.accessors().nth(accessor).unwrap();
+        read_f32(&accessor, &buffer_data)
+    }
+
+    #[test]
+    fn accessors_skip_the_byte_stride_padding() {
+        // two vec3 interleaved with a float each
+        let bytes: std::vec::Vec<u8> = [1.0f32, 2.0, 3.0, -1.0, 4.0, 5.0, 6.0, -1.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let mut fixture = Fixture::default();
+        let view = fixture.view(&bytes, Some(16));
+        let accessor = fixture.accessor(json!({
+            "bufferView": view,
+            "componentType": 5126,
+            "count": 2,
+            "type": "VEC3",
+        }));
+        assert_eq!(
+            read_fixture_f32(&fixture, accessor).unwrap(),
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn normalized_integers_map_to_unit_range() {
+        let mut fixture = Fixture::default();
+        let u8_view = fixture.view(&[0, 51, 255, 128], None);
+        let u8_accessor = fixture.accessor(json!({
+            "bufferView": u8_view,
+            "componentType": 5121,
+            "normalized": true,
+            "count": 1,
+            "type": "VEC4",
+        }));
+        let u16_bytes: std::vec::Vec<u8> = [0u16, 65535, 13107]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let u16_view = fixture.view(&u16_bytes, None);
+        let u16_accessor = fixture.accessor(json!({
+            "bufferView": u16_view,
+            "componentType": 5123,
+            "normalized": true,
+            "count": 3,
+            "type": "SCALAR",
+        }));
+        let i8_view = fixture.view(&[0x80, 0x81, 0x7f, 0], None);
+        let i8_accessor = fixture.accessor(json!({
+            "bufferView": i8_view,
+            "componentType": 5120,
+            "normalized": true,
+            "count": 4,
+            "type": "SCALAR",
+        }));
+        // not normalized, integers are read as they are
+        let plain_accessor = fixture.accessor(json!({
+            "bufferView": u8_view,
+            "componentType": 5121,
+            "count": 1,
+            "type": "VEC4",
+        }));
+
+        assert_eq!(
+            read_fixture_f32(&fixture, u8_accessor).unwrap(),
+            [0.0, 0.2, 1.0, 128.0 / 255.0]
+        );
+        assert_eq!(
+            read_fixture_f32(&fixture, u16_accessor).unwrap(),
+            [0.0, 1.0, 0.2]
+        );
+        // -128 and -127 both map to -1
+        assert_eq!(
+            read_fixture_f32(&fixture, i8_accessor).unwrap(),
+            [-1.0, -1.0, 1.0, 0.0]
+        );
+        assert_eq!(
+            read_fixture_f32(&fixture, plain_accessor).unwrap(),
+            [0.0, 51.0, 255.0, 128.0]
+        );
+    }
+
+    #[test]
+    fn sparse_values_replace_their_elements() {
+        let mut fixture = Fixture::default();
+        let base = fixture.floats(&[1.0, 2.0, 3.0, 4.0], "SCALAR", 1);
+        let indices_view = fixture.view(&[1, 3], None);
+        let values: std::vec::Vec<u8> = [20.0f32, 40.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let values_view = fixture.view(&values, None);
+        let sparse = json!({
+            "count": 2,
+            "indices": { "bufferView": indices_view, "componentType": 5121 },
+            "values": { "bufferView": values_view },
+        });
+        fixture.accessors[base]["sparse"] = sparse.clone();
+        // without a buffer view, the elements start zeroed
+        let zeroed = fixture.accessor(json!({
+            "componentType": 5126,
+            "count": 4,
+            "type": "SCALAR",
+            "sparse": sparse,
+        }));
+
+        assert_eq!(
+            read_fixture_f32(&fixture, base).unwrap(),
+            [1.0, 20.0, 3.0, 40.0]
+        );
+        assert_eq!(
+            read_fixture_f32(&fixture, zeroed).unwrap(),
+            [0.0, 20.0, 0.0, 40.0]
+        );
+    }
+
+    #[test]
+    fn accessors_overrunning_their_view_are_errors() {
+        let mut fixture = Fixture::default();
+        let view = fixture.floats(&[1.0, 2.0, 3.0], "SCALAR", 1);
+        // a second view right after keeps the overrun inside the buffer
+        fixture.floats(&[4.0, 5.0], "SCALAR", 1);
+        let view = fixture.accessors[view]["bufferView"].as_u64().unwrap();
+        let overrun = fixture.accessor(json!({
+            "bufferView": view,
+            "byteOffset": 4,
+            "componentType": 5126,
+            "count": 3,
+            "type": "SCALAR",
+        }));
+
+        assert!(matches!(
+            read_fixture_f32(&fixture, overrun),
+            Err(AccessorError::OutsideView {
+                end: 16,
+                view_length: 12,
+                ..
+            })
+        ));
+
+        // a view past the end of its buffer
+        let (document, mut buffer_data) = fixture.document();
+        buffer_data[0].0.truncate(16);
+        let accessor = document.accessors().nth(1).unwrap();
+        assert!(matches!(
+            read_f32(&accessor, &buffer_data),
+            Err(AccessorError::OutOfBounds { end: 20, .. })
+        ));
+    }
 }
@@ -0,0 +1,159 @@
+use super::gltf_asset::{Node, Scene};
+use super::scene_nodes::global_transforms;
+use crate::vkutils;
+use crate::vkutils::vk_destroy::VkDestroy;
+use ash::vk;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightKind {
+    Directional,
+    Point,
+    // cone angles in radians
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+// KHR_lights_punctual light, placed in the world by the nodes referencing it
+#[derive(Clone)]
+pub struct Light {
+    pub _name: Option<std::string::String>,
+    pub kind: LightKind,
+    pub color: glm::Vec3,
+    // lux for directional lights, candela otherwise
+    pub intensity: f32,
+    pub range: Option<f32>,
+}
+
+// World space light, mirrors `PunctualLight` in descriptor_set_common.glsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct GPUPunctualLight {
+    // xyz world position, w range or 0 if unlimited
+    pub position: glm::Vec4,
+    // xyz normalized direction the light shines in, w one of the LIGHT_TYPE_* defines
+    pub direction: glm::Vec4,
+    // rgb color premultiplied with the intensity
+    pub color: glm::Vec4,
+    // x cos(outer cone angle), y 1 / (cos(inner) - cos(outer)), unused for other light types
+    pub spot: glm::Vec4,
+}
+
+impl Light {
+    pub fn from_gltf(light: &gltf::khr_lights_punctual::Light) -> Self {
+        let kind = match light.kind() {
+            gltf::khr_lights_punctual::Kind::Directional => LightKind::Directional,
+            gltf::khr_lights_punctual::Kind::Point => LightKind::Point,
+            gltf::khr_lights_punctual::Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            },
+        };
+
+        Self {
+            _name: light.name().map(|s| s.to_string()),
+            kind,
+            color: glm::make_vec3(&light.color()),
+            intensity: light.intensity(),
+            range: light.range(),
+        }
+    }
+
+    // `direction` normalized, the way the light shines
+    pub fn gpu_light(&self, position: &glm::Vec3, direction: &glm::Vec3) -> GPUPunctualLight {
+        let (light_type, spot) = match self.kind {
+            LightKind::Directional => (0.0, glm::Vec4::zeros()),
+            LightKind::Point => (1.0, glm::Vec4::zeros()),
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                let cos_outer = outer_cone_angle.cos();
+                let cos_inner = inner_cone_angle.cos();
+                // equal angles would divide by zero, give the cone a hard edge instead
+                let scale = 1.0 / (cos_inner - cos_outer).max(0.001);
+                (2.0, glm::vec4(cos_outer, scale, 0.0, 0.0))
+            }
+        };
+
+        GPUPunctualLight {
+            position: glm::vec4(
+                position.x,
+                position.y,
+                position.z,
+                self.range.unwrap_or(0.0),
+            ),
+            direction: glm::vec4(direction.x, direction.y, direction.z, light_type),
+            color: glm::vec4(
+                self.color.x * self.intensity,
+                self.color.y * self.intensity,
+                self.color.z * self.intensity,
+                0.0,
+            ),
+            spot,
+        }
+    }
+}
+
+// Every light node of the scene in world space, lights shine down their local -Z
+pub fn scene_lights(
+    nodes: &[Node],
+    lights: &[Light],
+    scene: &Scene,
+    root_transform: &glm::Mat4,
+) -> std::vec::Vec<GPUPunctualLight> {
+    let globals = global_transforms(nodes, root_transform);
+    let mut gpu_lights = vec![];
+    let mut stack = scene.nodes.clone();
+    while let Some(node_index) = stack.pop() {
+        let node = &nodes[node_index];
+        if let Some(light_index) = node.light_index {
+            let transform = &globals[node_index];
+            let position = transform.column(3).xyz();
+            let direction = (transform * glm::vec4(0.0, 0.0, -1.0, 0.0))
+                .xyz()
+                .normalize();
+            gpu_lights.push(lights[light_index].gpu_light(&position, &direction));
+        }
+        stack.extend(&node.children);
+    }
+    gpu_lights
+}
+
+// Lights of every loaded asset, uploaded once, shaders read `count` lights from `buffer`
+pub struct PunctualLights {
+    buffer: vkutils::buffer::Buffer,
+    pub count: u32,
+}
+
+impl PunctualLights {
+    pub fn new(ctx: &vkutils::context::VulkanContext, lights: &[GPUPunctualLight]) -> Self {
+        // zero-sized buffers are invalid, keep room for one light
+        let buffer = ctx.create_bar_buffer(
+            lights.len().max(1) * std::mem::size_of::<GPUPunctualLight>(),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        );
+        if !lights.is_empty() {
+            buffer.update_contents(lights);
+        }
+
+        Self {
+            buffer,
+            count: lights.len() as u32,
+        }
+    }
+
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        self.buffer.device_address.unwrap()
+    }
+}
+
+impl std::ops::Drop for PunctualLights {
+    fn drop(&mut self) {
+        self.buffer.vk_destroy();
+    }
+}
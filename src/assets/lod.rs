@@ -0,0 +1,226 @@
+use super::vertex::Vertex;
+
+// One simplification step of the LOD chain, see config::LodConfig
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LodLevel {
+    // of the base level's triangles
+    pub ratio: f32,
+    // relative to the primitive's extents
+    pub max_error: f32,
+}
+
+impl std::hash::Hash for LodLevel {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.ratio.to_bits().hash(state);
+        self.max_error.to_bits().hash(state);
+    }
+}
+
+// Simplified triangle list of a primitive, indexing its vertex buffer
+pub struct Lod {
+    pub indices: std::vec::Vec<u32>,
+    // object space distance the simplified surface may be off the original
+    pub error: f32,
+}
+
+// Object space, of the bind pose for skinned and morphed primitives
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingSphere {
+    pub center: glm::Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn of(vertices: &[Vertex]) -> Self {
+        let Some(first) = vertices.first() else {
+            return Self {
+                center: glm::Vec3::zeros(),
+                radius: 0.0,
+            };
+        };
+        let (min, max) = vertices.iter().fold(
+            (
+                glm::make_vec3(&first.position),
+                glm::make_vec3(&first.position),
+            ),
+            |(min, max), vertex| {
+                let position = glm::make_vec3(&vertex.position);
+                (glm::min2(&min, &position), glm::max2(&max, &position))
+            },
+        );
+        let center = (min + max) * 0.5;
+        let radius = vertices
+            .iter()
+            .map(|vertex| glm::distance(&center, &glm::make_vec3(&vertex.position)))
+            .fold(0.0, f32::max);
+        Self { center, radius }
+    }
+}
+
+// Coarser versions of a triangle list, one per level. A level that can't get below the previous
+// one's triangle count within its error bound ends the chain. Borders stay put, primitives of a
+// mesh split by material must not crack apart.
+pub fn build_lods(vertices: &[Vertex], indices: &[u32], levels: &[LodLevel]) -> std::vec::Vec<Lod> {
+    let adapter = meshopt::VertexDataAdapter::new(
+        meshopt::typed_to_bytes(vertices),
+        Vertex::STRIDE,
+        std::mem::offset_of!(Vertex, position),
+    )
+    .expect("Failed to create vertex adapter");
+    let scale = meshopt::simplify_scale(&adapter);
+
+    let mut lods: std::vec::Vec<Lod> = vec![];
+    for level in levels {
+        let source = lods.last().map_or(indices, |lod| lod.indices.as_slice());
+        let target_count = (indices.len() as f32 * level.ratio) as usize / 3 * 3;
+        let mut error = 0.0;
+        let mut simplified = meshopt::simplify(
+            source,
+            &adapter,
+            target_count,
+            level.max_error,
+            meshopt::SimplifyOptions::LockBorder,
+            Some(&mut error),
+        );
+        if simplified.is_empty() || simplified.len() >= source.len() {
+            break;
+        }
+        meshopt::optimize_vertex_cache_in_place(&mut simplified, vertices.len());
+        // errors of a chain add up, each level is simplified from the previous one
+        let previous_error = lods.last().map_or(0.0, |lod| lod.error);
+        lods.push(Lod {
+            indices: simplified,
+            error: previous_error + error * scale,
+        });
+    }
+    lods
+}
+
+// What LOD selection needs to know about the view camera
+pub struct LodView {
+    pub projview: glm::Mat4,
+    pub viewport_height: f32,
+    // largest error on screen a level may have
+    pub threshold_pixels: f32,
+    // the GUI override, clamped to the coarsest level a primitive has
+    pub forced_level: Option<usize>,
+    // the meshlet path draws the cluster hierarchy instead of a level unless one is forced, see
+    // cluster_lod
+    pub cluster_hierarchy: bool,
+}
+
+impl LodView {
+    // Coarsest level whose error projects below the threshold. `errors` are object space, one
+    // per level with the full resolution one at 0.
+    pub fn select(
+        &self,
+        errors: &[f32],
+        bounds: &BoundingSphere,
+        world_transform: &glm::Mat4,
+    ) -> usize {
+        let coarsest = errors.len() - 1;
+        if let Some(level) = self.forced_level {
+            return level.min(coarsest);
+        }
+
+        let scale = (0..3)
+            .map(|column| world_transform.column(column).xyz().norm())
+            .fold(0.0, f32::max);
+        let center =
+            world_transform * glm::vec4(bounds.center.x, bounds.center.y, bounds.center.z, 1.0);
+        // w is the view depth of perspective projections, the nearest point of the sphere
+        // decides. Orthographic projections are 1 everywhere.
+        let w = match self.is_orthographic() {
+            true => 1.0,
+            false => glm::dot(&self.projview.row(3).transpose(), &center) - bounds.radius * scale,
+        };
+        if w <= 0.0 {
+            return 0;
+        }
+        let pixels_per_unit = self.pixels_per_unit() / w;
+
+        errors
+            .iter()
+            .rposition(|error| error * scale * pixels_per_unit <= self.threshold_pixels)
+            .unwrap_or(0)
+    }
+
+    // Whether the meshlet path draws the cluster hierarchy. The task shader divides cluster errors
+    // by their distance, orthographic views draw a level like the traditional path instead.
+    pub fn draws_cluster_hierarchy(&self) -> bool {
+        self.cluster_hierarchy && self.forced_level.is_none() && !self.is_orthographic()
+    }
+
+    // Scales an error over its distance to the camera to a multiple of the threshold, what the
+    // task shader compares cluster errors with
+    pub fn cluster_error_scale(&self) -> f32 {
+        self.pixels_per_unit() / self.threshold_pixels
+    }
+
+    // the last row of a perspective projection picks the view depth as w
+    fn is_orthographic(&self) -> bool {
+        self.projview.row(3) == glm::vec4(0.0, 0.0, 0.0, 1.0).transpose()
+    }
+
+    // at depth 1, also right for orthographic projections
+    fn pixels_per_unit(&self) -> f32 {
+        let ndc_per_unit = self.projview.row(1).transpose().xyz().norm();
+        ndc_per_unit * self.viewport_height * 0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(projview: glm::Mat4) -> LodView {
+        LodView {
+            projview,
+            viewport_height: 1000.0,
+            threshold_pixels: 1.0,
+            forced_level: None,
+            cluster_hierarchy: true,
+        }
+    }
+
+    fn sphere_at(z: f32) -> glm::Mat4 {
+        glm::translation(&glm::vec3(0.0, 0.0, z))
+    }
+
+    #[test]
+    fn orthographic_views_select_levels_regardless_of_distance() {
+        let orthographic = view(glm::ortho_zo(-10.0, 10.0, -10.0, 10.0, 0.1, 1000.0));
+        assert!(!orthographic.draws_cluster_hierarchy());
+        // 50 pixels per unit, level 1 projects to 0.5, level 2 to 5 pixels
+        let errors = [0.0, 0.01, 0.1];
+        let bounds = BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 2.0,
+        };
+        for z in [-1.0, -100.0, -900.0] {
+            assert_eq!(orthographic.select(&errors, &bounds, &sphere_at(z)), 1);
+        }
+    }
+
+    #[test]
+    fn perspective_views_coarsen_with_distance() {
+        let perspective = view(glm::perspective_zo(1.0, 1.0, 0.1, 1000.0));
+        assert!(perspective.draws_cluster_hierarchy());
+        let errors = [0.0, 0.01, 0.1];
+        let bounds = BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 1.0,
+        };
+        let levels: std::vec::Vec<usize> = [-2.0, -20.0, -900.0]
+            .iter()
+            .map(|&z| perspective.select(&errors, &bounds, &sphere_at(z)))
+            .collect();
+        assert_eq!(levels, [0, 1, 2]);
+        // a forced level is drawn as it is
+        let forced = LodView {
+            forced_level: Some(1),
+            ..perspective
+        };
+        assert!(!forced.draws_cluster_hierarchy());
+    }
+}
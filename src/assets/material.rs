@@ -0,0 +1,189 @@
+use crate::vkutils;
+use ash::vk;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlphaMode {
+    Opaque = 0,
+    Mask = 1,
+    Blend = 2,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureRef {
+    pub texture_index: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Clone)]
+pub struct Material {
+    pub _name: Option<std::string::String>,
+    pub base_color_factor: glm::Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: glm::Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    // glTF 2.0 spec, 3.9.6 "Default Material"
+    fn default() -> Self {
+        Self {
+            _name: None,
+            base_color_factor: glm::make_vec4(&[1.0, 1.0, 1.0, 1.0]),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_factor: glm::make_vec3(&[0.0, 0.0, 0.0]),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+impl Material {
+    pub fn from_gltf(material: &gltf::Material) -> Self {
+        let pbr = material.pbr_metallic_roughness();
+        let texture_ref = |info: Option<gltf::texture::Info>| {
+            info.map(|info| TextureRef {
+                texture_index: info.texture().index(),
+                tex_coord: info.tex_coord(),
+            })
+        };
+
+        let normal_texture = material.normal_texture();
+        let occlusion_texture = material.occlusion_texture();
+
+        Self {
+            _name: material.name().map(|s| s.to_string()),
+            base_color_factor: glm::make_vec4(&pbr.base_color_factor()),
+            base_color_texture: texture_ref(pbr.base_color_texture()),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            metallic_roughness_texture: texture_ref(pbr.metallic_roughness_texture()),
+            normal_texture: normal_texture.as_ref().map(|t| TextureRef {
+                texture_index: t.texture().index(),
+                tex_coord: t.tex_coord(),
+            }),
+            normal_scale: normal_texture.as_ref().map_or(1.0, |t| t.scale()),
+            occlusion_texture: occlusion_texture.as_ref().map(|t| TextureRef {
+                texture_index: t.texture().index(),
+                tex_coord: t.tex_coord(),
+            }),
+            occlusion_strength: occlusion_texture.as_ref().map_or(1.0, |t| t.strength()),
+            emissive_factor: glm::make_vec3(&material.emissive_factor()),
+            emissive_texture: texture_ref(material.emissive_texture()),
+            alpha_mode: match material.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+            },
+            alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            double_sided: material.double_sided(),
+        }
+    }
+}
+
+// Mirrors `Material` in descriptor_set_common.glsl (std430)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GPUMaterial {
+    base_color_factor: glm::Vec4,
+    emissive_factor: glm::Vec4, // w unused
+    metallic_factor: f32,
+    roughness_factor: f32,
+    alpha_cutoff: f32,
+    alpha_mode: u32,
+    double_sided: u32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    // bit N set - texture N (in field order below) samples TEXCOORD_1 instead of TEXCOORD_0
+    tex_coord_sets: u32,
+    // bindless texture slots, NO_TEXTURE if absent
+    base_color_texture: i32,
+    metallic_roughness_texture: i32,
+    normal_texture: i32,
+    occlusion_texture: i32,
+    emissive_texture: i32,
+    _padding: [u32; 3],
+}
+
+const NO_TEXTURE: i32 = -1;
+
+impl GPUMaterial {
+    // texture_slots maps glTF texture indices to bindless slots, see Textures
+    fn new(material: &Material, texture_slots: &[u32]) -> Self {
+        let e = material.emissive_factor;
+        let slots = [
+            material.base_color_texture,
+            material.metallic_roughness_texture,
+            material.normal_texture,
+            material.occlusion_texture,
+            material.emissive_texture,
+        ];
+        let texture = |slot: usize| {
+            slots[slot].map_or(NO_TEXTURE, |texture| {
+                texture_slots[texture.texture_index] as i32
+            })
+        };
+        let tex_coord_sets = slots
+            .iter()
+            .enumerate()
+            .filter(|(_, texture)| texture.is_some_and(|texture| texture.tex_coord > 0))
+            .fold(0, |bits, (slot, _)| bits | (1 << slot));
+
+        Self {
+            base_color_factor: material.base_color_factor,
+            emissive_factor: glm::make_vec4(&[e.x, e.y, e.z, 0.0]),
+            metallic_factor: material.metallic_factor,
+            roughness_factor: material.roughness_factor,
+            alpha_cutoff: material.alpha_cutoff,
+            alpha_mode: material.alpha_mode as u32,
+            double_sided: material.double_sided as u32,
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+            tex_coord_sets,
+            base_color_texture: texture(0),
+            metallic_roughness_texture: texture(1),
+            normal_texture: texture(2),
+            occlusion_texture: texture(3),
+            emissive_texture: texture(4),
+            _padding: [0; 3],
+        }
+    }
+}
+
+pub(super) fn upload_materials(
+    ctx: &vkutils::context::VulkanContext,
+    materials: &[Material],
+    texture_slots: &[u32],
+) -> vkutils::buffer::Buffer {
+    let mut gpu_materials: Vec<GPUMaterial> = materials
+        .iter()
+        .map(|material| GPUMaterial::new(material, texture_slots))
+        .collect();
+    // zero-sized buffers are invalid, keep at least one entry around
+    if gpu_materials.is_empty() {
+        gpu_materials.push(GPUMaterial::new(&Material::default(), texture_slots));
+    }
+
+    ctx.upload_buffer(
+        &gpu_materials,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+    )
+}
@@ -1,59 +1,121 @@
 use meshopt::ffi::meshopt_Meshlet;
 
+use super::lod::BoundingSphere;
+use super::meshlet_vertex::QuantizationGrid;
+use super::vertex::Vertex;
 use crate::vkutils::{self, vk_destroy::VkDestroy};
 
+// Mirrors MeshletBounds in descriptor_set_meshlet.glsl
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct MeshletBounds {
-    center: glm::Vec3,
-    radius: f32,
-    cone_apex: glm::Vec3,
-    cone_cutoff: f32,
-    cone_axis: glm::Vec3,
-    // TODO its actually signed int - I wanted to avoid another glsl extensions
-    remaining_cone_data: f32,
+    pub center: glm::Vec3,
+    pub radius: f32,
+    // meshopt's snorm8 normal cone, conservative for the test against the bounding sphere
+    cone_axis: [i8; 3],
+    cone_cutoff: i8,
+    // grid point the meshlet's positions are offsets from, see meshlet_vertex::QuantizationGrid
+    pub origin: [u32; 3],
 }
 
-pub struct Meshlet {
+// meshopt builder settings, cached meshlets are only valid for the ones they were built with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeshletParams {
+    pub max_vertices: u32,
+    pub max_triangles: u32,
+    pub cone_weight: f32,
+}
+
+// TODO revise max vertices and triangle count - fix in shaders as well
+// TODO use cone weight, when implementing cone culling
+pub const MESHLET_PARAMS: MeshletParams = MeshletParams {
+    max_vertices: 64,
+    max_triangles: 124,
+    cone_weight: 0.5,
+};
+
+// Meshlets of one level of a primitive's LOD chain, or of its whole cluster hierarchy
+pub struct MeshletLod {
     pub meshlet_buffer: vkutils::buffer::Buffer,
-    pub vertex_buffer: vkutils::buffer::Buffer,
     pub meshlet_vertices: vkutils::buffer::Buffer,
+    // meshlet_vertex::PackedPosition, parallel to meshlet_vertices
+    pub positions_buffer: vkutils::buffer::Buffer,
     pub triangle_buffer: vkutils::buffer::Buffer,
     pub meshlet_bounds_buffer: vkutils::buffer::Buffer,
+    // cluster_lod::ClusterLodBounds, only the hierarchy has them
+    pub lod_bounds_buffer: Option<vkutils::buffer::Buffer>,
     pub meshlets_count: u32,
     pub bounds_count: u32,
 }
 
-impl std::ops::Drop for Meshlet {
+impl std::ops::Drop for MeshletLod {
     fn drop(&mut self) {
         self.meshlet_buffer.vk_destroy();
-        self.vertex_buffer.vk_destroy();
         self.meshlet_vertices.vk_destroy();
+        self.positions_buffer.vk_destroy();
         self.triangle_buffer.vk_destroy();
         self.meshlet_bounds_buffer.vk_destroy();
+        if let Some(lod_bounds_buffer) = &self.lod_bounds_buffer {
+            lod_bounds_buffer.vk_destroy();
+        }
+    }
+}
+
+pub struct Meshlet {
+    // meshlet_vertex::PackedVertex, positions are packed per level
+    pub vertex_buffer: vkutils::buffer::Buffer,
+    // the full resolution level first, all of them index vertex_buffer
+    pub lods: std::vec::Vec<MeshletLod>,
+    // object space error of every level, 0 for the full resolution one
+    pub lod_errors: std::vec::Vec<f32>,
+    // every level of the cluster hierarchy, the task shader picks the cut. None if loaded
+    // without LoadOptions::cluster_lod.
+    pub hierarchy: Option<MeshletLod>,
+    // what positions of every level and the hierarchy are quantized to
+    pub grid: QuantizationGrid,
+    pub bounding_sphere: BoundingSphere,
+    // morph::MorphDelta, None without morph targets
+    pub morph_deltas: Option<vkutils::buffer::Buffer>,
+    pub morph_target_count: u32,
+    pub material_index: u32,
+}
+
+impl std::ops::Drop for Meshlet {
+    fn drop(&mut self) {
+        self.vertex_buffer.vk_destroy();
+        if let Some(morph_deltas) = &self.morph_deltas {
+            morph_deltas.vk_destroy();
+        }
     }
 }
 
 pub fn build_meshlets2(
-    vertices: &std::vec::Vec<f32>,
+    vertices: &std::vec::Vec<Vertex>,
     indices: &std::vec::Vec<u32>,
 ) -> (meshopt::Meshlets, std::vec::Vec<MeshletBounds>) {
-    let vertices_slice = unsafe {
-        std::slice::from_raw_parts(
-            vertices.as_ptr() as *const u8,
-            vertices.len() * std::mem::size_of::<f32>(),
-        )
-    };
-    // TODO this kurwa stride is giga bad, consider using strongly typed vector
-    let vertex_adapter =
-        meshopt::VertexDataAdapter::new(vertices_slice, std::mem::size_of::<f32>() * 8, 0)
-            .expect("Failed to create vertex adapter");
+    let vertex_adapter = meshopt::VertexDataAdapter::new(
+        meshopt::typed_to_bytes(vertices),
+        Vertex::STRIDE,
+        std::mem::offset_of!(Vertex, position),
+    )
+    .expect("Failed to create vertex adapter");
 
-    // TODO revise max vertices and triangle count - fix in shaders as well
-    // TODO use cone weight, when implementing cone culling
-    let mut meshopt_meshlets =
-        meshopt::build_meshlets(indices.as_slice(), &vertex_adapter, 64, 124, 0.5);
+    let meshopt_meshlets = meshopt::build_meshlets(
+        indices.as_slice(),
+        &vertex_adapter,
+        MESHLET_PARAMS.max_vertices as usize,
+        MESHLET_PARAMS.max_triangles as usize,
+        MESHLET_PARAMS.cone_weight,
+    );
+    finish_meshlets(&vertex_adapter, meshopt_meshlets)
+}
 
+// Optimizes meshlets within MESHLET_PARAMS' limits, computes their culling bounds and pads them
+// for the draw
+pub fn finish_meshlets(
+    vertex_adapter: &meshopt::VertexDataAdapter,
+    mut meshopt_meshlets: meshopt::Meshlets,
+) -> (meshopt::Meshlets, std::vec::Vec<MeshletBounds>) {
     // TODO does it really work?
     for meshlet in meshopt_meshlets.meshlets.iter_mut() {
         unsafe {
@@ -74,17 +136,14 @@ pub fn build_meshlets2(
 
     let mut meshlets_bounds = vec![];
 
-    // TODO repack the data, to reduce size. Cone data perhaps unnecessary
     for meshlet in meshopt_meshlets.iter() {
-        let meshlet_bounds = meshopt::compute_meshlet_bounds(meshlet, &vertex_adapter);
-        // Repack the data, cuz sth is wrong with padding somewhere
+        let meshlet_bounds = meshopt::compute_meshlet_bounds(meshlet, vertex_adapter);
         meshlets_bounds.push(MeshletBounds {
             center: glm::make_vec3(&meshlet_bounds.center),
             radius: meshlet_bounds.radius,
-            cone_apex: glm::make_vec3(&meshlet_bounds.cone_apex),
-            cone_cutoff: meshlet_bounds.cone_cutoff,
-            cone_axis: glm::make_vec3(&meshlet_bounds.cone_axis),
-            remaining_cone_data: 0.0f32,
+            cone_axis: meshlet_bounds.cone_axis_s8,
+            cone_cutoff: meshlet_bounds.cone_cutoff_s8,
+            origin: [0; 3],
         });
     }
 
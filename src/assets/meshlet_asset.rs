@@ -1,7 +1,17 @@
-use super::gltf_asset::{GltfAssetData, IndexBufferType, Node, Scene};
+use super::animation::Pose;
+use super::cluster_lod::{build_cluster_hierarchy, ClusterLodBounds};
+use super::gltf_asset::{GltfAssetData, IndexBufferType, LoadOptions, Node, Scene};
+use super::lod::LodView;
+use super::material::upload_materials;
 use super::mesh::{Mesh, Primitives};
-use super::meshlet::{build_meshlets2, Meshlet};
+use super::meshlet::{build_meshlets2, Meshlet, MeshletLod};
+use super::meshlet_cache::{self, PrimitiveClusters, PrimitiveMeshlets};
+use super::meshlet_vertex::{pack_positions, PackedVertex, QuantizationGrid, VertexMemory};
+use super::morph::MorphWeights;
 use super::scene_nodes::{build_node_transformation_data, SceneNodesBuffers};
+use super::skin::{Skin, SkinPalettes};
+use super::texture::Textures;
+use super::vertex::Vertex;
 use crate::vkutils;
 use crate::vkutils::push_constants::GPUPushConstantsMeshlet;
 use crate::vkutils::vk_destroy::VkDestroy;
@@ -13,72 +23,122 @@ struct MeshletDraw {
     pub transform: vk::DeviceAddress, // TransformBuf  — model matrix
     pub meshlets: vk::DeviceAddress,  // MeshletBuf
     pub vertices: vk::DeviceAddress,  // VertexBuf
+    pub positions: vk::DeviceAddress, // MeshletPositionBuf
     pub vertex_indices: vk::DeviceAddress, // VertexIndexBuf
     pub tri_indices: vk::DeviceAddress, // TriangleIndexBuf
     pub bounds: vk::DeviceAddress,    // MeshletBoundsBuf
+    pub joints: vk::DeviceAddress,    // JointBuf, unused if joint_count is 0
+    pub morph_deltas: vk::DeviceAddress, // MorphDeltaBuf, unused if morph_target_count is 0
+    pub morph_weights: vk::DeviceAddress, // MorphWeightBuf
+    pub lod_bounds: vk::DeviceAddress, // ClusterLodBoundsBuf, only for the hierarchy
     pub meshlets_count: u32,
+    pub material_index: u32,
+    pub joint_count: u32,
+    pub morph_target_count: u32,
+    pub lod_error_scale: f32, // LodView::cluster_error_scale, 0 draws every meshlet
+    pub grid: QuantizationGrid, // what MeshletPositionBuf offsets are steps on
+    _padding: u32,
+}
+
+// Draws of a scene, one per mesh node and primitive. Both buffers stay mapped, select_lods swaps
+// the meshlets of the picked level in.
+struct SceneDraws {
+    instance_buffer: vkutils::buffer::Buffer,
+    indirect_buffer: vkutils::buffer::Buffer,
+    draws: Vec<MeshletDraw>,
+    commands: Vec<vk::DrawMeshTasksIndirectCommandEXT>,
+    // node, mesh and primitive of every draw
+    instances: Vec<(usize, usize, usize)>,
+}
+
+impl std::ops::Drop for SceneDraws {
+    fn drop(&mut self) {
+        self.instance_buffer.vk_destroy();
+        self.indirect_buffer.vk_destroy();
+    }
 }
 
 pub struct MeshletAsset {
-    pub _meshes: Vec<Mesh>,
-    pub default_scene: Option<usize>,
-    _node_transform_data: Vec<SceneNodesBuffers>,
-    instance_buffers: Vec<vkutils::buffer::Buffer>,
-    indirect_buffers: Vec<(vkutils::buffer::Buffer, usize)>,
+    pub meshes: Vec<Mesh>,
+    // scene the passes record, the glTF default one unless switched at runtime
+    pub current_scene: usize,
+    node_transform_data: Vec<SceneNodesBuffers>,
+    // bind/rest pose, animations are applied on top of a copy
+    rest_nodes: Vec<Node>,
+    nodes: Vec<Node>,
+    skins: Vec<Skin>,
+    skin_palettes: Vec<SkinPalettes>,
+    morph_weights: Vec<MorphWeights>,
+    materials_buffer: vkutils::buffer::Buffer,
+    scene_draws: Vec<SceneDraws>,
+    pub vertex_memory: VertexMemory,
 }
 
 impl MeshletAsset {
-    pub fn from_gltf(ctx: &vkutils::context::VulkanContext, asset_data: &GltfAssetData) -> Self {
+    // `built_meshlets` from load_or_build_meshlets, kept apart so they can be built on a worker
+    // thread
+    pub fn from_gltf(
+        ctx: &vkutils::context::VulkanContext,
+        asset_data: &GltfAssetData,
+        built_meshlets: Vec<Vec<PrimitiveClusters>>,
+        textures: &Textures,
+        transform: &glm::Mat4,
+    ) -> Self {
         let mut meshes: Vec<Mesh> = vec![];
+        let mut vertex_memory = VertexMemory::default();
 
-        for mesh in &asset_data.meshes {
+        for (mesh, mesh_meshlets) in std::iter::zip(&asset_data.meshes, built_meshlets) {
             let mut primitives: Vec<Meshlet> = vec![];
 
-            for primitive in &mesh.primitives {
+            for (primitive, clusters) in std::iter::zip(&mesh.primitives, mesh_meshlets) {
                 let vertex_data = &primitive.vertex_buffer;
-                let index_data: Vec<u32> = match &primitive.index_buffer {
-                    IndexBufferType::U16(items) => {
-                        items.iter().map(|u16val| *u16val as u32).collect()
-                    }
-                    IndexBufferType::U32(items) => items.clone(),
-                };
-
-                let (meshlets, bounds) = build_meshlets2(&vertex_data, &index_data);
+                let packed_vertices: Vec<PackedVertex> =
+                    vertex_data.iter().map(PackedVertex::pack).collect();
 
-                let meshlet_buffer = ctx.upload_buffer(
-                    &meshlets.meshlets,
-                    vk::BufferUsageFlags::STORAGE_BUFFER
-                        | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-                );
                 let vertex_buffer = ctx.upload_buffer(
-                    &vertex_data,
-                    vk::BufferUsageFlags::STORAGE_BUFFER
-                        | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-                );
-                let meshlet_vertices = ctx.upload_buffer(
-                    &meshlets.vertices,
-                    vk::BufferUsageFlags::STORAGE_BUFFER
-                        | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-                );
-                let triangle_buffer = ctx.upload_buffer(
-                    &meshlets.triangles,
-                    vk::BufferUsageFlags::STORAGE_BUFFER
-                        | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-                );
-                let meshlet_bounds_buffer = ctx.upload_buffer(
-                    &bounds,
+                    &packed_vertices,
                     vk::BufferUsageFlags::STORAGE_BUFFER
                         | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
                 );
+                vertex_memory.add_vertices(vertex_data.len());
+                for meshlets in clusters.levels.iter().chain(&clusters.hierarchy) {
+                    vertex_memory
+                        .add_meshlets(meshlets.meshlets.vertices.len(), meshlets.bounds.len());
+                }
+                let lods = clusters
+                    .levels
+                    .iter()
+                    .map(|level| upload_meshlets(ctx, vertex_data, level, None, &clusters.grid))
+                    .collect();
+                let hierarchy = clusters.hierarchy.as_ref().map(|hierarchy| {
+                    upload_meshlets(
+                        ctx,
+                        vertex_data,
+                        hierarchy,
+                        Some(&clusters.hierarchy_lod_bounds),
+                        &clusters.grid,
+                    )
+                });
+                let morph_deltas = (!primitive.morph_deltas.is_empty()).then(|| {
+                    ctx.upload_buffer(
+                        &primitive.morph_deltas,
+                        vk::BufferUsageFlags::STORAGE_BUFFER
+                            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                    )
+                });
 
                 primitives.push(Meshlet {
-                    meshlet_buffer,
                     vertex_buffer,
-                    meshlet_vertices,
-                    triangle_buffer,
-                    meshlet_bounds_buffer,
-                    meshlets_count: meshlets.len() as u32,
-                    bounds_count: bounds.len() as u32,
+                    lods,
+                    lod_errors: std::iter::once(0.0)
+                        .chain(primitive.lods.iter().map(|lod| lod.error))
+                        .collect(),
+                    hierarchy,
+                    grid: clusters.grid,
+                    bounding_sphere: primitive.bounding_sphere,
+                    morph_deltas,
+                    morph_target_count: primitive.morph_target_count as u32,
+                    material_index: primitive.material_index as u32,
                 });
             }
             meshes.push(Mesh {
@@ -87,38 +147,117 @@ impl MeshletAsset {
             });
         }
 
+        let materials_buffer = upload_materials(ctx, &asset_data.materials, &textures.slots);
+
         let nodes: Vec<Node> = asset_data.nodes.clone();
         let scenes: Vec<Scene> = asset_data.scenes.clone();
 
         println!("Meshes count: {} (Meshlet)", meshes.len());
 
+        let skins: Vec<Skin> = asset_data.skins.clone();
+
         let mut node_transform_data = vec![];
-        let mut instance_buffers = vec![];
-        let mut indirect_buffers = vec![];
+        let mut skin_palettes = vec![];
+        let mut morph_weights = vec![];
+        let mut scene_draws = vec![];
 
         for scene in &scenes {
-            let transform_data = build_node_transformation_data(ctx, &mut meshes, &nodes, &scene);
+            let transform_data =
+                build_node_transformation_data(ctx, &mut meshes, &nodes, scene, transform);
+            let palettes = SkinPalettes::new(ctx, &nodes, &skins, scene);
+            let weights = MorphWeights::new(ctx, &nodes, scene);
 
-            let instances_buffer = build_instance_data(
+            let draws = build_scene_draws(
                 ctx,
                 &transform_data.node_transform_buffer_address,
+                &palettes,
+                &skins,
+                &weights,
                 &meshes,
                 &nodes,
             );
-            let draw_buffer = build_buffer_for_indirect_draw(ctx, &meshes, &nodes);
 
-            instance_buffers.push(instances_buffer);
-            indirect_buffers.push(draw_buffer);
+            scene_draws.push(draws);
             node_transform_data.push(transform_data);
+            skin_palettes.push(palettes);
+            morph_weights.push(weights);
         }
 
         Self {
-            _meshes: meshes,
-            default_scene: None,
-            _node_transform_data: node_transform_data,
-            instance_buffers,
-            indirect_buffers,
+            meshes,
+            current_scene: asset_data.default_scene.unwrap_or(0),
+            node_transform_data,
+            rest_nodes: nodes.clone(),
+            nodes,
+            skins,
+            skin_palettes,
+            morph_weights,
+            materials_buffer,
+            scene_draws,
+            vertex_memory,
+        }
+    }
+
+    // Poses the node hierarchy from the rest pose and rewrites the model matrices, joint palettes
+    // and morph weights
+    pub fn pose(&mut self, pose: &Pose) {
+        self.nodes.clone_from(&self.rest_nodes);
+        for (node_index, weights) in pose.morph_weights {
+            self.nodes[*node_index].weights.clone_from(weights);
+        }
+        if let Some(animation) = pose.animation {
+            animation.apply(&mut self.nodes, pose.time);
+        }
+
+        for transform_data in &self.node_transform_data {
+            transform_data.update(&self.nodes);
+        }
+        for palettes in &self.skin_palettes {
+            palettes.update(&self.nodes, &self.skins);
+        }
+        for weights in &self.morph_weights {
+            weights.update(&self.nodes);
+        }
+    }
+
+    // Points every draw of the current scene at the cluster hierarchy, or at the meshlets of the
+    // level `view` picks for its node. Returns the number of hierarchy draws. The buffers are
+    // rewritten in place, nothing may be in flight.
+    pub fn select_lods(&mut self, view: &LodView) -> usize {
+        let Some(scene_draws) = self.scene_draws.get_mut(self.current_scene) else {
+            return 0;
+        };
+        let mut hierarchy_draws = 0;
+        let globals = self.node_transform_data[self.current_scene].world_transforms(&self.nodes);
+        for ((draw, command), &(node_index, mesh_index, primitive_index)) in std::iter::zip(
+            std::iter::zip(&mut scene_draws.draws, &mut scene_draws.commands),
+            &scene_draws.instances,
+        ) {
+            let Primitives::Meshlets(meshlets) = &self.meshes[mesh_index].primitives else {
+                continue;
+            };
+            let meshlet = &meshlets[primitive_index];
+            if let (true, Some(hierarchy)) = (view.draws_cluster_hierarchy(), &meshlet.hierarchy) {
+                set_level(draw, command, hierarchy, view.cluster_error_scale());
+                hierarchy_draws += 1;
+                continue;
+            }
+            let level = view.select(
+                &meshlet.lod_errors,
+                &meshlet.bounding_sphere,
+                &globals[node_index],
+            );
+            set_level(draw, command, &meshlet.lods[level], 0.0);
+        }
+        if !scene_draws.draws.is_empty() {
+            scene_draws
+                .instance_buffer
+                .update_contents(&scene_draws.draws);
+            scene_draws
+                .indirect_buffer
+                .update_contents(&scene_draws.commands);
         }
+        hierarchy_draws
     }
 
     pub fn draw_scene(
@@ -130,13 +269,16 @@ impl MeshletAsset {
         pipeline_layout: vk::PipelineLayout,
         push_constants: &mut GPUPushConstantsMeshlet,
     ) {
-        push_constants.meshlet_draws = self.instance_buffers[scene_index].device_address.unwrap();
-        let (buf, draws_count) = &self.indirect_buffers[scene_index];
+        let scene_draws = &self.scene_draws[scene_index];
+        push_constants.meshlet_draws = scene_draws.instance_buffer.device_address.unwrap();
+        push_constants.materials = self.materials_buffer.device_address.unwrap();
         unsafe {
             device.cmd_push_constants(
                 command_buffer,
                 pipeline_layout,
-                vk::ShaderStageFlags::TASK_EXT | vk::ShaderStageFlags::MESH_EXT,
+                vk::ShaderStageFlags::TASK_EXT
+                    | vk::ShaderStageFlags::MESH_EXT
+                    | vk::ShaderStageFlags::FRAGMENT,
                 0,
                 std::slice::from_raw_parts(
                     (push_constants as *const GPUPushConstantsMeshlet) as *const u8,
@@ -145,9 +287,9 @@ impl MeshletAsset {
             );
             mesh_shader_device.cmd_draw_mesh_tasks_indirect(
                 command_buffer,
-                buf.handle,
+                scene_draws.indirect_buffer.handle,
                 0,
-                *draws_count as u32,
+                scene_draws.commands.len() as u32,
                 12,
             );
         }
@@ -156,71 +298,238 @@ impl MeshletAsset {
 
 impl std::ops::Drop for MeshletAsset {
     fn drop(&mut self) {
-        for buf in &self.instance_buffers {
-            buf.vk_destroy();
-        }
-        for (buf, _) in &self.indirect_buffers {
-            buf.vk_destroy();
-        }
+        self.materials_buffer.vk_destroy();
     }
 }
 
-fn build_instance_data(
-    ctx: &vkutils::context::VulkanContext,
-    node_transform_buffer_address: &std::collections::HashMap<usize, vk::DeviceAddress>,
-    meshes: &Vec<Mesh>,
-    nodes: &Vec<Node>,
-) -> vkutils::buffer::Buffer {
-    let mut meshlet_draws = vec![];
-
-    for (node_index, node) in nodes.iter().enumerate() {
-        if let Some(mesh_index) = node.mesh_index {
-            let mesh = meshes.iter().nth(mesh_index).unwrap();
-            if let Primitives::Meshlets(meshlets) = &mesh.primitives {
-                for meshlet in meshlets {
-                    let draw = MeshletDraw {
-                        transform: *node_transform_buffer_address.get(&node_index).unwrap()
-                            as vk::DeviceAddress,
-                        meshlets: meshlet.meshlet_buffer.device_address.unwrap(),
-                        vertices: meshlet.vertex_buffer.device_address.unwrap(),
-                        vertex_indices: meshlet.meshlet_vertices.device_address.unwrap(),
-                        tri_indices: meshlet.triangle_buffer.device_address.unwrap(),
-                        bounds: meshlet.meshlet_bounds_buffer.device_address.unwrap(),
-                        meshlets_count: meshlet.bounds_count,
-                    };
-                    meshlet_draws.push(draw);
+// Meshlets of every LOD level and the cluster hierarchy per mesh and primitive, from the cache if
+// it was built from the same source and builder parameters. Building refreshes the cache.
+// The hierarchy is only built with LoadOptions::cluster_lod, it is part of the source hash.
+// Nothing is cached without LoadOptions::cache_dir. `on_meshlets_built` gets the meshlet count of
+// every level and hierarchy as it is ready.
+pub fn load_or_build_meshlets(
+    asset_data: &GltfAssetData,
+    options: &LoadOptions,
+    on_meshlets_built: &mut dyn FnMut(usize),
+) -> Vec<Vec<PrimitiveClusters>> {
+    let cache_path = options
+        .cache_dir
+        .as_deref()
+        .map(|dir| meshlet_cache::path(dir, asset_data.source_hash));
+    let shapes: Vec<Vec<(usize, usize)>> = asset_data
+        .meshes
+        .iter()
+        .map(|mesh| {
+            mesh.primitives
+                .iter()
+                .map(|primitive| (primitive.vertex_buffer.len(), primitive.lods.len() + 1))
+                .collect()
+        })
+        .collect();
+    if let Some(cache_path) = &cache_path {
+        match meshlet_cache::load(cache_path, asset_data.source_hash, &shapes) {
+            Ok(meshes) => {
+                println!("Meshlets loaded from {}", cache_path.display());
+                for primitive in meshes.iter().flatten() {
+                    for level in &primitive.levels {
+                        on_meshlets_built(level.meshlets.len());
+                    }
+                    if let Some(hierarchy) = &primitive.hierarchy {
+                        on_meshlets_built(hierarchy.meshlets.len());
+                    }
                 }
+                return meshes;
             }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => println!("Rebuilding meshlets, {}: {}", cache_path.display(), err),
         }
     }
 
-    ctx.upload_buffer(
-        &meshlet_draws,
-        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-    )
+    let meshes: Vec<Vec<PrimitiveClusters>> = asset_data
+        .meshes
+        .iter()
+        .map(|mesh| {
+            mesh.primitives
+                .iter()
+                .map(|primitive| {
+                    let index_data: Vec<u32> = match &primitive.index_buffer {
+                        IndexBufferType::U16(items) => {
+                            items.iter().map(|u16val| *u16val as u32).collect()
+                        }
+                        IndexBufferType::U32(items) => items.clone(),
+                    };
+                    let mut levels: Vec<PrimitiveMeshlets> = std::iter::once(&index_data)
+                        .chain(primitive.lods.iter().map(|lod| &lod.indices))
+                        .map(|indices| {
+                            let (meshlets, bounds) =
+                                build_meshlets2(&primitive.vertex_buffer, indices);
+                            on_meshlets_built(meshlets.len());
+                            PrimitiveMeshlets { meshlets, bounds }
+                        })
+                        .collect();
+                    let (mut hierarchy, hierarchy_lod_bounds) = if options.cluster_lod {
+                        let (meshlets, bounds, lod_bounds) =
+                            build_cluster_hierarchy(&primitive.vertex_buffer, &index_data);
+                        on_meshlets_built(meshlets.len());
+                        (Some(PrimitiveMeshlets { meshlets, bounds }), lod_bounds)
+                    } else {
+                        (None, vec![])
+                    };
+
+                    // one grid for all of them, a vertex decodes the same in every meshlet
+                    let grid = QuantizationGrid::new(
+                        &primitive.vertex_buffer,
+                        levels.iter().chain(&hierarchy).map(|level| &level.meshlets),
+                    );
+                    for level in levels.iter_mut().chain(&mut hierarchy) {
+                        grid.set_origins(
+                            &primitive.vertex_buffer,
+                            &level.meshlets,
+                            &mut level.bounds,
+                        );
+                    }
+                    PrimitiveClusters {
+                        levels,
+                        hierarchy,
+                        hierarchy_lod_bounds,
+                        grid,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    if let Some(cache_path) = &cache_path {
+        meshlet_cache::save_best_effort(cache_path, || {
+            meshlet_cache::save(cache_path, asset_data.source_hash, &meshes)
+        });
+    }
+    meshes
 }
 
-fn build_buffer_for_indirect_draw(
+fn upload_meshlets(
     ctx: &vkutils::context::VulkanContext,
-    meshes: &Vec<Mesh>,
-    nodes: &Vec<Node>,
-) -> (vkutils::buffer::Buffer, usize) {
+    vertices: &[Vertex],
+    PrimitiveMeshlets { meshlets, bounds }: &PrimitiveMeshlets,
+    lod_bounds: Option<&Vec<ClusterLodBounds>>,
+    grid: &QuantizationGrid,
+) -> MeshletLod {
+    let usage = vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+    MeshletLod {
+        meshlet_buffer: ctx.upload_buffer(&meshlets.meshlets, usage),
+        meshlet_vertices: ctx.upload_buffer(&meshlets.vertices, usage),
+        positions_buffer: ctx
+            .upload_buffer(&pack_positions(vertices, meshlets, bounds, grid), usage),
+        triangle_buffer: ctx.upload_buffer(&meshlets.triangles, usage),
+        meshlet_bounds_buffer: ctx.upload_buffer(bounds, usage),
+        lod_bounds_buffer: lod_bounds.map(|lod_bounds| ctx.upload_buffer(lod_bounds, usage)),
+        meshlets_count: meshlets.len() as u32,
+        bounds_count: bounds.len() as u32,
+    }
+}
+
+// Full resolution until select_lods runs
+fn build_scene_draws(
+    ctx: &vkutils::context::VulkanContext,
+    node_transform_buffer_address: &std::collections::HashMap<usize, vk::DeviceAddress>,
+    skin_palettes: &SkinPalettes,
+    skins: &[Skin],
+    morph_weights: &MorphWeights,
+    meshes: &[Mesh],
+    nodes: &[Node],
+) -> SceneDraws {
     let mut draws = vec![];
-    for node in nodes {
+    let mut commands = vec![];
+    let mut instances = vec![];
+
+    for (node_index, node) in nodes.iter().enumerate() {
+        // nodes of other scenes have no transform
+        let Some(&transform) = node_transform_buffer_address.get(&node_index) else {
+            continue;
+        };
         if let Some(mesh_index) = node.mesh_index {
-            let mesh = meshes.iter().nth(mesh_index).unwrap();
+            let mesh = &meshes[mesh_index];
             if let Primitives::Meshlets(meshlets) = &mesh.primitives {
-                for meshlet in meshlets {
-                    draws.push(vk::DrawMeshTasksIndirectCommandEXT {
-                        group_count_x: meshlet.meshlets_count / 64,
+                let (joints, joint_count) =
+                    skin_palettes.joints(node_index, skins).unwrap_or((0, 0));
+                let weights = morph_weights.weights(node_index).unwrap_or(0);
+                for (primitive_index, meshlet) in meshlets.iter().enumerate() {
+                    let mut draw = MeshletDraw {
+                        transform,
+                        meshlets: 0,
+                        vertices: meshlet.vertex_buffer.device_address.unwrap(),
+                        positions: 0,
+                        vertex_indices: 0,
+                        tri_indices: 0,
+                        bounds: 0,
+                        joints,
+                        morph_deltas: meshlet
+                            .morph_deltas
+                            .as_ref()
+                            .map_or(0, |buffer| buffer.device_address.unwrap()),
+                        morph_weights: weights,
+                        lod_bounds: 0,
+                        meshlets_count: 0,
+                        material_index: meshlet.material_index,
+                        joint_count,
+                        morph_target_count: meshlet.morph_target_count,
+                        lod_error_scale: 0.0,
+                        grid: meshlet.grid,
+                        _padding: 0,
+                    };
+                    let mut command = vk::DrawMeshTasksIndirectCommandEXT {
+                        group_count_x: 0,
                         group_count_y: 1,
                         group_count_z: 1,
-                    });
+                    };
+                    set_level(&mut draw, &mut command, &meshlet.lods[0], 0.0);
+                    draws.push(draw);
+                    commands.push(command);
+                    instances.push((node_index, mesh_index, primitive_index));
                 }
             }
         }
     }
 
-    let buffer = ctx.upload_buffer(&draws, vk::BufferUsageFlags::INDIRECT_BUFFER);
-    (buffer, draws.len())
+    // zero-sized buffers are invalid, keep room for one draw around
+    let instance_buffer = ctx.create_bar_buffer(
+        draws.len().max(1) * std::mem::size_of::<MeshletDraw>(),
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+    );
+    let indirect_buffer = ctx.create_bar_buffer(
+        commands.len().max(1) * std::mem::size_of::<vk::DrawMeshTasksIndirectCommandEXT>(),
+        vk::BufferUsageFlags::INDIRECT_BUFFER,
+    );
+    if !draws.is_empty() {
+        instance_buffer.update_contents(&draws);
+        indirect_buffer.update_contents(&commands);
+    }
+    SceneDraws {
+        instance_buffer,
+        indirect_buffer,
+        draws,
+        commands,
+        instances,
+    }
+}
+
+fn set_level(
+    draw: &mut MeshletDraw,
+    command: &mut vk::DrawMeshTasksIndirectCommandEXT,
+    lod: &MeshletLod,
+    lod_error_scale: f32,
+) {
+    draw.meshlets = lod.meshlet_buffer.device_address.unwrap();
+    draw.vertex_indices = lod.meshlet_vertices.device_address.unwrap();
+    draw.positions = lod.positions_buffer.device_address.unwrap();
+    draw.tri_indices = lod.triangle_buffer.device_address.unwrap();
+    draw.bounds = lod.meshlet_bounds_buffer.device_address.unwrap();
+    draw.lod_bounds = lod
+        .lod_bounds_buffer
+        .as_ref()
+        .map_or(0, |buffer| buffer.device_address.unwrap());
+    draw.meshlets_count = lod.bounds_count;
+    draw.lod_error_scale = lod_error_scale;
+    // build_meshlets2 pads the meshlets to whole task workgroups
+    command.group_count_x = lod.meshlets_count / 64;
 }
@@ -0,0 +1,467 @@
+// On-disk cache of build_meshlets2 output, so unchanged assets skip meshlet building on startup.
+//
+// One file per source hash in LoadOptions::cache_dir, native endianness, a cache doesn't move
+// between machines:
+//   magic "GRMC", version: u32, source hash: u64 (the header geometry_cache shares), MeshletParams,
+//   mesh count: u32
+//   per mesh: primitive count: u32
+//     per primitive: LOD level count: u32, the full resolution level first
+//       per level: meshlet, vertex, triangle byte and bounds counts: u32 each, followed by the
+//       meshlets, meshlet vertices (indices into the primitive's vertices), triangles and bounds
+//     whether the cluster hierarchy follows: u32, 0 or 1, then the hierarchy like a level and its
+//     LOD bounds count: u32 and LOD bounds
+//     the quantization grid the bounds' origins are on: 4 f32
+
+use super::cluster_lod::ClusterLodBounds;
+use super::meshlet::{MeshletBounds, MeshletParams, MESHLET_PARAMS};
+use super::meshlet_vertex::QuantizationGrid;
+use std::io::prelude::*;
+
+const MAGIC: [u8; 4] = *b"GRMC";
+// bump whenever the file layout, the glTF loader or the meshlet builder output changes
+const VERSION: u32 = 7;
+// numbers temporary files, loader threads may cache assets sharing a source at the same time
+static SAVE_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+// build_meshlets2 output of one LOD level of a primitive
+pub struct PrimitiveMeshlets {
+    pub meshlets: meshopt::Meshlets,
+    pub bounds: std::vec::Vec<MeshletBounds>,
+}
+
+// What the meshlet path draws a primitive with
+pub struct PrimitiveClusters {
+    // discrete LOD levels, the full resolution one first
+    pub levels: std::vec::Vec<PrimitiveMeshlets>,
+    // cluster_lod::build_cluster_hierarchy output, all of its levels in one. None without
+    // LoadOptions::cluster_lod.
+    pub hierarchy: Option<PrimitiveMeshlets>,
+    // parallel to the hierarchy's bounds, empty without one
+    pub hierarchy_lod_bounds: std::vec::Vec<ClusterLodBounds>,
+    // the origins in the bounds of the levels and the hierarchy are points on it
+    pub grid: QuantizationGrid,
+}
+
+// Next to the executable, wherever it is started from. None if its path is unknown.
+pub fn default_dir() -> Option<std::path::PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join("meshlet-cache"))
+}
+
+pub fn path(dir: &std::path::Path, source_hash: u64) -> std::path::PathBuf {
+    cache_path(dir, source_hash, "meshlets")
+}
+
+pub(super) fn cache_path(
+    dir: &std::path::Path,
+    source_hash: u64,
+    extension: &str,
+) -> std::path::PathBuf {
+    dir.join(format!("{:016x}.{}", source_hash, extension))
+}
+
+// Clusters per mesh and primitive. `primitive_shapes` has the vertex and level count of every
+// primitive per mesh, a cache that doesn't match it, or the source hash and builder parameters,
+// is stale.
+pub fn load(
+    path: &std::path::Path,
+    source_hash: u64,
+    primitive_shapes: &[std::vec::Vec<(usize, usize)>],
+) -> std::io::Result<std::vec::Vec<std::vec::Vec<PrimitiveClusters>>> {
+    let mut reader = open(path, MAGIC, source_hash)?;
+    let params = MeshletParams {
+        max_vertices: reader.read_u32()?,
+        max_triangles: reader.read_u32()?,
+        cone_weight: reader.read_array::<f32>(1)?[0],
+    };
+    if params != MESHLET_PARAMS {
+        return Err(invalid_data(format!("built with {:?}", params)));
+    }
+
+    if reader.read_u32()? as usize != primitive_shapes.len() {
+        return Err(invalid_data("mesh count mismatch"));
+    }
+    let mut meshes = vec![];
+    for shapes in primitive_shapes {
+        if reader.read_u32()? as usize != shapes.len() {
+            return Err(invalid_data("primitive count mismatch"));
+        }
+        let mut primitives = vec![];
+        for &(vertex_count, level_count) in shapes {
+            if reader.read_u32()? as usize != level_count {
+                return Err(invalid_data("LOD level count mismatch"));
+            }
+            let mut levels = vec![];
+            for _ in 0..level_count {
+                levels.push(read_meshlets(&mut reader, vertex_count)?);
+            }
+            let (hierarchy, hierarchy_lod_bounds) = match reader.read_u32()? {
+                0 => (None, vec![]),
+                1 => {
+                    let hierarchy = read_meshlets(&mut reader, vertex_count)?;
+                    let lod_bounds_count = reader.read_u32()? as usize;
+                    if lod_bounds_count != hierarchy.bounds.len() {
+                        return Err(invalid_data("LOD bounds count mismatch"));
+                    }
+                    (Some(hierarchy), reader.read_array(lod_bounds_count)?)
+                }
+                _ => return Err(invalid_data("invalid hierarchy flag")),
+            };
+            primitives.push(PrimitiveClusters {
+                levels,
+                hierarchy,
+                hierarchy_lod_bounds,
+                grid: reader.read_array(1)?[0],
+            });
+        }
+        meshes.push(primitives);
+    }
+    Ok(meshes)
+}
+
+pub fn save(
+    path: &std::path::Path,
+    source_hash: u64,
+    meshes: &[std::vec::Vec<PrimitiveClusters>],
+) -> std::io::Result<()> {
+    write_atomically(path, MAGIC, source_hash, |writer| {
+        write_clusters(writer, &MESHLET_PARAMS, meshes)
+    })
+}
+
+fn write_clusters(
+    writer: &mut impl Write,
+    params: &MeshletParams,
+    meshes: &[std::vec::Vec<PrimitiveClusters>],
+) -> std::io::Result<()> {
+    writer.write_all(&params.max_vertices.to_ne_bytes())?;
+    writer.write_all(&params.max_triangles.to_ne_bytes())?;
+    writer.write_all(&params.cone_weight.to_ne_bytes())?;
+    writer.write_all(&(meshes.len() as u32).to_ne_bytes())?;
+    for primitives in meshes {
+        writer.write_all(&(primitives.len() as u32).to_ne_bytes())?;
+        for primitive in primitives {
+            writer.write_all(&(primitive.levels.len() as u32).to_ne_bytes())?;
+            for level in &primitive.levels {
+                write_meshlets(writer, level)?;
+            }
+            writer.write_all(&(primitive.hierarchy.is_some() as u32).to_ne_bytes())?;
+            if let Some(hierarchy) = &primitive.hierarchy {
+                write_meshlets(writer, hierarchy)?;
+                let lod_bounds = &primitive.hierarchy_lod_bounds;
+                writer.write_all(&(lod_bounds.len() as u32).to_ne_bytes())?;
+                writer.write_all(meshopt::typed_to_bytes(lod_bounds))?;
+            }
+            writer.write_all(meshopt::typed_to_bytes(std::slice::from_ref(
+                &primitive.grid,
+            )))?;
+        }
+    }
+    Ok(())
+}
+
+// Runs `save` and only logs its failure, a read-only or full disk only costs the next startup its
+// time
+pub(super) fn save_best_effort(path: &std::path::Path, save: impl FnOnce() -> std::io::Result<()>) {
+    if let Err(err) = save() {
+        eprintln!("Failed to write {}: {}", path.display(), err);
+    }
+}
+
+// A reader past the header of a cache file of `magic` built from `source_hash` by this VERSION
+pub(super) fn open(
+    path: &std::path::Path,
+    magic: [u8; 4],
+    source_hash: u64,
+) -> std::io::Result<Reader> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = Reader {
+        remaining: file.metadata()?.len(),
+        inner: std::io::BufReader::new(file),
+    };
+
+    if reader.read_array::<u8>(magic.len())? != magic {
+        return Err(invalid_data("not a cache of this kind"));
+    }
+    let version = reader.read_u32()?;
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "version {}, expected {}",
+            version, VERSION
+        )));
+    }
+    if reader.read_array::<u64>(1)?[0] != source_hash {
+        return Err(invalid_data("built from a different source"));
+    }
+    Ok(reader)
+}
+
+// The header followed by what `write` writes. Written to a temporary file first, a crash never
+// leaves a torn cache behind.
+pub(super) fn write_atomically(
+    path: &std::path::Path,
+    magic: [u8; 4],
+    source_hash: u64,
+    write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let save_index = SAVE_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let tmp_path = path.with_extension(format!("{}.tmp", save_index));
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+
+    writer.write_all(&magic)?;
+    writer.write_all(&VERSION.to_ne_bytes())?;
+    writer.write_all(&source_hash.to_ne_bytes())?;
+    write(&mut writer)?;
+
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+fn read_meshlets(reader: &mut Reader, vertex_count: usize) -> std::io::Result<PrimitiveMeshlets> {
+    let meshlet_count = reader.read_u32()? as usize;
+    let vertices_count = reader.read_u32()? as usize;
+    let triangles_count = reader.read_u32()? as usize;
+    let bounds_count = reader.read_u32()? as usize;
+    let meshlets = PrimitiveMeshlets {
+        meshlets: meshopt::Meshlets {
+            meshlets: reader.read_array(meshlet_count)?,
+            vertices: reader.read_array(vertices_count)?,
+            triangles: reader.read_array(triangles_count)?,
+        },
+        bounds: reader.read_array(bounds_count)?,
+    };
+    validate(&meshlets, vertex_count)?;
+    Ok(meshlets)
+}
+
+fn write_meshlets(writer: &mut impl Write, primitive: &PrimitiveMeshlets) -> std::io::Result<()> {
+    let meshlets = &primitive.meshlets;
+    for count in [
+        meshlets.meshlets.len(),
+        meshlets.vertices.len(),
+        meshlets.triangles.len(),
+        primitive.bounds.len(),
+    ] {
+        writer.write_all(&(count as u32).to_ne_bytes())?;
+    }
+    writer.write_all(meshopt::typed_to_bytes(&meshlets.meshlets))?;
+    writer.write_all(meshopt::typed_to_bytes(&meshlets.vertices))?;
+    writer.write_all(&meshlets.triangles)?;
+    writer.write_all(meshopt::typed_to_bytes(&primitive.bounds))
+}
+
+// The shaders trust meshlet offsets, triangles and vertex indices, a corrupt cache must not reach
+// the GPU
+fn validate(primitive: &PrimitiveMeshlets, vertex_count: usize) -> std::io::Result<()> {
+    let meshlets = &primitive.meshlets;
+    let in_bounds = meshlets.meshlets.iter().all(|meshlet| {
+        let vertices =
+            meshlet.vertex_offset as usize..(meshlet.vertex_offset + meshlet.vertex_count) as usize;
+        let triangles = meshlet.triangle_offset as usize
+            ..meshlet.triangle_offset as usize + meshlet.triangle_count as usize * 3;
+        vertices.end <= meshlets.vertices.len()
+            && triangles.end <= meshlets.triangles.len()
+            // u8 indices into the meshlet's vertices
+            && meshlets.triangles[triangles]
+                .iter()
+                .all(|&index| u32::from(index) < meshlet.vertex_count)
+    });
+    if !in_bounds
+        || meshlets
+            .vertices
+            .iter()
+            .any(|&v| v as usize >= vertex_count)
+    {
+        return Err(invalid_data("meshlets reach out of bounds"));
+    }
+    // the task shader runs once per bounds, bounds without a meshlet would draw past the meshlets
+    if primitive.bounds.len() > meshlets.meshlets.len() {
+        return Err(invalid_data("more bounds than meshlets"));
+    }
+    Ok(())
+}
+
+pub(super) fn invalid_data(reason: impl Into<std::string::String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.into())
+}
+
+pub(super) struct Reader {
+    inner: std::io::BufReader<std::fs::File>,
+    // bytes left in the file, so corrupt counts fail instead of allocating huge arrays
+    remaining: u64,
+}
+
+impl Reader {
+    pub fn read_u32(&mut self) -> std::io::Result<u32> {
+        Ok(self.read_array::<u32>(1)?[0])
+    }
+
+    // T must be plain old data, valid for any bit pattern
+    pub fn read_array<T: Copy>(&mut self, count: usize) -> std::io::Result<std::vec::Vec<T>> {
+        let size = count as u64 * std::mem::size_of::<T>() as u64;
+        if size > self.remaining {
+            return Err(invalid_data("truncated"));
+        }
+        self.remaining -= size;
+
+        let mut array = std::vec::Vec::<T>::with_capacity(count);
+        unsafe {
+            std::ptr::write_bytes(array.as_mut_ptr(), 0, count);
+            array.set_len(count);
+            self.inner.read_exact(std::slice::from_raw_parts_mut(
+                array.as_mut_ptr() as *mut u8,
+                size as usize,
+            ))?;
+        }
+        Ok(array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cluster_lod::build_cluster_hierarchy;
+    use super::super::meshlet::build_meshlets2;
+    use super::super::testing::{temp_file, wavy_grid};
+    use super::*;
+
+    const SOURCE_HASH: u64 = 0x0123_4567_89ab_cdef;
+
+    // one mesh with one primitive of a full resolution level and the hierarchy
+    fn clusters(n: u32) -> (std::vec::Vec<std::vec::Vec<PrimitiveClusters>>, usize) {
+        let (vertices, indices) = wavy_grid(n);
+        let (meshlets, mut bounds) = build_meshlets2(&vertices, &indices);
+        let (hierarchy, mut hierarchy_bounds, hierarchy_lod_bounds) =
+            build_cluster_hierarchy(&vertices, &indices);
+        let quantization_grid = QuantizationGrid::new(&vertices, [&meshlets, &hierarchy]);
+        quantization_grid.set_origins(&vertices, &meshlets, &mut bounds);
+        quantization_grid.set_origins(&vertices, &hierarchy, &mut hierarchy_bounds);
+        let primitive = PrimitiveClusters {
+            levels: vec![PrimitiveMeshlets { meshlets, bounds }],
+            hierarchy: Some(PrimitiveMeshlets {
+                meshlets: hierarchy,
+                bounds: hierarchy_bounds,
+            }),
+            hierarchy_lod_bounds,
+            grid: quantization_grid,
+        };
+        (vec![vec![primitive]], vertices.len())
+    }
+
+    fn meshlet_bytes(meshlets: &PrimitiveMeshlets) -> std::vec::Vec<u8> {
+        let mut bytes = vec![];
+        write_meshlets(&mut bytes, meshlets).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn saved_clusters_load_the_same() {
+        let (meshes, vertex_count) = clusters(24);
+        let (_dir, path) = temp_file("round_trip.meshlets");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        let loaded = load(&path, SOURCE_HASH, &[vec![(vertex_count, 1)]]).unwrap();
+
+        let (expected, loaded) = (&meshes[0][0], &loaded[0][0]);
+        assert_eq!(loaded.levels.len(), 1);
+        assert_eq!(
+            meshlet_bytes(&loaded.levels[0]),
+            meshlet_bytes(&expected.levels[0])
+        );
+        assert_eq!(
+            meshlet_bytes(loaded.hierarchy.as_ref().unwrap()),
+            meshlet_bytes(expected.hierarchy.as_ref().unwrap())
+        );
+        assert_eq!(
+            meshopt::typed_to_bytes(&loaded.hierarchy_lod_bounds),
+            meshopt::typed_to_bytes(&expected.hierarchy_lod_bounds)
+        );
+        assert_eq!(loaded.grid, expected.grid);
+    }
+
+    #[test]
+    fn clusters_without_hierarchy_load_without_one() {
+        let (mut meshes, vertex_count) = clusters(8);
+        meshes[0][0].hierarchy = None;
+        meshes[0][0].hierarchy_lod_bounds.clear();
+        let (_dir, path) = temp_file("no_hierarchy.meshlets");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        let loaded = load(&path, SOURCE_HASH, &[vec![(vertex_count, 1)]]).unwrap();
+        assert!(loaded[0][0].hierarchy.is_none());
+        assert!(loaded[0][0].hierarchy_lod_bounds.is_empty());
+    }
+
+    #[test]
+    fn stale_caches_are_rejected() {
+        let (meshes, vertex_count) = clusters(8);
+        let shapes = [vec![(vertex_count, 1)]];
+        let (_dir, path) = temp_file("stale_source.meshlets");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        assert!(load(&path, SOURCE_HASH + 1, &shapes).is_err());
+        // the glTF changed its vertex or level count under the same hash
+        assert!(load(&path, SOURCE_HASH, &[vec![(vertex_count - 1, 1)]]).is_err());
+        assert!(load(&path, SOURCE_HASH, &[vec![(vertex_count, 2)]]).is_err());
+
+        let (_dir, path) = temp_file("stale_params.meshlets");
+        let params = MeshletParams {
+            max_vertices: MESHLET_PARAMS.max_vertices / 2,
+            ..MESHLET_PARAMS
+        };
+        write_atomically(&path, MAGIC, SOURCE_HASH, |writer| {
+            write_clusters(writer, &params, &meshes)
+        })
+        .unwrap();
+        assert!(load(&path, SOURCE_HASH, &shapes).is_err());
+    }
+
+    #[test]
+    fn truncated_caches_are_rejected() {
+        let (meshes, vertex_count) = clusters(8);
+        let shapes = [vec![(vertex_count, 1)]];
+        let (_dir, path) = temp_file("truncated.meshlets");
+        save(&path, SOURCE_HASH, &meshes).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        // every prefix, cut in the header, a count or an array
+        for length in (0..bytes.len()).step_by(7) {
+            std::fs::write(&path, &bytes[..length]).unwrap();
+            assert!(load(&path, SOURCE_HASH, &shapes).is_err(), "{}", length);
+        }
+        std::fs::write(&path, b"not a meshlet cache at all").unwrap();
+        assert!(load(&path, SOURCE_HASH, &shapes).is_err());
+    }
+
+    #[test]
+    fn corrupt_meshlets_are_rejected() {
+        let corruptions: [fn(&mut PrimitiveMeshlets); 4] = [
+            |level| level.meshlets.vertices[0] = u32::MAX,
+            |level| level.meshlets.meshlets[0].vertex_count += 1000,
+            // a triangle index past the meshlet's vertices, still within the vertex array
+            |level| {
+                let vertex_count = level.meshlets.meshlets[0].vertex_count;
+                level.meshlets.triangles[0] = vertex_count as u8;
+            },
+            |level| {
+                let meshlet_count = level.meshlets.meshlets.len();
+                level.bounds.resize(meshlet_count + 1, level.bounds[0]);
+            },
+        ];
+        for (i, corrupt) in corruptions.iter().enumerate() {
+            let (mut meshes, vertex_count) = clusters(8);
+            corrupt(&mut meshes[0][0].levels[0]);
+            let (_dir, path) = temp_file(&format!("corrupt_{}.meshlets", i));
+            save(&path, SOURCE_HASH, &meshes).unwrap();
+            let loaded = load(&path, SOURCE_HASH, &[vec![(vertex_count, 1)]]);
+            assert_eq!(
+                loaded.err().map(|err| err.kind()),
+                Some(std::io::ErrorKind::InvalidData),
+                "corruption {}",
+                i
+            );
+        }
+    }
+}
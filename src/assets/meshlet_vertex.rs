@@ -0,0 +1,490 @@
+// Compact vertex data of the meshlet path, unpack_vertex in descriptor_set_meshlet.glsl decodes it.
+// Attributes are packed once per vertex: octahedral normals and tangents, half float texture
+// coordinates, 8-bit colors and 16-bit joints and weights. Positions are packed per meshlet vertex
+// instead, as 16-bit offsets from the meshlet's origin on a grid shared by every meshlet of the
+// primitive, levels and hierarchy included. The grid is as fine as 16 bits allow across the
+// largest meshlet, and a vertex shared by meshlets lands on the same grid point in all of them, so
+// it decodes bit-identically and meshlet borders don't crack.
+
+use super::meshlet::MeshletBounds;
+use super::vertex::Vertex;
+
+// offsets span at most this many steps, with room for rounding on both ends
+const GRID_STEPS: f32 = 65533.0;
+
+// Mirrors PackedVertex in descriptor_set_meshlet.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PackedVertex {
+    // octahedral snorm16
+    normal: [i16; 2],
+    tangent: [i16; 2],
+    // half floats
+    tex_coord: [u16; 2],
+    tex_coord1: [u16; 2],
+    // unorm8
+    color: [u8; 4],
+    joints: [u16; 4],
+    // unorm16
+    weights: [u16; 4],
+}
+
+// Mirrors MeshletPositionBuf in descriptor_set_meshlet.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PackedPosition {
+    // grid steps from MeshletBounds::origin
+    offset: [u16; 3],
+    // snorm16, of the tangent
+    bitangent_sign: i16,
+}
+
+// Positions of a primitive's meshlets are base + grid point * step, mirrored by the grid fields of
+// MeshletDraw in descriptor_set_meshlet.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct QuantizationGrid {
+    // the corner of the primitive's bounding box, grid points are never negative
+    base: glm::Vec3,
+    step: f32,
+}
+
+impl PackedVertex {
+    pub fn pack(vertex: &Vertex) -> Self {
+        Self {
+            normal: encode_octahedral(&glm::make_vec3(&vertex.normal)),
+            tangent: encode_octahedral(&glm::make_vec3(&vertex.tangent[..3])),
+            tex_coord: vertex.tex_coord.map(meshopt::quantize_half),
+            tex_coord1: vertex.tex_coord1.map(meshopt::quantize_half),
+            color: vertex
+                .color
+                .map(|channel| meshopt::quantize_unorm(channel, 8) as u8),
+            // glTF joints are 8 or 16-bit
+            joints: vertex.joints.map(|joint| joint as u16),
+            weights: vertex
+                .weights
+                .map(|weight| meshopt::quantize_unorm(weight, 16) as u16),
+        }
+    }
+}
+
+impl QuantizationGrid {
+    // Fine enough for the largest of `levels`' meshlets to span GRID_STEPS along its longest axis
+    pub fn new<'a>(
+        vertices: &[Vertex],
+        levels: impl IntoIterator<Item = &'a meshopt::Meshlets>,
+    ) -> Self {
+        let base = vertices
+            .iter()
+            .map(|vertex| glm::make_vec3(&vertex.position))
+            .reduce(|min, position| glm::min2(&min, &position))
+            .unwrap_or_default();
+        let mut extent = 0.0_f32;
+        for meshlets in levels {
+            for meshlet in meshlets.meshlets.iter() {
+                let range = meshlet.vertex_offset as usize
+                    ..(meshlet.vertex_offset + meshlet.vertex_count) as usize;
+                let positions = meshlets.vertices[range]
+                    .iter()
+                    .map(|&vertex| glm::make_vec3(&vertices[vertex as usize].position));
+                let Some((min, max)) = positions
+                    .map(|position| (position, position))
+                    .reduce(|(min, max), (p, _)| (glm::min2(&min, &p), glm::max2(&max, &p)))
+                else {
+                    continue;
+                };
+                extent = extent.max(glm::comp_max(&(max - min)));
+            }
+        }
+        // a single point, every grid point is the base
+        let step = if extent > 0.0 {
+            extent / GRID_STEPS
+        } else {
+            1.0
+        };
+        Self { base, step }
+    }
+
+    // nearest grid point, float to int casts saturate
+    fn point(&self, position: &[f32; 3]) -> [u32; 3] {
+        let steps = (glm::make_vec3(position) - self.base) / self.step;
+        [steps.x, steps.y, steps.z].map(|steps| steps.round() as u32)
+    }
+
+    // Sets the origin of every meshlet to the smallest grid point of its vertices. The padding
+    // meshlets past `bounds` have no vertices.
+    pub fn set_origins(
+        &self,
+        vertices: &[Vertex],
+        meshlets: &meshopt::Meshlets,
+        bounds: &mut [MeshletBounds],
+    ) {
+        for (meshlet, bounds) in std::iter::zip(&meshlets.meshlets, bounds) {
+            let range = meshlet.vertex_offset as usize
+                ..(meshlet.vertex_offset + meshlet.vertex_count) as usize;
+            bounds.origin = meshlets.vertices[range]
+                .iter()
+                .map(|&vertex| self.point(&vertices[vertex as usize].position))
+                .reduce(|origin, point| std::array::from_fn(|axis| origin[axis].min(point[axis])))
+                .unwrap_or_default();
+        }
+    }
+}
+
+pub fn pack_position(
+    vertex: &Vertex,
+    bounds: &MeshletBounds,
+    grid: &QuantizationGrid,
+) -> PackedPosition {
+    let point = grid.point(&vertex.position);
+    PackedPosition {
+        offset: std::array::from_fn(|axis| {
+            (point[axis] - bounds.origin[axis]).min(u16::MAX as u32) as u16
+        }),
+        bitangent_sign: snorm16(vertex.tangent[3]),
+    }
+}
+
+// Positions of every meshlet vertex, parallel to `meshlets.vertices`, relative to the origins
+// QuantizationGrid::set_origins put into `bounds`
+pub fn pack_positions(
+    vertices: &[Vertex],
+    meshlets: &meshopt::Meshlets,
+    bounds: &[MeshletBounds],
+    grid: &QuantizationGrid,
+) -> std::vec::Vec<PackedPosition> {
+    let mut positions = vec![PackedPosition::default(); meshlets.vertices.len()];
+    for (meshlet, bounds) in std::iter::zip(&meshlets.meshlets, bounds) {
+        let range =
+            meshlet.vertex_offset as usize..(meshlet.vertex_offset + meshlet.vertex_count) as usize;
+        for (position, &vertex) in
+            std::iter::zip(&mut positions[range.clone()], &meshlets.vertices[range])
+        {
+            *position = pack_position(&vertices[vertex as usize], bounds, grid);
+        }
+    }
+    positions
+}
+
+// CPU reference of unpack_vertex() in descriptor_set_meshlet.glsl. A vertex without a tangent
+// gets +Z with a bitangent sign of 0.
+#[cfg(test)]
+pub fn unpack(
+    packed: &PackedVertex,
+    position: &PackedPosition,
+    bounds: &MeshletBounds,
+    grid: &QuantizationGrid,
+) -> Vertex {
+    // integer, the same grid point in every meshlet
+    let point: [u32; 3] =
+        std::array::from_fn(|axis| bounds.origin[axis] + u32::from(position.offset[axis]));
+    let tangent = decode_octahedral(packed.tangent);
+    Vertex {
+        position: (grid.base
+            + glm::vec3(point[0] as f32, point[1] as f32, point[2] as f32) * grid.step)
+            .into(),
+        normal: decode_octahedral(packed.normal).into(),
+        tex_coord: packed.tex_coord.map(meshopt::dequantize_half),
+        tangent: [
+            tangent.x,
+            tangent.y,
+            tangent.z,
+            unsnorm16(position.bitangent_sign),
+        ],
+        tex_coord1: packed.tex_coord1.map(meshopt::dequantize_half),
+        color: packed.color.map(|channel| channel as f32 / 255.0),
+        joints: packed.joints.map(u32::from),
+        weights: packed.weights.map(|weight| weight as f32 / 65535.0),
+    }
+}
+
+// Bytes of the meshlet path's vertices and culling bounds, packed and as the f32 data they
+// replace: a full Vertex per vertex and meshopt's bounds per meshlet
+#[derive(Clone, Copy, Default, Debug)]
+pub struct VertexMemory {
+    pub packed: usize,
+    pub unpacked: usize,
+}
+
+impl VertexMemory {
+    pub fn add_vertices(&mut self, vertex_count: usize) {
+        self.packed += vertex_count * std::mem::size_of::<PackedVertex>();
+        self.unpacked += vertex_count * Vertex::STRIDE;
+    }
+
+    // `meshlet_vertex_count` counts the vertices of every meshlet, shared ones once per meshlet
+    pub fn add_meshlets(&mut self, meshlet_vertex_count: usize, bounds_count: usize) {
+        self.packed += meshlet_vertex_count * std::mem::size_of::<PackedPosition>()
+            + bounds_count * std::mem::size_of::<MeshletBounds>();
+        self.unpacked += bounds_count * std::mem::size_of::<meshopt::Bounds>();
+    }
+}
+
+impl std::ops::Add for VertexMemory {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            packed: self.packed + other.packed,
+            unpacked: self.unpacked + other.unpacked,
+        }
+    }
+}
+
+fn snorm16(value: f32) -> i16 {
+    meshopt::quantize_snorm(value, 16) as i16
+}
+
+// like unpackSnorm2x16
+#[cfg(test)]
+fn unsnorm16(value: i16) -> f32 {
+    (value as f32 / 32767.0).max(-1.0)
+}
+
+// Unit vector folded onto the octahedron, zero vectors come back as +Z
+fn encode_octahedral(vector: &glm::Vec3) -> [i16; 2] {
+    let length = vector.x.abs() + vector.y.abs() + vector.z.abs();
+    if length == 0.0 {
+        return [0, 0];
+    }
+    let vector = vector / length;
+    let (x, y) = if vector.z >= 0.0 {
+        (vector.x, vector.y)
+    } else {
+        // the lower half folds over the diagonals
+        (
+            (1.0 - vector.y.abs()) * sign_not_zero(vector.x),
+            (1.0 - vector.x.abs()) * sign_not_zero(vector.y),
+        )
+    };
+    [snorm16(x), snorm16(y)]
+}
+
+#[cfg(test)]
+fn decode_octahedral(encoded: [i16; 2]) -> glm::Vec3 {
+    let (x, y) = (unsnorm16(encoded[0]), unsnorm16(encoded[1]));
+    let z = 1.0 - x.abs() - y.abs();
+    let fold = (-z).max(0.0);
+    glm::normalize(&glm::vec3(
+        x - fold * sign_not_zero(x),
+        y - fold * sign_not_zero(y),
+        z,
+    ))
+}
+
+fn sign_not_zero(value: f32) -> f32 {
+    if value >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cluster_lod::build_cluster_hierarchy;
+    use super::super::meshlet::build_meshlets2;
+    use super::super::testing::wavy_grid;
+    use super::*;
+
+    // octahedral snorm16 keeps directions within this many radians
+    const ANGLE_EPSILON: f32 = 1e-4;
+    const HALF_MIN_NORMAL: f32 = 1.0 / 16384.0;
+
+    // deterministic values in [0, 1)
+    fn random(state: &mut u64) -> f32 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*state >> 40) as f32 / (1 << 24) as f32
+    }
+
+    fn random_direction(state: &mut u64) -> [f32; 3] {
+        let vector = glm::vec3(
+            random(state) - 0.5,
+            random(state) - 0.5,
+            random(state) - 0.5,
+        );
+        glm::normalize(&vector).into()
+    }
+
+    // wavy_grid away from the origin, with random attributes
+    fn height_field(n: u32) -> (std::vec::Vec<Vertex>, std::vec::Vec<u32>) {
+        let (mut vertices, indices) = wavy_grid(n);
+        let mut state = 7;
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            let [x, y, z] = vertex.position;
+            let tangent = random_direction(&mut state);
+            let weights = [(); 4].map(|_| random(&mut state));
+            let weight_sum: f32 = weights.iter().sum();
+            *vertex = Vertex {
+                position: [x * 0.37 + 100.0, y * 0.37 - 50.0, z * 2.0],
+                normal: random_direction(&mut state),
+                tex_coord: [random(&mut state) * 4.0 - 2.0, random(&mut state)],
+                tangent: [tangent[0], tangent[1], tangent[2], [-1.0, 0.0, 1.0][i % 3]],
+                tex_coord1: [random(&mut state), random(&mut state)],
+                color: [(); 4].map(|_| random(&mut state)),
+                joints: [i as u32 * 7 % 300, y as u32, 1000, 65535],
+                weights: weights.map(|weight| weight / weight_sum),
+            };
+        }
+        (vertices, indices)
+    }
+
+    // the full resolution meshlets and the hierarchy on one grid, like load_or_build_meshlets
+    fn quantized(
+        vertices: &[Vertex],
+        indices: &std::vec::Vec<u32>,
+    ) -> (
+        std::vec::Vec<(meshopt::Meshlets, std::vec::Vec<MeshletBounds>)>,
+        QuantizationGrid,
+    ) {
+        let (meshlets, bounds) = build_meshlets2(&vertices.to_vec(), indices);
+        let (hierarchy, hierarchy_bounds, _) = build_cluster_hierarchy(vertices, indices);
+        let mut levels = vec![(meshlets, bounds), (hierarchy, hierarchy_bounds)];
+        let grid = QuantizationGrid::new(vertices, levels.iter().map(|(meshlets, _)| meshlets));
+        for (meshlets, bounds) in &mut levels {
+            grid.set_origins(vertices, meshlets, bounds);
+        }
+        (levels, grid)
+    }
+
+    // every meshlet vertex slot as (vertex index, decoded vertex)
+    fn decoded(
+        vertices: &[Vertex],
+        meshlets: &meshopt::Meshlets,
+        bounds: &[MeshletBounds],
+        grid: &QuantizationGrid,
+    ) -> std::vec::Vec<(usize, Vertex)> {
+        let positions = pack_positions(vertices, meshlets, bounds, grid);
+        let mut slots = vec![];
+        for (meshlet, bounds) in std::iter::zip(&meshlets.meshlets, bounds) {
+            for slot in meshlet.vertex_offset..meshlet.vertex_offset + meshlet.vertex_count {
+                let vertex = meshlets.vertices[slot as usize] as usize;
+                let packed = PackedVertex::pack(&vertices[vertex]);
+                slots.push((
+                    vertex,
+                    unpack(&packed, &positions[slot as usize], bounds, grid),
+                ));
+            }
+        }
+        slots
+    }
+
+    // unlike acos of the dot product, precise for small angles
+    fn angle(a: &[f32], b: &[f32]) -> f32 {
+        let (a, b) = (glm::make_vec3(a), glm::make_vec3(b));
+        glm::length(&glm::cross(&a, &b)).atan2(glm::dot(&a, &b))
+    }
+
+    #[test]
+    fn packed_vertices_round_trip() {
+        let (vertices, indices) = height_field(80);
+        let (levels, grid) = quantized(&vertices, &indices);
+        let max_radius = levels
+            .iter()
+            .flat_map(|(_, bounds)| bounds.iter().map(|bounds| bounds.radius))
+            .fold(0.0, f32::max);
+        // no coarser than 16 bits across the largest meshlet's bounding sphere
+        assert!(grid.step / 2.0 <= max_radius / 32767.0);
+
+        for (meshlets, bounds) in &levels {
+            for (index, unpacked) in decoded(&vertices, meshlets, bounds, &grid) {
+                let vertex = &vertices[index];
+                for axis in 0..3 {
+                    // half a step, and f32 rounding of base + point * step
+                    let tolerance =
+                        grid.step / 2.0 + vertex.position[axis].abs() * 4.0 * f32::EPSILON;
+                    let error = (unpacked.position[axis] - vertex.position[axis]).abs();
+                    assert!(error <= tolerance, "{} {:?}", index, unpacked.position);
+                }
+                assert!(angle(&unpacked.normal, &vertex.normal) <= ANGLE_EPSILON);
+                assert!(angle(&unpacked.tangent[..3], &vertex.tangent[..3]) <= ANGLE_EPSILON);
+                assert_eq!(unpacked.tangent[3], vertex.tangent[3]);
+                // 11 significant bits, meshopt flushes values below the smallest normal half
+                // float to zero
+                for (unpacked, uv) in std::iter::zip(
+                    unpacked.tex_coord.iter().chain(&unpacked.tex_coord1),
+                    vertex.tex_coord.iter().chain(&vertex.tex_coord1),
+                ) {
+                    let tolerance = if uv.abs() < HALF_MIN_NORMAL {
+                        HALF_MIN_NORMAL
+                    } else {
+                        uv.abs() / 2048.0
+                    };
+                    assert!((unpacked - uv).abs() <= tolerance, "{} {}", uv, unpacked);
+                }
+                for channel in 0..4 {
+                    let error = (unpacked.color[channel] - vertex.color[channel]).abs();
+                    assert!(error <= 1.0 / 255.0);
+                    let error = (unpacked.weights[channel] - vertex.weights[channel]).abs();
+                    assert!(error <= 1.0 / 65535.0);
+                }
+                assert_eq!(unpacked.joints, vertex.joints);
+            }
+        }
+    }
+
+    #[test]
+    fn octahedral_directions_round_trip() {
+        let directions = [
+            // the axes, both hemispheres
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+            [0.577, 0.577, 0.577],
+            [-0.577, 0.577, -0.577],
+            [0.577, -0.577, -0.577],
+            // on and just below the fold at z = 0
+            [0.6, 0.8, 0.0],
+            [-0.8, -0.6, 0.0],
+            [0.6, -0.8, -0.001],
+            // where sign_not_zero picks a side in the lower hemisphere
+            [0.0, 0.6, -0.8],
+            [0.0, -0.6, -0.8],
+            [-0.6, 0.0, -0.8],
+            [0.6, 0.0, -0.8],
+        ];
+        for direction in directions {
+            let direction = glm::normalize(&glm::make_vec3(&direction));
+            let decoded = decode_octahedral(encode_octahedral(&direction));
+            assert!(
+                angle(decoded.as_slice(), direction.as_slice()) <= ANGLE_EPSILON,
+                "{:?} -> {:?}",
+                direction,
+                decoded
+            );
+        }
+        // zero vectors, i.e. no tangent
+        assert_eq!(
+            decode_octahedral(encode_octahedral(&glm::vec3(0.0, 0.0, 0.0))),
+            glm::vec3(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn shared_vertices_decode_identically() {
+        let (vertices, indices) = height_field(48);
+        let (levels, grid) = quantized(&vertices, &indices);
+
+        let mut positions: std::vec::Vec<Option<[u32; 3]>> = vec![None; vertices.len()];
+        let mut shared = 0;
+        for (meshlets, bounds) in &levels {
+            for (index, unpacked) in decoded(&vertices, meshlets, bounds, &grid) {
+                let bits = unpacked.position.map(f32::to_bits);
+                match positions[index] {
+                    Some(first) => {
+                        assert_eq!(bits, first, "vertex {}", index);
+                        shared += 1;
+                    }
+                    None => positions[index] = Some(bits),
+                }
+            }
+        }
+        // borders within each level and between the levels
+        assert!(shared > vertices.len());
+    }
+}
@@ -1,10 +1,30 @@
+pub(super) mod animation;
+pub(super) mod cameras;
+pub(super) mod cluster_lod;
+pub(super) mod error;
+pub(super) mod geometry_cache;
 pub(super) mod gltf_asset;
+pub(super) mod lights;
+pub(super) mod lod;
+pub(super) mod material;
 pub(super) mod mesh;
 pub(super) mod meshlet;
 pub mod meshlet_asset;
+pub(super) mod meshlet_cache;
+pub(super) mod meshlet_vertex;
+pub(super) mod morph;
+pub(super) mod normals;
+pub(super) mod optimize;
 pub(super) mod primitive;
 mod scene_nodes;
+pub(super) mod skin;
+pub(super) mod tangents;
+#[cfg(test)]
+pub(crate) mod testing;
+pub(super) mod texture;
 pub mod traditional_asset;
+pub(super) mod vertex;
 
 pub use meshlet_asset::MeshletAsset;
+pub use texture::Textures;
 pub use traditional_asset::TraditionalAsset;
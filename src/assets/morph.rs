@@ -0,0 +1,103 @@
+use super::gltf_asset::{Node, Scene};
+use crate::vkutils;
+use crate::vkutils::vk_destroy::VkDestroy;
+use ash::vk;
+
+// One target's displacement of one vertex, mirrors `MorphDelta` in descriptor_set_common.glsl.
+// Primitives store them vertex-major, i.e. [vertex * target_count + target].
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MorphDelta {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    // xyz only, the bitangent sign is not morphed
+    pub tangent: [f32; 3],
+}
+
+// For every vertex the first vertex with bit-identical deltas in all targets. Welding vertices
+// keyed by it keeps vertices apart that would morph apart.
+pub fn first_identical_vertex(
+    targets: &[std::vec::Vec<MorphDelta>],
+    vertex_count: usize,
+) -> std::vec::Vec<u32> {
+    let mut first_vertex: std::collections::HashMap<std::vec::Vec<u32>, u32> =
+        std::collections::HashMap::new();
+    (0..vertex_count)
+        .map(|vertex| {
+            let key = targets
+                .iter()
+                .flat_map(|deltas| {
+                    let delta = &deltas[vertex];
+                    [delta.position, delta.normal, delta.tangent]
+                })
+                .flatten()
+                .map(f32::to_bits)
+                .collect();
+            *first_vertex.entry(key).or_insert(vertex as u32)
+        })
+        .collect()
+}
+
+// Morph weights of every morphed mesh instance in a scene, packed into one mapped buffer that
+// is rewritten whenever node weights change
+pub(super) struct MorphWeights {
+    buffer: vkutils::buffer::Buffer,
+    // (mesh node, first weight in buffer)
+    instances: std::vec::Vec<(usize, usize)>,
+}
+
+impl MorphWeights {
+    pub fn new(ctx: &vkutils::context::VulkanContext, nodes: &[Node], scene: &Scene) -> Self {
+        let mut instances = vec![];
+        let mut weight_count = 0;
+        let mut stack = scene.nodes.clone();
+        while let Some(node_index) = stack.pop() {
+            let node = &nodes[node_index];
+            if node.mesh_index.is_some() && !node.weights.is_empty() {
+                instances.push((node_index, weight_count));
+                weight_count += node.weights.len();
+            }
+            stack.extend(&node.children);
+        }
+
+        // zero-sized buffers are invalid, keep at least one weight around
+        let buffer = ctx.create_bar_buffer(
+            weight_count.max(1) * std::mem::size_of::<f32>(),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        );
+
+        let weights = Self { buffer, instances };
+        weights.update(nodes);
+        weights
+    }
+
+    // MorphWeightBuf address of a morphed mesh node
+    pub fn weights(&self, node_index: usize) -> Option<vk::DeviceAddress> {
+        self.instances
+            .iter()
+            .find(|(mesh_node, _)| *mesh_node == node_index)
+            .map(|&(_, first_weight)| {
+                self.buffer.device_address.unwrap()
+                    + (first_weight * std::mem::size_of::<f32>()) as vk::DeviceAddress
+            })
+    }
+
+    pub fn update(&self, nodes: &[Node]) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        let weights: std::vec::Vec<f32> = self
+            .instances
+            .iter()
+            .flat_map(|&(mesh_node, _)| nodes[mesh_node].weights.iter().copied())
+            .collect();
+        self.buffer.update_contents(&weights);
+    }
+}
+
+impl std::ops::Drop for MorphWeights {
+    fn drop(&mut self) {
+        self.buffer.vk_destroy();
+    }
+}
@@ -0,0 +1,185 @@
+// Normals for primitives that come without a NORMAL attribute.
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Hash)]
+pub enum NormalGeneration {
+    // glTF 2.0 3.7.2.1: "client implementations MUST calculate flat normals"
+    #[default]
+    Flat,
+    // angle-weighted average of the faces sharing a position
+    Smooth,
+}
+
+// None for degenerate and sliver triangles, e.g. at sphere poles, their orientation is float noise
+fn face_normal(p0: &glm::Vec3, p1: &glm::Vec3, p2: &glm::Vec3) -> Option<glm::Vec3> {
+    let n = glm::cross(&(p1 - p0), &(p2 - p0));
+    let longest_edge_sq = glm::length2(&(p1 - p0))
+        .max(glm::length2(&(p2 - p0)))
+        .max(glm::length2(&(p2 - p1)));
+    match glm::length(&n) > 1e-6 * longest_edge_sq {
+        true => Some(glm::normalize(&n)),
+        false => None,
+    }
+}
+
+// Expands an indexed attribute into one value per triangle corner.
+pub fn unweld<T: Copy>(attribute: &[T], indices: &[u32]) -> std::vec::Vec<T> {
+    indices.iter().map(|&i| attribute[i as usize]).collect()
+}
+
+// One normal per triangle corner, i.e. per index. Pair with `unweld`ed attributes.
+pub fn flat_normals(positions: &[glm::Vec3], indices: &[u32]) -> std::vec::Vec<glm::Vec3> {
+    let mut normals = std::vec::Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [p0, p1, p2] = [0, 1, 2].map(|corner| &positions[triangle[corner] as usize]);
+        // degenerate triangles don't matter, they don't cover any pixels
+        let n = face_normal(p0, p1, p2).unwrap_or(glm::vec3(0.0, 0.0, 1.0));
+        normals.extend([n, n, n]);
+    }
+    normals
+}
+
+// One normal per vertex. Vertices at the same position (split by UV seams etc.) share the normal,
+// every face contributes proportionally to its corner angle.
+pub fn smooth_normals(positions: &[glm::Vec3], indices: &[u32]) -> std::vec::Vec<glm::Vec3> {
+    // positions are compared on a grid relative to the mesh size, generated meshes tend to have
+    // float noise in what should be a single vertex
+    let extent = positions
+        .iter()
+        .fold(0.0_f32, |extent, p| extent.max(glm::comp_max(&glm::abs(p))));
+    let cell = (extent * 1e-6).max(f32::MIN_POSITIVE);
+    let key = |p: &glm::Vec3| [p.x, p.y, p.z].map(|c| (c / cell).round() as i64);
+    let mut accumulated: std::collections::HashMap<[i64; 3], glm::Vec3> =
+        std::collections::HashMap::new();
+
+    for triangle in indices.chunks_exact(3) {
+        let p = [0, 1, 2].map(|corner| positions[triangle[corner] as usize]);
+        let Some(n) = face_normal(&p[0], &p[1], &p[2]) else {
+            continue;
+        };
+
+        for corner in 0..3 {
+            let e1 = p[(corner + 1) % 3] - p[corner];
+            let e2 = p[(corner + 2) % 3] - p[corner];
+            let angle = glm::angle(&e1, &e2);
+            *accumulated
+                .entry(key(&p[corner]))
+                .or_insert(glm::Vec3::zeros()) += n * angle;
+        }
+    }
+
+    positions
+        .iter()
+        .map(|p| match accumulated.get(&key(p)) {
+            Some(n) if glm::length(n) > 0.0 => glm::normalize(n),
+            _ => glm::vec3(0.0, 0.0, 1.0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // unit cube around the origin, counter-clockwise seen from outside
+    fn cube() -> (std::vec::Vec<glm::Vec3>, std::vec::Vec<u32>) {
+        let positions = (0..8)
+            .map(|i| {
+                glm::vec3(
+                    (i & 1) as f32 - 0.5,
+                    ((i >> 1) & 1) as f32 - 0.5,
+                    ((i >> 2) & 1) as f32 - 0.5,
+                )
+            })
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // -Z
+            4, 5, 6, 5, 7, 6, // +Z
+            0, 1, 4, 1, 5, 4, // -Y
+            2, 6, 3, 3, 6, 7, // +Y
+            0, 4, 2, 2, 4, 6, // -X
+            1, 3, 5, 3, 7, 5, // +X
+        ];
+        (positions, indices)
+    }
+
+    // latitude-longitude sphere, the rings at the poles collapse into degenerate triangles
+    fn uv_sphere(center: glm::Vec3, radius: f32) -> (std::vec::Vec<glm::Vec3>, std::vec::Vec<u32>) {
+        let (rings, segments) = (8, 12);
+        let mut positions = vec![];
+        for ring in 0..=rings {
+            let theta = std::f32::consts::PI * ring as f32 / rings as f32;
+            for segment in 0..=segments {
+                let phi = std::f32::consts::TAU * segment as f32 / segments as f32;
+                positions.push(
+                    center
+                        + radius
+                            * glm::vec3(
+                                theta.sin() * phi.cos(),
+                                theta.cos(),
+                                -theta.sin() * phi.sin(),
+                            ),
+                );
+            }
+        }
+        let mut indices = vec![];
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let i = ring * (segments + 1) + segment;
+                let below = i + segments + 1;
+                indices.extend([i, below, i + 1, i + 1, below, below + 1]);
+            }
+        }
+        (positions, indices)
+    }
+
+    #[test]
+    fn flat_normals_of_a_cube_point_out_of_its_faces() {
+        let (positions, indices) = cube();
+        let normals = flat_normals(&positions, &indices);
+        assert_eq!(normals.len(), indices.len());
+        for (triangle, normals) in indices.chunks_exact(3).zip(normals.chunks_exact(3)) {
+            let centroid = triangle
+                .iter()
+                .map(|&i| positions[i as usize])
+                .sum::<glm::Vec3>()
+                / 3.0;
+            for n in normals {
+                // a single axis, the one the face lies on
+                assert_eq!(n.iter().filter(|c| c.abs() == 1.0).count(), 1);
+                assert_eq!(n.iter().filter(|c| **c == 0.0).count(), 2);
+                assert!(glm::dot(n, &centroid) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals_of_a_sphere_point_away_from_its_center() {
+        let center = glm::vec3(3.0, -2.0, 5.0);
+        let (positions, indices) = uv_sphere(center, 2.0);
+        let normals = smooth_normals(&positions, &indices);
+        assert_eq!(normals.len(), positions.len());
+        for (p, n) in positions.iter().zip(&normals) {
+            let outward = glm::normalize(&(p - center));
+            // the coarse tessellation tilts the normals a little
+            assert!(glm::dot(n, &outward) > 0.95, "{:?} at {:?}", n, p);
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_have_finite_normals() {
+        let positions = [
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(2.0, 0.0, 0.0),
+            glm::vec3(0.0, 0.0, 0.0),
+        ];
+        // collinear, a repeated index and two vertices at the same position
+        let indices = [0, 1, 2, 1, 1, 2, 0, 3, 1];
+        let flat = flat_normals(&positions, &indices);
+        let smooth = smooth_normals(&positions, &indices);
+        for n in flat.iter().chain(&smooth) {
+            assert!(n.iter().all(|c| c.is_finite()));
+            assert!((glm::length(n) - 1.0).abs() < 1e-6);
+        }
+    }
+}
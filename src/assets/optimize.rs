@@ -0,0 +1,138 @@
+use super::gltf_asset::{IndexBufferType, Primitive};
+use super::morph::MorphDelta;
+use super::vertex::Vertex;
+
+// post-transform cache the ACMR is measured with, meshopt's FIFO model of a typical GPU
+const CACHE_SIZE: u32 = 16;
+// how much of the vertex cache gain the overdraw pass may give back, meshopt's suggestion
+const OVERDRAW_THRESHOLD: f32 = 1.05;
+
+// Vertex cache and overdraw figures of one or more primitives, summed so bigger ones weigh more
+#[derive(Clone, Copy, Default)]
+pub struct MeshStats {
+    vertices_transformed: u64,
+    triangles: u64,
+    pixels_covered: u64,
+    pixels_shaded: u64,
+}
+
+impl MeshStats {
+    pub fn of(vertices: &[Vertex], indices: &[u32]) -> Self {
+        if indices.is_empty() {
+            return Self::default();
+        }
+        let cache = meshopt::analyze_vertex_cache(indices, vertices.len(), CACHE_SIZE, 0, 0);
+        let overdraw = meshopt::analyze_overdraw(indices, &position_adapter(vertices));
+        Self {
+            vertices_transformed: cache.vertices_transformed as u64,
+            triangles: indices.len() as u64 / 3,
+            pixels_covered: overdraw.pixels_covered as u64,
+            pixels_shaded: overdraw.pixels_shaded as u64,
+        }
+    }
+
+    pub fn add(&mut self, other: &MeshStats) {
+        self.vertices_transformed += other.vertices_transformed;
+        self.triangles += other.triangles;
+        self.pixels_covered += other.pixels_covered;
+        self.pixels_shaded += other.pixels_shaded;
+    }
+
+    // average cache miss ratio, vertex shader invocations per triangle, 0.5 at best
+    pub fn acmr(&self) -> f32 {
+        self.vertices_transformed as f32 / self.triangles.max(1) as f32
+    }
+
+    // fragment shader invocations per covered pixel, 1 at best
+    pub fn overdraw(&self) -> f32 {
+        self.pixels_shaded as f32 / self.pixels_covered.max(1) as f32
+    }
+}
+
+// Merges duplicate vertices, orders triangles for the post-transform cache and then overdraw,
+// and vertices in the order the triangles fetch them. Morph deltas follow their vertices.
+// Returns the stats from before and after.
+pub fn optimize_primitive(primitive: &mut Primitive) -> (MeshStats, MeshStats) {
+    let indices: std::vec::Vec<u32> = match &primitive.index_buffer {
+        IndexBufferType::U16(items) => items.iter().map(|i| *i as u32).collect(),
+        IndexBufferType::U32(items) => items.clone(),
+    };
+    let before = MeshStats::of(&primitive.vertex_buffer, &indices);
+    if indices.is_empty() {
+        return (before, before);
+    }
+    let target_count = primitive.morph_target_count;
+
+    // vertices equal in every attribute and every target's deltas, unreferenced ones are dropped
+    let vertex_count = primitive.vertex_buffer.len();
+    let delta_stride = target_count * std::mem::size_of::<MorphDelta>();
+    let mut streams = vec![meshopt::VertexStream::new(primitive.vertex_buffer.as_ptr())];
+    for target in 0..target_count {
+        let deltas = primitive.morph_deltas[target..].as_ptr();
+        streams.push(meshopt::VertexStream::new_with_stride::<MorphDelta, _>(
+            deltas,
+            delta_stride,
+        ));
+    }
+    let (unique_count, remap) =
+        meshopt::generate_vertex_remap_multi(vertex_count, &streams, Some(&indices));
+    let mut indices = meshopt::remap_index_buffer(Some(&indices), vertex_count, &remap);
+    let vertices = meshopt::remap_vertex_buffer(&primitive.vertex_buffer, unique_count, &remap);
+    let morph_deltas =
+        remap_morph_deltas(&primitive.morph_deltas, target_count, unique_count, &remap);
+
+    meshopt::optimize_vertex_cache_in_place(&mut indices, unique_count);
+    meshopt::optimize_overdraw_in_place(
+        &mut indices,
+        &position_adapter(&vertices),
+        OVERDRAW_THRESHOLD,
+    );
+
+    // every vertex is referenced after the dedup, the remap covers all of them
+    let fetch_remap = meshopt::optimize_vertex_fetch_remap(&indices, unique_count);
+    let indices = meshopt::remap_index_buffer(Some(&indices), unique_count, &fetch_remap);
+    let vertices = meshopt::remap_vertex_buffer(&vertices, unique_count, &fetch_remap);
+    let morph_deltas = remap_morph_deltas(&morph_deltas, target_count, unique_count, &fetch_remap);
+
+    let after = MeshStats::of(&vertices, &indices);
+    primitive.index_buffer = match primitive.index_buffer {
+        // the vertex count only shrinks, u16 indices still fit
+        IndexBufferType::U16(_) => {
+            IndexBufferType::U16(indices.into_iter().map(|i| i as u16).collect())
+        }
+        IndexBufferType::U32(_) => IndexBufferType::U32(indices),
+    };
+    primitive.vertex_buffer = vertices;
+    primitive.morph_deltas = morph_deltas;
+    (before, after)
+}
+
+fn position_adapter(vertices: &[Vertex]) -> meshopt::VertexDataAdapter<'_> {
+    meshopt::VertexDataAdapter::new(
+        meshopt::typed_to_bytes(vertices),
+        Vertex::STRIDE,
+        std::mem::offset_of!(Vertex, position),
+    )
+    .expect("Failed to create vertex adapter")
+}
+
+// Deltas are vertex-major, a vertex's block of `target_count` deltas moves where `remap` puts it.
+// Dropped vertices (remap u32::MAX) take their deltas with them.
+fn remap_morph_deltas(
+    deltas: &[MorphDelta],
+    target_count: usize,
+    vertex_count: usize,
+    remap: &[u32],
+) -> std::vec::Vec<MorphDelta> {
+    let mut remapped = vec![MorphDelta::default(); vertex_count * target_count];
+    if target_count == 0 {
+        return remapped;
+    }
+    for (block, &new_vertex) in std::iter::zip(deltas.chunks_exact(target_count), remap) {
+        if new_vertex != u32::MAX {
+            let start = new_vertex as usize * target_count;
+            remapped[start..start + target_count].copy_from_slice(block);
+        }
+    }
+    remapped
+}
@@ -1,3 +1,4 @@
+use super::lod::BoundingSphere;
 use crate::vkutils;
 use crate::vkutils::vk_destroy::VkDestroy;
 
@@ -8,11 +9,25 @@ pub struct FVFCombinedPrimitives {
     pub primitive_index_count: std::vec::Vec<u32>, // number of indices for a primitive at index
     pub primitive_index_offset_in_combined_index_buffer: std::vec::Vec<u32>,
     pub primitive_parent_node_indices: std::vec::Vec<std::vec::Vec<usize>>,
+    pub primitive_material_indices: std::vec::Vec<u32>,
+    // all primitives' morph::MorphDelta, None if no primitive has morph targets
+    pub morph_deltas: Option<vkutils::buffer::Buffer>,
+    pub primitive_morph_delta_offset_in_combined_buffer: std::vec::Vec<u32>,
+    pub primitive_morph_target_count: std::vec::Vec<u32>,
+    // per primitive, first index and index count of every level in the combined index buffer,
+    // the full resolution one first
+    pub primitive_lod_index_ranges: std::vec::Vec<std::vec::Vec<(u32, u32)>>,
+    // per primitive, object space error of every level, 0 for the full resolution one
+    pub primitive_lod_errors: std::vec::Vec<std::vec::Vec<f32>>,
+    pub primitive_bounding_spheres: std::vec::Vec<BoundingSphere>,
 }
 
 impl std::ops::Drop for FVFCombinedPrimitives {
     fn drop(&mut self) {
         self.vb.vk_destroy();
         self.ib.vk_destroy();
+        if let Some(morph_deltas) = &self.morph_deltas {
+            morph_deltas.vk_destroy();
+        }
     }
 }
@@ -8,6 +8,28 @@ pub(super) struct SceneNodesBuffers {
     pub memory: vk::DeviceMemory,
     pub buffers: Vec<(vk::Buffer, vk::DeviceAddress, *mut std::ffi::c_void)>,
     pub node_transform_buffer_address: std::collections::HashMap<usize, vk::DeviceAddress>,
+    // mapped model matrix of every mesh node
+    node_transform_ptr: std::collections::HashMap<usize, *mut std::ffi::c_void>,
+    // places the whole asset in the world
+    root_transform: glm::Mat4,
+}
+
+impl SceneNodesBuffers {
+    // rewrites the model matrices after node transforms changed, e.g. by an animation
+    pub fn update(&self, nodes: &[Node]) {
+        let globals = self.world_transforms(nodes);
+        for (node_index, ptr) in &self.node_transform_ptr {
+            unsafe {
+                let mapped_slice = core::slice::from_raw_parts_mut(ptr.cast(), 1);
+                mapped_slice.copy_from_slice(&[globals[*node_index]]);
+            }
+        }
+    }
+
+    // what `update` writes, for every node
+    pub fn world_transforms(&self, nodes: &[Node]) -> Vec<glm::Mat4> {
+        global_transforms(nodes, &self.root_transform)
+    }
 }
 
 impl std::ops::Drop for SceneNodesBuffers {
@@ -27,6 +49,7 @@ pub(super) fn build_node_transformation_data(
     meshes: &mut Vec<Mesh>,
     nodes: &Vec<Node>,
     scene: &Scene,
+    root_transform: &glm::Mat4,
 ) -> SceneNodesBuffers {
     let (model_data_memory, mut model_data_buffers_with_addr) =
         create_buffers(ctx, scene, nodes, std::mem::size_of::<glm::Mat4>());
@@ -36,7 +59,7 @@ pub(super) fn build_node_transformation_data(
     for node_index in &scene.nodes {
         upload_model_data(
             *node_index,
-            &glm::Mat4::identity(),
+            root_transform,
             &nodes,
             meshes,
             &mut model_data_buffers_with_addr,
@@ -45,14 +68,55 @@ pub(super) fn build_node_transformation_data(
         );
     }
 
+    let ptr_by_address: std::collections::HashMap<vk::DeviceAddress, *mut std::ffi::c_void> =
+        model_data_buffers_with_addr
+            .iter()
+            .map(|(_buffer, address, ptr)| (*address, *ptr))
+            .collect();
+    let node_transform_ptr = node_transform_buffer_address
+        .iter()
+        .map(|(node_index, address)| (*node_index, ptr_by_address[address]))
+        .collect();
+
     SceneNodesBuffers {
         device: ctx.device.clone(),
         memory: model_data_memory,
         buffers: model_data_buffers_with_addr,
         node_transform_buffer_address,
+        node_transform_ptr,
+        root_transform: *root_transform,
     }
 }
 
+// World transform of every node, hierarchy roots are parented to `root_transform`
+pub(super) fn global_transforms(
+    nodes: &[Node],
+    root_transform: &glm::Mat4,
+) -> std::vec::Vec<glm::Mat4> {
+    let mut is_child = vec![false; nodes.len()];
+    for node in nodes {
+        for &child in &node.children {
+            is_child[child] = true;
+        }
+    }
+
+    let mut globals = vec![glm::Mat4::identity(); nodes.len()];
+    let mut stack: std::vec::Vec<(usize, glm::Mat4)> = (0..nodes.len())
+        .filter(|&node_index| !is_child[node_index])
+        .map(|node_index| (node_index, *root_transform))
+        .collect();
+
+    while let Some((node_index, parent_transform)) = stack.pop() {
+        let transform = parent_transform * nodes[node_index].matrix;
+        globals[node_index] = transform;
+        for &child in &nodes[node_index].children {
+            stack.push((child, transform));
+        }
+    }
+
+    globals
+}
+
 fn count_scene_instances(scene: &Scene, nodes: &Vec<Node>) -> usize {
     let mut instances_count: usize = 0;
     for node_index in &scene.nodes {
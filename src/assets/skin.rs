@@ -0,0 +1,160 @@
+use super::gltf_asset::{Node, Scene};
+use super::scene_nodes::global_transforms;
+use crate::vkutils;
+use crate::vkutils::vk_destroy::VkDestroy;
+use ash::vk;
+
+#[derive(Clone)]
+pub struct Skin {
+    pub _name: Option<std::string::String>,
+    // node indices
+    pub joints: std::vec::Vec<usize>,
+    // one per joint, identity if the glTF skin has none
+    pub inverse_bind_matrices: std::vec::Vec<glm::Mat4>,
+}
+
+// Joint matrices of a skinned mesh instanced at mesh_node. Shaders still apply the node's model
+// matrix, so it is cancelled out here - glTF 2.0 3.7.3.3: the skinned mesh node transform MUST be
+// ignored.
+pub fn joint_palette(
+    skin: &Skin,
+    global_transforms: &[glm::Mat4],
+    mesh_node: usize,
+) -> std::vec::Vec<glm::Mat4> {
+    let inverse_mesh_transform = glm::inverse(&global_transforms[mesh_node]);
+    std::iter::zip(&skin.joints, &skin.inverse_bind_matrices)
+        .map(|(&joint, inverse_bind_matrix)| {
+            inverse_mesh_transform * global_transforms[joint] * inverse_bind_matrix
+        })
+        .collect()
+}
+
+// CPU reference of skin_matrix() in vertex_common.glsl
+#[cfg(test)]
+pub fn skin_matrix(palette: &[glm::Mat4], joints: [u32; 4], weights: [f32; 4]) -> glm::Mat4 {
+    if weights.iter().sum::<f32>() == 0.0 {
+        return glm::Mat4::identity();
+    }
+    std::iter::zip(joints, weights).fold(glm::Mat4::zeros(), |skin, (joint, weight)| {
+        skin + palette[joint as usize] * weight
+    })
+}
+
+// Joint palettes of every skinned mesh instance in a scene, packed into one mapped buffer that
+// is rewritten whenever the node hierarchy moves
+pub(super) struct SkinPalettes {
+    buffer: vkutils::buffer::Buffer,
+    // (mesh node, skin, first matrix in buffer)
+    instances: std::vec::Vec<(usize, usize, usize)>,
+}
+
+impl SkinPalettes {
+    pub fn new(
+        ctx: &vkutils::context::VulkanContext,
+        nodes: &[Node],
+        skins: &[Skin],
+        scene: &Scene,
+    ) -> Self {
+        let mut instances = vec![];
+        let mut matrix_count = 0;
+        let mut stack = scene.nodes.clone();
+        while let Some(node_index) = stack.pop() {
+            let node = &nodes[node_index];
+            if let (Some(_), Some(skin_index)) = (node.mesh_index, node.skin_index) {
+                instances.push((node_index, skin_index, matrix_count));
+                matrix_count += skins[skin_index].joints.len();
+            }
+            stack.extend(&node.children);
+        }
+
+        // zero-sized buffers are invalid, keep at least one matrix around
+        let buffer = ctx.create_bar_buffer(
+            matrix_count.max(1) * std::mem::size_of::<glm::Mat4>(),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        );
+
+        let palettes = Self { buffer, instances };
+        palettes.update(nodes, skins);
+        palettes
+    }
+
+    // (JointBuf address, joint count) of a skinned mesh node
+    pub fn joints(&self, node_index: usize, skins: &[Skin]) -> Option<(vk::DeviceAddress, u32)> {
+        self.instances
+            .iter()
+            .find(|(mesh_node, _, _)| *mesh_node == node_index)
+            .map(|&(_, skin_index, first_matrix)| {
+                let address = self.buffer.device_address.unwrap()
+                    + (first_matrix * std::mem::size_of::<glm::Mat4>()) as vk::DeviceAddress;
+                (address, skins[skin_index].joints.len() as u32)
+            })
+    }
+
+    pub fn update(&self, nodes: &[Node], skins: &[Skin]) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        // the asset's root transform cancels out of the palette
+        let globals = global_transforms(nodes, &glm::Mat4::identity());
+        let matrices: std::vec::Vec<glm::Mat4> = self
+            .instances
+            .iter()
+            .flat_map(|&(mesh_node, skin_index, _)| {
+                joint_palette(&skins[skin_index], &globals, mesh_node)
+            })
+            .collect();
+        self.buffer.update_contents(&matrices);
+    }
+}
+
+impl std::ops::Drop for SkinPalettes {
+    fn drop(&mut self) {
+        self.buffer.vk_destroy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> std::vec::Vec<glm::Mat4> {
+        vec![
+            glm::translation(&glm::vec3(1.0, 0.0, 0.0)),
+            glm::rotation(std::f32::consts::FRAC_PI_2, &glm::vec3(0.0, 0.0, 1.0)),
+            glm::scaling(&glm::vec3(2.0, 2.0, 2.0)),
+        ]
+    }
+
+    #[test]
+    fn identity_palette_keeps_vertices_in_place() {
+        let palette = vec![glm::Mat4::identity(); 3];
+        let skin = skin_matrix(&palette, [0, 1, 2, 0], [0.5, 0.25, 0.125, 0.125]);
+        assert_eq!(skin, glm::Mat4::identity());
+    }
+
+    #[test]
+    fn single_joint_is_its_matrix() {
+        let palette = palette();
+        for joint in 0..palette.len() as u32 {
+            let skin = skin_matrix(&palette, [joint, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]);
+            assert_eq!(skin, palette[joint as usize]);
+        }
+    }
+
+    #[test]
+    fn weights_blend_the_joint_matrices() {
+        let palette = palette();
+        let skin = skin_matrix(&palette, [0, 2, 1, 1], [0.25, 0.75, 0.0, 0.0]);
+        let point = skin * glm::vec4(1.0, 1.0, 0.0, 1.0);
+        // translated to (2, 1), scaled to (2, 2)
+        assert!(glm::distance(&point, &glm::vec4(2.0, 1.75, 0.0, 1.0)) < 1e-6);
+        assert_eq!(skin, palette[0] * 0.25 + palette[2] * 0.75);
+    }
+
+    #[test]
+    fn zero_weights_are_identity() {
+        let skin = skin_matrix(&palette(), [1, 2, 0, 1], [0.0; 4]);
+        assert_eq!(skin, glm::Mat4::identity());
+    }
+}
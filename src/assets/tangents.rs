@@ -0,0 +1,61 @@
+// MikkTSpace tangents for primitives that come without a TANGENT attribute.
+
+use super::vertex::Vertex;
+
+// w == 0 marks a missing tangent, shaders fall back to screen space derivatives
+pub const NO_TANGENT: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+struct MikkTSpaceGeometry<'a> {
+    vertices: &'a [Vertex],
+    indices: &'a [u32],
+    // one per triangle corner
+    tangents: std::vec::Vec<glm::Vec4>,
+}
+
+impl MikkTSpaceGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkTSpaceGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).tex_coord
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = glm::make_vec4(&tangent);
+    }
+}
+
+// One tangent per triangle corner, i.e. per index - MikkTSpace may give a shared vertex different
+// tangents in different faces, so the caller has to unweld (and weld back). None if the geometry
+// is unsuitable, e.g. has no faces.
+pub fn generate_tangents(vertices: &[Vertex], indices: &[u32]) -> Option<std::vec::Vec<glm::Vec4>> {
+    let mut geometry = MikkTSpaceGeometry {
+        vertices,
+        indices,
+        tangents: vec![glm::make_vec4(&NO_TANGENT); indices.len()],
+    };
+
+    match bevy_mikktspace::generate_tangents(&mut geometry) {
+        true => Some(geometry.tangents),
+        false => None,
+    }
+}
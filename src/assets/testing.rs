@@ -0,0 +1,69 @@
+// Helpers shared by the unit tests of the asset and config modules
+
+use super::vertex::Vertex;
+
+// numbers test directories, tests run in parallel
+static DIR_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+// A directory of its own in the temp dir, removed with everything in it on drop
+pub struct TestDir(std::path::PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "grass-rs-tests-{}-{}-{}",
+            std::process::id(),
+            DIR_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            name
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TestDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        // best effort, a failed cleanup doesn't fail the test
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// `file_name` in a TestDir of its own, removed along with the returned TestDir
+pub fn temp_file(file_name: &str) -> (TestDir, std::path::PathBuf) {
+    let dir = TestDir::new(file_name);
+    let path = dir.join(file_name);
+    (dir, path)
+}
+
+// n x n unit quads of a wavy height field facing +Z, so simplifying it has an error
+pub fn wavy_grid(n: u32) -> (std::vec::Vec<Vertex>, std::vec::Vec<u32>) {
+    let vertices = (0..=n)
+        .flat_map(|y| {
+            (0..=n).map(move |x| Vertex {
+                position: [
+                    x as f32,
+                    y as f32,
+                    (x as f32 * 0.4).sin() + (y as f32 * 0.3).cos(),
+                ],
+                normal: [0.0, 0.0, 1.0],
+                ..Default::default()
+            })
+        })
+        .collect();
+    let mut indices = vec![];
+    for y in 0..n {
+        for x in 0..n {
+            let i = y * (n + 1) + x;
+            indices.extend([i, i + 1, i + n + 1, i + 1, i + n + 2, i + n + 1]);
+        }
+    }
+    (vertices, indices)
+}
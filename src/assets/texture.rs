@@ -0,0 +1,246 @@
+use super::error::AssetError;
+use super::gltf_asset::GltfAssetData;
+use crate::vkutils;
+use crate::vkutils::vk_destroy::VkDestroy;
+use ash::vk;
+use std::io::prelude::*;
+
+// decoded glTF image, always RGBA8
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: std::vec::Vec<u8>,
+    // sampled as base color or emissive somewhere, i.e. sRGB encoded
+    pub srgb: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub address_mode_u: vk::SamplerAddressMode,
+    pub address_mode_v: vk::SamplerAddressMode,
+}
+
+impl SamplerDesc {
+    pub fn from_gltf(sampler: &gltf::texture::Sampler) -> Self {
+        use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+        let address_mode = |mode: WrappingMode| match mode {
+            WrappingMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            WrappingMode::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+            WrappingMode::Repeat => vk::SamplerAddressMode::REPEAT,
+        };
+
+        // TODO mipmap modes are ignored until images get mip levels
+        let min_filter = match sampler.min_filter() {
+            Some(MinFilter::Nearest)
+            | Some(MinFilter::NearestMipmapNearest)
+            | Some(MinFilter::NearestMipmapLinear) => vk::Filter::NEAREST,
+            _ => vk::Filter::LINEAR,
+        };
+
+        Self {
+            mag_filter: match sampler.mag_filter() {
+                Some(MagFilter::Nearest) => vk::Filter::NEAREST,
+                _ => vk::Filter::LINEAR,
+            },
+            min_filter,
+            address_mode_u: address_mode(sampler.wrap_s()),
+            address_mode_v: address_mode(sampler.wrap_t()),
+        }
+    }
+}
+
+pub struct Texture {
+    pub image_index: usize,
+    pub sampler: SamplerDesc,
+}
+
+impl Texture {
+    pub fn from_gltf(texture: &gltf::Texture) -> Self {
+        Self {
+            image_index: texture.source().index(),
+            sampler: SamplerDesc::from_gltf(&texture.sampler()),
+        }
+    }
+}
+
+// raw (still encoded) bytes of an image, from a buffer view, a data URI or a file next to the
+// gltf
+pub(super) fn read_image_bytes(
+    image: &gltf::Image,
+    dir: &std::path::Path,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<std::vec::Vec<u8>, AssetError> {
+    let image_error = |reason: std::string::String| AssetError::Image {
+        image: image.index(),
+        reason,
+    };
+
+    match image.source() {
+        gltf::image::Source::View { view, .. } => {
+            let buffer = &buffer_data[view.buffer().index()].0;
+            buffer
+                .get(view.offset()..view.offset() + view.length())
+                .map(|bytes| bytes.to_vec())
+                .ok_or_else(|| image_error("buffer view out of bounds".to_string()))
+        }
+        gltf::image::Source::Uri { uri, .. } => {
+            if let Some(data) = uri.strip_prefix("data:") {
+                let (_, encoded) = data.split_once(";base64,").ok_or_else(|| {
+                    image_error("only base64 data URIs are supported".to_string())
+                })?;
+                base64::decode(encoded).map_err(|e| image_error(e.to_string()))
+            } else {
+                let uri = urlencoding::decode(uri).map_err(|e| image_error(e.to_string()))?;
+                let path = dir.join(uri.as_ref());
+                let mut contents = vec![];
+                std::fs::File::open(&path)
+                    .and_then(|mut f| f.read_to_end(&mut contents))
+                    .map_err(|e| image_error(format!("{}: {}", path.display(), e)))?;
+                Ok(contents)
+            }
+        }
+    }
+}
+
+pub(super) fn decode_image(encoded: &[u8], srgb: bool) -> Result<ImageData, std::string::String> {
+    let mut width: i32 = 0;
+    let mut height: i32 = 0;
+    let mut comps: i32 = 0;
+
+    let img_data = unsafe {
+        stb_image_rust::stbi_load_from_memory(
+            encoded.as_ptr(),
+            encoded.len() as i32,
+            &mut width,
+            &mut height,
+            &mut comps,
+            stb_image_rust::STBI_rgb_alpha,
+        )
+    };
+    if img_data.is_null() {
+        return Err("failed to decode image".to_string());
+    }
+
+    let size_in_bytes = width as usize * height as usize * 4; // w * h * rgba comps
+    let pixels = unsafe {
+        let pixels = std::slice::from_raw_parts(img_data, size_in_bytes).to_vec();
+        stb_image_rust::stbi_image_free(img_data);
+        pixels
+    };
+
+    Ok(ImageData {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+        srgb,
+    })
+}
+
+// GPU side of the glTF textures, shared by every asset built from the same GltfAssetData
+pub struct Textures {
+    images: std::vec::Vec<vkutils::image::Image>,
+    samplers: std::vec::Vec<vkutils::sampler::Sampler>,
+    // bindless slot of every glTF texture, indexed by texture index
+    pub slots: std::vec::Vec<u32>,
+}
+
+impl Textures {
+    // `replaced` is the slot_range of the textures these replace, reused if they fit. They are
+    // free afterwards, unless an error is returned.
+    pub fn from_gltf(
+        ctx: &vkutils::context::VulkanContext,
+        asset_data: &GltfAssetData,
+        replaced: std::ops::Range<u32>,
+    ) -> Result<Self, AssetError> {
+        // before uploading anything that would have to be destroyed again
+        let first_slot = ctx
+            .bindless_descriptor_set
+            .reserve_texture_slots(asset_data.textures.len() as u32, replaced)
+            .ok_or(AssetError::TextureSlots {
+                requested: asset_data.textures.len(),
+            })?;
+
+        let images: std::vec::Vec<vkutils::image::Image> = asset_data
+            .images
+            .iter()
+            .map(|image| {
+                let format = match image.srgb {
+                    true => vk::Format::R8G8B8A8_SRGB,
+                    false => vk::Format::R8G8B8A8_UNORM,
+                };
+                ctx.upload_image2d(
+                    &image.pixels,
+                    vk::Extent2D {
+                        width: image.width,
+                        height: image.height,
+                    },
+                    format,
+                )
+            })
+            .collect();
+
+        let mut samplers: std::vec::Vec<vkutils::sampler::Sampler> = vec![];
+        let mut sampler_lookup: std::collections::HashMap<SamplerDesc, usize> =
+            std::collections::HashMap::new();
+
+        let mut slots = vec![];
+
+        for (texture_index, texture) in asset_data.textures.iter().enumerate() {
+            let sampler_index = *sampler_lookup.entry(texture.sampler).or_insert_with(|| {
+                let create_info = vk::SamplerCreateInfo::default()
+                    .mag_filter(texture.sampler.mag_filter)
+                    .min_filter(texture.sampler.min_filter)
+                    .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+                    .address_mode_u(texture.sampler.address_mode_u)
+                    .address_mode_v(texture.sampler.address_mode_v)
+                    .address_mode_w(vk::SamplerAddressMode::REPEAT)
+                    .mip_lod_bias(0.0)
+                    .max_anisotropy(1.0)
+                    .min_lod(0.0)
+                    // images are uploaded without mip levels
+                    .max_lod(0.0)
+                    .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE);
+                samplers.push(vkutils::sampler::Sampler::from_create_info(
+                    ctx.device.clone(),
+                    &create_info,
+                ));
+                samplers.len() - 1
+            });
+
+            let slot = first_slot + texture_index as u32;
+            ctx.bindless_descriptor_set.update_texture2d(
+                images[texture.image_index].view,
+                samplers[sampler_index].handle,
+                slot,
+            );
+            slots.push(slot);
+        }
+
+        Ok(Self {
+            images,
+            samplers,
+            slots,
+        })
+    }
+
+    // `slots` are consecutive, to hand them on to the textures replacing these
+    pub fn slot_range(&self) -> std::ops::Range<u32> {
+        self.slots
+            .first()
+            .map_or(0..0, |first| *first..*first + self.slots.len() as u32)
+    }
+}
+
+impl std::ops::Drop for Textures {
+    fn drop(&mut self) {
+        for image in &self.images {
+            image.vk_destroy();
+        }
+        for sampler in &self.samplers {
+            sampler.vk_destroy();
+        }
+    }
+}
@@ -1,7 +1,13 @@
+use super::animation::Pose;
 use super::gltf_asset::{GltfAssetData, IndexBufferType, Node, Scene};
+use super::lod::LodView;
+use super::material::upload_materials;
 use super::mesh::{Mesh, Primitives};
+use super::morph::{MorphDelta, MorphWeights};
 use super::primitive::FVFCombinedPrimitives;
 use super::scene_nodes::{build_node_transformation_data, SceneNodesBuffers};
+use super::skin::{Skin, SkinPalettes};
+use super::texture::Textures;
 use crate::vkutils;
 use crate::vkutils::push_constants::GPUPushConstantsTraditional;
 use crate::vkutils::vk_destroy::VkDestroy;
@@ -11,25 +17,66 @@ use ash::vk;
 #[derive(Debug, Clone, Copy)]
 struct TraditionalInstance {
     pub transform: vk::DeviceAddress,
+    pub joints: vk::DeviceAddress, // JointBuf, unused if joint_count is 0
+    pub morph_deltas: vk::DeviceAddress, // MorphDeltaBuf, unused if morph_target_count is 0
+    pub morph_weights: vk::DeviceAddress, // MorphWeightBuf
+    pub material_index: u32,
+    pub joint_count: u32,
+    pub morph_target_count: u32,
+    _padding: u32,
+}
+
+// Indirect draws of a scene, one per instance so every instance can draw its own LOD
+struct SceneDraws {
+    buffer: vkutils::buffer::Buffer,
+    commands: Vec<vk::DrawIndexedIndirectCommand>,
+    // node and primitive of every command
+    instances: Vec<(usize, usize)>,
+}
+
+impl std::ops::Drop for SceneDraws {
+    fn drop(&mut self) {
+        self.buffer.vk_destroy();
+    }
 }
 
 pub struct TraditionalAsset {
     pub meshes: Vec<Mesh>,
-    pub default_scene: Option<usize>,
-    _node_transform_data: Vec<SceneNodesBuffers>,
+    // scene the passes record, the glTF default one unless switched at runtime
+    pub current_scene: usize,
+    node_transform_data: Vec<SceneNodesBuffers>,
+    // bind/rest pose, animations are applied on top of a copy
+    rest_nodes: Vec<Node>,
+    nodes: Vec<Node>,
+    skins: Vec<Skin>,
+    skin_palettes: Vec<SkinPalettes>,
+    morph_weights: Vec<MorphWeights>,
+    materials_buffer: vkutils::buffer::Buffer,
     instances_buffers: Vec<vkutils::buffer::Buffer>,
     offsets_buffers: Vec<vkutils::buffer::Buffer>,
-    indirect_draw_buffers: Vec<(vkutils::buffer::Buffer, usize)>,
+    indirect_draws: Vec<SceneDraws>,
 }
 
 impl TraditionalAsset {
-    pub fn from_gltf(ctx: &vkutils::context::VulkanContext, asset_data: &GltfAssetData) -> Self {
+    pub fn from_gltf(
+        ctx: &vkutils::context::VulkanContext,
+        asset_data: &GltfAssetData,
+        textures: &Textures,
+        transform: &glm::Mat4,
+    ) -> Self {
         let mut vertices = vec![];
         let mut indices = vec![];
         let mut primitive_vertex_offset_in_combined_vertex_buffer = vec![];
         let mut primitive_index_count = vec![];
         let mut primitive_index_offset_in_combined_index_buffer = vec![];
         let mut primitive_parent_node_indices = vec![];
+        let mut primitive_material_indices = vec![];
+        let mut morph_deltas = vec![];
+        let mut primitive_morph_delta_offset_in_combined_buffer = vec![];
+        let mut primitive_morph_target_count = vec![];
+        let mut primitive_lod_index_ranges = vec![];
+        let mut primitive_lod_errors = vec![];
+        let mut primitive_bounding_spheres = vec![];
 
         let mut vertex_offset_in_combined_vb = 0 as u32;
         let mut index_offset_in_combined_ib = 0 as u32;
@@ -46,9 +93,14 @@ impl TraditionalAsset {
                 }
 
                 primitive_parent_node_indices.push(parent_node_indices);
+                primitive_material_indices.push(primitive.material_index as u32);
+
+                primitive_morph_delta_offset_in_combined_buffer.push(morph_deltas.len() as u32);
+                primitive_morph_target_count.push(primitive.morph_target_count as u32);
+                morph_deltas.extend_from_slice(&primitive.morph_deltas);
 
                 vertices.append(&mut primitive.vertex_buffer.clone());
-                let vertex_count = (primitive.vertex_buffer.len() / 8) as u32;
+                let vertex_count = primitive.vertex_buffer.len() as u32;
                 primitive_vertex_offset_in_combined_vertex_buffer
                     .push(vertex_offset_in_combined_vb);
                 vertex_offset_in_combined_vb += vertex_count;
@@ -63,13 +115,32 @@ impl TraditionalAsset {
                 };
                 primitive_index_count.push(ib.len() as u32);
                 primitive_index_offset_in_combined_index_buffer.push(index_offset_in_combined_ib);
+                let mut lod_index_ranges = vec![(index_offset_in_combined_ib, ib.len() as u32)];
+                let mut lod_errors = vec![0.0];
                 index_offset_in_combined_ib += ib.len() as u32;
                 indices.append(&mut ib);
+
+                // coarser levels follow, sharing the primitive's vertices
+                for lod in &primitive.lods {
+                    lod_index_ranges.push((index_offset_in_combined_ib, lod.indices.len() as u32));
+                    lod_errors.push(lod.error);
+                    index_offset_in_combined_ib += lod.indices.len() as u32;
+                    indices.extend_from_slice(&lod.indices);
+                }
+                primitive_lod_index_ranges.push(lod_index_ranges);
+                primitive_lod_errors.push(lod_errors);
+                primitive_bounding_spheres.push(primitive.bounding_sphere);
             }
         }
 
         let vb = ctx.upload_buffer(&vertices, vk::BufferUsageFlags::VERTEX_BUFFER);
         let ib = ctx.upload_buffer(&indices, vk::BufferUsageFlags::INDEX_BUFFER);
+        let morph_deltas = (!morph_deltas.is_empty()).then(|| {
+            ctx.upload_buffer(
+                &morph_deltas,
+                vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            )
+        });
 
         let combined = FVFCombinedPrimitives {
             vb,
@@ -78,6 +149,13 @@ impl TraditionalAsset {
             primitive_index_count,
             primitive_index_offset_in_combined_index_buffer,
             primitive_parent_node_indices,
+            primitive_material_indices,
+            morph_deltas,
+            primitive_morph_delta_offset_in_combined_buffer,
+            primitive_morph_target_count,
+            primitive_lod_index_ranges,
+            primitive_lod_errors,
+            primitive_bounding_spheres,
         };
 
         let mut meshes = vec![Mesh {
@@ -85,39 +163,117 @@ impl TraditionalAsset {
             primitives: Primitives::FixedVertexFunctionCombined(combined),
         }];
 
+        let materials_buffer = upload_materials(ctx, &asset_data.materials, &textures.slots);
+
         let nodes: Vec<Node> = asset_data.nodes.clone();
         let scenes: Vec<Scene> = asset_data.scenes.clone();
 
         println!("Meshes count: {} (Traditional)", meshes.len());
 
+        let skins: Vec<Skin> = asset_data.skins.clone();
+
         let mut node_transform_data = vec![];
+        let mut skin_palettes = vec![];
+        let mut morph_weights = vec![];
         let mut instances_buffers = vec![];
         let mut offsets_buffers = vec![];
-        let mut indirect_draw_buffers = vec![];
+        let mut indirect_draws = vec![];
 
         for scene in &scenes {
-            let transform_data = build_node_transformation_data(ctx, &mut meshes, &nodes, &scene);
+            let transform_data =
+                build_node_transformation_data(ctx, &mut meshes, &nodes, scene, transform);
+            let palettes = SkinPalettes::new(ctx, &nodes, &skins, scene);
+            let weights = MorphWeights::new(ctx, &nodes, scene);
 
             let (offsets_buffer, instances_buffer) = fvf_build_instance_data(
+                ctx,
+                &transform_data.node_transform_buffer_address,
+                &palettes,
+                &skins,
+                &weights,
+                &meshes,
+            );
+            let draws = fvf_build_indirect_draws(
                 ctx,
                 &transform_data.node_transform_buffer_address,
                 &meshes,
             );
-            let indirect_buf = fvf_build_indirect_buffer(ctx, &meshes);
 
             offsets_buffers.push(offsets_buffer);
             instances_buffers.push(instances_buffer);
-            indirect_draw_buffers.push(indirect_buf);
+            indirect_draws.push(draws);
             node_transform_data.push(transform_data);
+            skin_palettes.push(palettes);
+            morph_weights.push(weights);
         }
 
         Self {
             meshes,
-            default_scene: None,
-            _node_transform_data: node_transform_data,
+            current_scene: asset_data.default_scene.unwrap_or(0),
+            node_transform_data,
+            rest_nodes: nodes.clone(),
+            nodes,
+            skins,
+            skin_palettes,
+            morph_weights,
+            materials_buffer,
             instances_buffers,
             offsets_buffers,
-            indirect_draw_buffers,
+            indirect_draws,
+        }
+    }
+
+    // Poses the node hierarchy from the rest pose and rewrites the model matrices, joint palettes
+    // and morph weights
+    pub fn pose(&mut self, pose: &Pose) {
+        self.nodes.clone_from(&self.rest_nodes);
+        for (node_index, weights) in pose.morph_weights {
+            self.nodes[*node_index].weights.clone_from(weights);
+        }
+        if let Some(animation) = pose.animation {
+            animation.apply(&mut self.nodes, pose.time);
+        }
+
+        for transform_data in &self.node_transform_data {
+            transform_data.update(&self.nodes);
+        }
+        for palettes in &self.skin_palettes {
+            palettes.update(&self.nodes, &self.skins);
+        }
+        for weights in &self.morph_weights {
+            weights.update(&self.nodes);
+        }
+    }
+
+    // Points every draw of the current scene at the level `view` picks for its instance, counting
+    // the draws per level. The indirect buffer is rewritten in place, nothing may be in flight.
+    pub fn select_lods(&mut self, view: &LodView, draw_counts: &mut [usize]) {
+        let Some(draws) = self.indirect_draws.get_mut(self.current_scene) else {
+            return;
+        };
+        let globals = self.node_transform_data[self.current_scene].world_transforms(&self.nodes);
+        for mesh in &self.meshes {
+            if let Primitives::FixedVertexFunctionCombined(primitives) = &mesh.primitives {
+                for (command, &(node_index, primitive_index)) in
+                    std::iter::zip(&mut draws.commands, &draws.instances)
+                {
+                    let level = view.select(
+                        &primitives.primitive_lod_errors[primitive_index],
+                        &primitives.primitive_bounding_spheres[primitive_index],
+                        &globals[node_index],
+                    );
+                    let (first_index, index_count) =
+                        primitives.primitive_lod_index_ranges[primitive_index][level];
+                    command.first_index = first_index;
+                    command.index_count = index_count;
+                    if let Some(count) = draw_counts.get_mut(level) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        if !draws.commands.is_empty() {
+            draws.buffer.update_contents(&draws.commands);
         }
     }
 
@@ -135,8 +291,9 @@ impl TraditionalAsset {
                     self.instances_buffers[scene_index].device_address.unwrap();
                 push_constants.instance_offsets =
                     self.offsets_buffers[scene_index].device_address.unwrap();
+                push_constants.materials = self.materials_buffer.device_address.unwrap();
 
-                let (indirect_buf, draw_count) = &self.indirect_draw_buffers[scene_index];
+                let draws = &self.indirect_draws[scene_index];
 
                 unsafe {
                     device.cmd_push_constants(
@@ -166,9 +323,9 @@ impl TraditionalAsset {
 
                     device.cmd_draw_indexed_indirect(
                         command_buffer,
-                        indirect_buf.handle,
+                        draws.buffer.handle,
                         0,
-                        *draw_count as u32,
+                        draws.commands.len() as u32,
                         std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
                     );
                 }
@@ -179,60 +336,111 @@ impl TraditionalAsset {
 
 impl std::ops::Drop for TraditionalAsset {
     fn drop(&mut self) {
+        self.materials_buffer.vk_destroy();
         for buf in &self.instances_buffers {
             buf.vk_destroy();
         }
         for buf in &self.offsets_buffers {
             buf.vk_destroy();
         }
-        for (buf, _) in &self.indirect_draw_buffers {
-            buf.vk_destroy();
-        }
     }
 }
 
-fn fvf_build_indirect_buffer(
+// Full resolution until select_lods runs, the buffer stays mapped for it
+fn fvf_build_indirect_draws(
     ctx: &vkutils::context::VulkanContext,
+    node_transform_buffer_address: &std::collections::HashMap<usize, vk::DeviceAddress>,
     meshes: &[Mesh],
-) -> (vkutils::buffer::Buffer, usize) {
-    let mut draws = vec![];
+) -> SceneDraws {
+    let mut commands = vec![];
+    let mut instances = vec![];
     for mesh in meshes {
         if let Primitives::FixedVertexFunctionCombined(primitives) = &mesh.primitives {
             for (i, &index_count) in primitives.primitive_index_count.iter().enumerate() {
-                draws.push(vk::DrawIndexedIndirectCommand {
-                    index_count,
-                    instance_count: primitives.primitive_parent_node_indices[i].len() as u32,
-                    first_index: primitives.primitive_index_offset_in_combined_index_buffer[i],
-                    vertex_offset: primitives.primitive_vertex_offset_in_combined_vertex_buffer[i]
-                        as i32,
-                    first_instance: 0,
-                });
+                // same order and filter as fvf_build_instance_data
+                for &node_index in &primitives.primitive_parent_node_indices[i] {
+                    // nodes of other scenes have no transform
+                    if !node_transform_buffer_address.contains_key(&node_index) {
+                        continue;
+                    }
+                    commands.push(vk::DrawIndexedIndirectCommand {
+                        index_count,
+                        instance_count: 1,
+                        first_index: primitives.primitive_index_offset_in_combined_index_buffer[i],
+                        vertex_offset: primitives.primitive_vertex_offset_in_combined_vertex_buffer
+                            [i] as i32,
+                        first_instance: 0,
+                    });
+                    instances.push((node_index, i));
+                }
             }
         }
     }
-    let draw_count = draws.len();
-    let buffer = ctx.upload_buffer(&draws, vk::BufferUsageFlags::INDIRECT_BUFFER);
-    (buffer, draw_count)
+
+    // zero-sized buffers are invalid, keep room for one command around
+    let buffer = ctx.create_bar_buffer(
+        commands.len().max(1) * std::mem::size_of::<vk::DrawIndexedIndirectCommand>(),
+        vk::BufferUsageFlags::INDIRECT_BUFFER,
+    );
+    if !commands.is_empty() {
+        buffer.update_contents(&commands);
+    }
+    SceneDraws {
+        buffer,
+        commands,
+        instances,
+    }
 }
 
 fn fvf_build_instance_data(
     ctx: &vkutils::context::VulkanContext,
     node_transform_buffer_address: &std::collections::HashMap<usize, vk::DeviceAddress>,
+    skin_palettes: &SkinPalettes,
+    skins: &[Skin],
+    morph_weights: &MorphWeights,
     meshes: &Vec<Mesh>,
 ) -> (vkutils::buffer::Buffer, vkutils::buffer::Buffer) {
     let mut instance_data = vec![];
     let mut instance_offset = vec![];
     for mesh in meshes {
         if let Primitives::FixedVertexFunctionCombined(primitives) = &mesh.primitives {
-            let mut offset = 0 as u32;
-            for node_indices in &primitives.primitive_parent_node_indices {
+            let morph_deltas_address = primitives
+                .morph_deltas
+                .as_ref()
+                .map_or(0, |buffer| buffer.device_address.unwrap());
+            for (primitive_index, (node_indices, material_index)) in std::iter::zip(
+                &primitives.primitive_parent_node_indices,
+                &primitives.primitive_material_indices,
+            )
+            .enumerate()
+            {
+                let morph_deltas = morph_deltas_address
+                    + (primitives.primitive_morph_delta_offset_in_combined_buffer[primitive_index]
+                        as usize
+                        * std::mem::size_of::<MorphDelta>())
+                        as vk::DeviceAddress;
+                let morph_target_count = primitives.primitive_morph_target_count[primitive_index];
                 for node_index in node_indices {
+                    // nodes of other scenes have no transform
+                    let Some(&transform) = node_transform_buffer_address.get(node_index) else {
+                        continue;
+                    };
+                    // every instance is a draw of its own, see fvf_build_indirect_draws
+                    instance_offset.push(instance_data.len() as u32);
+                    let (joints, joint_count) =
+                        skin_palettes.joints(*node_index, skins).unwrap_or((0, 0));
+                    let morph_weights = morph_weights.weights(*node_index).unwrap_or(0);
                     instance_data.push(TraditionalInstance {
-                        transform: *node_transform_buffer_address.get(node_index).unwrap(),
+                        transform,
+                        joints,
+                        morph_deltas,
+                        morph_weights,
+                        material_index: *material_index,
+                        joint_count,
+                        morph_target_count,
+                        _padding: 0,
                     });
                 }
-                instance_offset.push(offset);
-                offset += node_indices.len() as u32;
             }
         }
     }
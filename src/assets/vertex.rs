@@ -0,0 +1,86 @@
+use ash::vk;
+
+// Interleaved vertex shared by the traditional and meshlet paths.
+// Mirrors `Vertex` and the VERTEX_*_LOCATION defines in vertex_common.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    // w is the bitangent sign, 0 if there is no tangent - see tangents::NO_TANGENT
+    pub tangent: [f32; 4],
+    // TEXCOORD_1, e.g. lightmaps
+    pub tex_coord1: [f32; 2],
+    // COLOR_0, linear RGBA, white if absent
+    pub color: [f32; 4],
+    // JOINTS_0/WEIGHTS_0, indices into the skin's joints - all weights 0 if not skinned
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl Vertex {
+    pub const STRIDE: usize = std::mem::size_of::<Vertex>();
+
+    // (location, format, offset) of every attribute, locations match vertex_common.glsl
+    const ATTRIBUTES: [(u32, vk::Format, usize); 8] = [
+        (
+            0,
+            vk::Format::R32G32B32_SFLOAT,
+            std::mem::offset_of!(Vertex, position),
+        ),
+        (
+            1,
+            vk::Format::R32G32B32_SFLOAT,
+            std::mem::offset_of!(Vertex, normal),
+        ),
+        (
+            2,
+            vk::Format::R32G32_SFLOAT,
+            std::mem::offset_of!(Vertex, tex_coord),
+        ),
+        (
+            3,
+            vk::Format::R32G32B32A32_SFLOAT,
+            std::mem::offset_of!(Vertex, tangent),
+        ),
+        (
+            4,
+            vk::Format::R32G32_SFLOAT,
+            std::mem::offset_of!(Vertex, tex_coord1),
+        ),
+        (
+            5,
+            vk::Format::R32G32B32A32_SFLOAT,
+            std::mem::offset_of!(Vertex, color),
+        ),
+        (
+            6,
+            vk::Format::R32G32B32A32_UINT,
+            std::mem::offset_of!(Vertex, joints),
+        ),
+        (
+            7,
+            vk::Format::R32G32B32A32_SFLOAT,
+            std::mem::offset_of!(Vertex, weights),
+        ),
+    ];
+
+    pub fn binding_description(binding: u32) -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::default()
+            .binding(binding)
+            .stride(Self::STRIDE as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+    }
+
+    // Shaders may consume only a subset, unused attributes are fine in Vulkan
+    pub fn attribute_descriptions(binding: u32) -> [vk::VertexInputAttributeDescription; 8] {
+        Self::ATTRIBUTES.map(|(location, format, offset)| {
+            vk::VertexInputAttributeDescription::default()
+                .location(location)
+                .binding(binding)
+                .format(format)
+                .offset(offset as u32)
+        })
+    }
+}
@@ -27,6 +27,16 @@ pub struct Camera {
     gui_data: GuiData,
 }
 
+// A named viewpoint a camera can be reset to, e.g. a glTF camera node
+#[derive(Clone)]
+pub struct Preset {
+    pub name: std::string::String,
+    pub position: glm::Vec3,
+    // normalized look direction
+    pub direction: glm::Vec3,
+    pub projection: projection::Projection,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct GPUCameraData {
@@ -61,6 +71,60 @@ impl Camera {
         }
     }
 
+    // FPS camera at the preset's viewpoint. The view roll is lost, FPS movement has no roll.
+    pub fn from_preset(width: f32, height: f32, preset: &Preset) -> Camera {
+        let mut camera = Camera::new(width, height);
+
+        let mut projection = preset.projection.clone();
+        projection.set_viewport(width, height);
+        camera.gui_data.projection_selection = match projection {
+            projection::Projection::Perspective(_) => 0,
+            projection::Projection::Orthographic(_) => 1,
+        };
+        camera.current_projection = projection;
+        camera.cache_current_projection_props();
+        camera.projection_matrix = camera.current_projection.compute_matrix();
+
+        camera.set_position(preset.position);
+        camera.set_direction(preset.direction);
+
+        camera
+    }
+
+    // Moves the camera keeping its look direction, switches to FPS movement
+    pub fn set_position(&mut self, position: glm::Vec3) {
+        self.movement = Box::new(movement::fps::FPS::new_from_angles(
+            position,
+            self.movement.yaw(),
+            self.movement.pitch(),
+        ));
+        self.gui_data.movement_selection = 0;
+        self.view_matrix = self.movement.compute_matrix();
+    }
+
+    // Turns the camera to look along `direction`, switches to FPS movement
+    pub fn set_direction(&mut self, direction: glm::Vec3) {
+        // FPS cameras look along the negated direction vector, pitch limited like look_around
+        let dir = -direction.normalize();
+        let pitch = dir
+            .y
+            .clamp(-1.0, 1.0)
+            .asin()
+            .clamp(-89.0_f32.to_radians(), 89.0_f32.to_radians());
+        self.movement = Box::new(movement::fps::FPS::new_from_angles(
+            self.movement.position().xyz(),
+            dir.z.atan2(dir.x),
+            pitch,
+        ));
+        self.gui_data.movement_selection = 0;
+        self.view_matrix = self.movement.compute_matrix();
+    }
+
+    // normalized
+    pub fn look_direction(&self) -> glm::Vec3 {
+        -movement::direction_vector(self.movement.yaw(), self.movement.pitch())
+    }
+
     pub fn update_pos(&mut self) {
         self.movement.update_position(self.movement_flags.clone());
         self.view_matrix = self.movement.compute_matrix();
@@ -52,9 +52,24 @@ pub mod orthtographic {
                 scale: [scale],
             }
         }
+
+        // Fixed half extents and view distances, e.g. from a glTF camera. Near and far are
+        // swapped to get the same reversed depth as the perspective projection.
+        pub fn from_extents(xmag: f32, ymag: f32, near: f32, far: f32) -> Self {
+            Self {
+                left: -xmag,
+                right: xmag,
+                bottom: -ymag,
+                top: ymag,
+                near: far,
+                far: near,
+                scale: [1.0],
+            }
+        }
     }
 }
 
+#[derive(Clone)]
 pub enum Projection {
     Perspective(perspective::Properties),
     Orthographic(orthtographic::Properties),
@@ -90,6 +105,13 @@ impl Projection {
         changed
     }
 
+    // Perspective projections follow the viewport, orthographic extents stay as they are
+    pub fn set_viewport(&mut self, w: f32, h: f32) {
+        if let Projection::Perspective(props) = self {
+            props.aspect = w / h;
+        }
+    }
+
     pub fn compute_matrix(&self) -> glm::Mat4 {
         match self {
             Projection::Perspective(props) => glm::reversed_perspective_rh_zo(
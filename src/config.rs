@@ -0,0 +1,815 @@
+use crate::renderer::TargetRender;
+use crate::skybox;
+
+pub const USAGE: &str = "\
+Usage: grass-rs [OPTIONS] [ASSET...]
+
+Arguments:
+  [ASSET...]                glTF files (.gltf/.glb) to load at the origin, replace the scene
+                            file's assets
+
+Options:
+  --config <FILE>           JSON scene file, relative paths in it resolve against its directory
+  --window-size <W>x<H>     initial window size, default 1440x900
+  --target-render <TARGET>  scene, scene-depth, shadow-map or meshlet (default)
+  --camera-position <X,Y,Z> initial camera position, default 0,1,5
+  --optimize-meshes         reorder vertices and indices with meshopt on load, prints the
+                            vertex cache and overdraw stats before and after
+  -h, --help                print this help
+
+Scene file, every key optional, command line options win. The GUI saves the current scene in
+the same format. Assets are paths or placed with a translation, XYZ euler rotation, scale and
+the glTF scene to show. The directional light casts the shadows, `lights` adds unshadowed
+lights on top of the glTF ones and `cameras` presets next to the glTF cameras. Angles are in
+degrees. `lods` are the simplification steps every primitive gets, each a ratio of the full
+triangle count and an error bound relative to the primitive's size, [] turns the levels off.
+`cluster_lod` builds the meshlet path's cluster hierarchy, false draws the levels there too.
+  {
+    \"assets\": [
+      \"models/ground.gltf\",
+      {\"path\": \"models/house.glb\", \"translation\": [4, 0, 0], \"rotation\": [0, 90, 0]},
+      {\"path\": \"models/crate.gltf\", \"scale\": [0.5, 0.5, 0.5], \"scene\": 1}
+    ],
+    \"window_size\": [1440, 900],
+    \"target_render\": \"meshlet\",
+    \"camera_position\": [0.0, 1.0, 5.0],
+    \"camera_direction\": [0.0, 0.0, -1.0],
+    \"optimize_meshes\": false,
+    \"lods\": [{\"ratio\": 0.5, \"max_error\": 0.01}, {\"ratio\": 0.25, \"max_error\": 0.02}],
+    \"cluster_lod\": true,
+    \"directional_light\": {
+      \"azimuth\": 30, \"inclination\": 45, \"distance\": 1.6, \"xz_target\": [0, 0],
+      \"color\": [1, 1, 1]
+    },
+    \"lights\": [
+      {\"type\": \"point\", \"position\": [0, 2, 0], \"color\": [1, 0.8, 0.6], \"intensity\": 5,
+       \"range\": 10},
+      {\"type\": \"spot\", \"position\": [2, 3, 0], \"direction\": [0, -1, 0], \"intensity\": 20,
+       \"inner_cone_angle\": 15, \"outer_cone_angle\": 30}
+    ],
+    \"skybox\": \"daylight\",
+    \"cameras\": [
+      {\"name\": \"Overview\", \"position\": [0, 10, 10], \"direction\": [0, -1, -1], \"fov\": 60}
+    ]
+  }";
+
+// glTF file and where its scenes are placed in the world
+#[derive(Clone, Debug)]
+pub struct AssetConfig {
+    pub path: std::path::PathBuf,
+    pub translation: glm::Vec3,
+    // euler angles in degrees, applied around X, then Y, then Z
+    pub rotation: glm::Vec3,
+    pub scale: glm::Vec3,
+    // None shows the glTF default scene
+    pub scene: Option<usize>,
+}
+
+impl AssetConfig {
+    // at the origin
+    pub fn new(path: std::path::PathBuf) -> Self {
+        Self {
+            path,
+            translation: glm::Vec3::zeros(),
+            rotation: glm::Vec3::zeros(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+            scene: None,
+        }
+    }
+
+    pub fn transform(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&euler_xyz(&self.rotation))
+            * glm::scaling(&self.scale)
+    }
+}
+
+// Shadow casting sun, orbits the XZ target like the GUI controls
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirLightConfig {
+    pub azimuth: f32,
+    pub inclination: f32,
+    pub distance: f32,
+    pub xz_target: [f32; 2],
+    pub color: [f32; 3],
+}
+
+impl Default for DirLightConfig {
+    fn default() -> Self {
+        Self {
+            azimuth: 30.0,
+            inclination: 45.0,
+            distance: 1.6,
+            xz_target: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightType {
+    Directional,
+    Point,
+    Spot,
+}
+
+// Punctual light in world space, same units as KHR_lights_punctual
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightConfig {
+    #[serde(rename = "type")]
+    pub light_type: LightType,
+    pub position: [f32; 3],
+    // direction the light shines in, unused by point lights
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<f32>,
+    // degrees, spot lights only
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        Self {
+            light_type: LightType::Point,
+            position: [0.0, 0.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            range: None,
+            inner_cone_angle: 0.0,
+            outer_cone_angle: 45.0,
+        }
+    }
+}
+
+// One level of the LOD chain, see assets::lod::LodLevel
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LodConfig {
+    pub ratio: f32,
+    pub max_error: f32,
+}
+
+// what assets get without `lods` in the scene file
+const DEFAULT_LODS: [LodConfig; 3] = [
+    LodConfig {
+        ratio: 0.5,
+        max_error: 0.01,
+    },
+    LodConfig {
+        ratio: 0.25,
+        max_error: 0.02,
+    },
+    LodConfig {
+        ratio: 0.125,
+        max_error: 0.05,
+    },
+];
+
+// Perspective camera preset
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraConfig {
+    pub name: std::string::String,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    // vertical, degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            name: "Camera".to_string(),
+            position: [0.0, 1.0, 5.0],
+            direction: [0.0, 0.0, -1.0],
+            fov: 45.0,
+            near: 0.01,
+            far: 500.0,
+        }
+    }
+}
+
+// Startup settings, what a scene file holds
+#[derive(Clone)]
+pub struct Config {
+    pub assets: std::vec::Vec<AssetConfig>,
+    pub window_size: [u32; 2],
+    pub target_render: TargetRender,
+    // None keeps the camera default
+    pub camera_position: Option<glm::Vec3>,
+    pub camera_direction: Option<glm::Vec3>,
+    pub directional_light: DirLightConfig,
+    pub lights: std::vec::Vec<LightConfig>,
+    // index into skybox::NAMES
+    pub skybox: usize,
+    pub cameras: std::vec::Vec<CameraConfig>,
+    pub optimize_meshes: bool,
+    pub lods: std::vec::Vec<LodConfig>,
+    pub cluster_lod: bool,
+    // scene file the config was loaded from
+    pub file: Option<std::path::PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            assets: vec![],
+            window_size: [1440, 900],
+            target_render: TargetRender::Meshlet,
+            camera_position: None,
+            camera_direction: None,
+            directional_light: DirLightConfig::default(),
+            lights: vec![],
+            skybox: 0,
+            cameras: vec![],
+            optimize_meshes: false,
+            lods: DEFAULT_LODS.to_vec(),
+            cluster_lod: true,
+            file: None,
+        }
+    }
+}
+
+// On-disk form of the config, see USAGE
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    assets: std::vec::Vec<AssetEntry>,
+    window_size: Option<[u32; 2]>,
+    target_render: Option<std::string::String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera_position: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera_direction: Option<[f32; 3]>,
+    directional_light: Option<DirLightConfig>,
+    #[serde(default)]
+    lights: std::vec::Vec<LightConfig>,
+    skybox: Option<std::string::String>,
+    #[serde(default)]
+    cameras: std::vec::Vec<CameraConfig>,
+    optimize_meshes: Option<bool>,
+    lods: Option<std::vec::Vec<LodConfig>>,
+    cluster_lod: Option<bool>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum AssetEntry {
+    Path(std::path::PathBuf),
+    Placed(PlacedAsset),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlacedAsset {
+    path: std::path::PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translation: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scene: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    UnknownOption(std::string::String),
+    MissingValue(std::string::String),
+    InvalidValue {
+        option: std::string::String,
+        value: std::string::String,
+        expected: &'static str,
+    },
+    Read {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: std::path::PathBuf,
+        source: serde_json::Error,
+    },
+    Write {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            ConfigError::MissingValue(option) => write!(f, "{} needs a value", option),
+            ConfigError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(f, "invalid {} {:?}, expected {}", option, value, expected),
+            ConfigError::Read { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(f, "could not parse {}: {}", path.display(), source)
+            }
+            ConfigError::Write { path, source } => {
+                write!(f, "could not write {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Write { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Config {
+    // Command line arguments without the program name. None if help was requested.
+    pub fn from_args(
+        args: impl IntoIterator<Item = std::string::String>,
+    ) -> Result<Option<Self>, ConfigError> {
+        let mut config_path = None;
+        let mut assets = vec![];
+        let mut window_size = None;
+        let mut target_render = None;
+        let mut camera_position = None;
+        let mut optimize_meshes = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| {
+                args.next()
+                    .ok_or_else(|| ConfigError::MissingValue(option.to_string()))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--config" => config_path = Some(std::path::PathBuf::from(value(&arg)?)),
+                "--window-size" => window_size = Some(parse_window_size(&arg, &value(&arg)?)?),
+                "--target-render" => {
+                    target_render = Some(parse_target_render(&arg, &value(&arg)?)?)
+                }
+                "--camera-position" => camera_position = Some(parse_position(&arg, &value(&arg)?)?),
+                "--optimize-meshes" => optimize_meshes = true,
+                option if option.starts_with("--") => {
+                    return Err(ConfigError::UnknownOption(option.to_string()))
+                }
+                _ => assets.push(AssetConfig::new(std::path::PathBuf::from(arg))),
+            }
+        }
+
+        let mut config = match config_path {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        if !assets.is_empty() {
+            config.assets = assets;
+        }
+        if let Some(window_size) = window_size {
+            config.window_size = window_size;
+        }
+        if let Some(target_render) = target_render {
+            config.target_render = target_render;
+        }
+        if camera_position.is_some() {
+            config.camera_position = camera_position;
+        }
+        if optimize_meshes {
+            config.optimize_meshes = true;
+        }
+        Ok(Some(config))
+    }
+
+    // Relative asset paths resolve against the config file's directory
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: ConfigFile =
+            serde_json::from_str(&text).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        let defaults = Self::default();
+        Ok(Self {
+            assets: file
+                .assets
+                .into_iter()
+                .map(|entry| match entry {
+                    AssetEntry::Path(path) => AssetConfig::new(dir.join(path)),
+                    AssetEntry::Placed(placed) => AssetConfig {
+                        path: dir.join(placed.path),
+                        translation: placed
+                            .translation
+                            .map_or(glm::Vec3::zeros(), |t| glm::make_vec3(&t)),
+                        rotation: placed
+                            .rotation
+                            .map_or(glm::Vec3::zeros(), |r| glm::make_vec3(&r)),
+                        scale: placed
+                            .scale
+                            .map_or(glm::vec3(1.0, 1.0, 1.0), |s| glm::make_vec3(&s)),
+                        scene: placed.scene,
+                    },
+                })
+                .collect(),
+            window_size: file.window_size.unwrap_or(defaults.window_size),
+            target_render: match file.target_render {
+                Some(target_render) => parse_target_render("target_render", &target_render)?,
+                None => defaults.target_render,
+            },
+            camera_position: file.camera_position.map(|p| glm::make_vec3(&p)),
+            camera_direction: file.camera_direction.map(|d| glm::make_vec3(&d)),
+            directional_light: file.directional_light.unwrap_or_default(),
+            lights: file.lights,
+            skybox: match file.skybox {
+                Some(name) => skybox::NAMES
+                    .iter()
+                    .position(|skybox| *skybox == name)
+                    .ok_or_else(|| invalid_value("skybox", &name, "daylight or learnopengl"))?,
+                None => defaults.skybox,
+            },
+            cameras: file.cameras,
+            optimize_meshes: file.optimize_meshes.unwrap_or(defaults.optimize_meshes),
+            lods: match file.lods {
+                Some(lods) => {
+                    for lod in &lods {
+                        let valid = lod.ratio > 0.0 && lod.ratio < 1.0 && lod.max_error >= 0.0;
+                        if !valid {
+                            return Err(invalid_value(
+                                "lods",
+                                &format!("{:?}", lod),
+                                "ratio between 0 and 1 and a max_error of at least 0",
+                            ));
+                        }
+                    }
+                    lods
+                }
+                None => defaults.lods,
+            },
+            cluster_lod: file.cluster_lod.unwrap_or(defaults.cluster_lod),
+            file: Some(path.to_path_buf()),
+        })
+    }
+
+    // Scene file loading back into this config, asset paths relative to the file's directory
+    pub fn save(&self, path: &std::path::Path) -> Result<(), ConfigError> {
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        // placement left at its default is not written
+        let unless = |v: &glm::Vec3, default: glm::Vec3| (*v != default).then_some([v.x, v.y, v.z]);
+        let file = ConfigFile {
+            assets: self
+                .assets
+                .iter()
+                .map(|asset| {
+                    let placed = PlacedAsset {
+                        path: relative_path(&asset.path, dir),
+                        translation: unless(&asset.translation, glm::Vec3::zeros()),
+                        rotation: unless(&asset.rotation, glm::Vec3::zeros()),
+                        scale: unless(&asset.scale, glm::vec3(1.0, 1.0, 1.0)),
+                        scene: asset.scene,
+                    };
+                    match placed {
+                        PlacedAsset {
+                            path,
+                            translation: None,
+                            rotation: None,
+                            scale: None,
+                            scene: None,
+                        } => AssetEntry::Path(path),
+                        placed => AssetEntry::Placed(placed),
+                    }
+                })
+                .collect(),
+            window_size: Some(self.window_size),
+            target_render: Some(target_render_name(self.target_render).to_string()),
+            camera_position: self.camera_position.map(|p| [p.x, p.y, p.z]),
+            camera_direction: self.camera_direction.map(|d| [d.x, d.y, d.z]),
+            directional_light: Some(self.directional_light.clone()),
+            lights: self.lights.clone(),
+            skybox: Some(skybox::NAMES[self.skybox].to_string()),
+            cameras: self.cameras.clone(),
+            optimize_meshes: Some(self.optimize_meshes),
+            lods: Some(self.lods.clone()),
+            cluster_lod: Some(self.cluster_lod),
+        };
+
+        // plain data, serializing can't fail
+        let text = serde_json::to_string_pretty(&file).expect("Failed to serialize scene");
+        std::fs::write(path, text).map_err(|source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+// rotation around X, then Y, then Z
+fn euler_xyz(degrees: &glm::Vec3) -> glm::Quat {
+    let axis_angle = |angle: f32, axis: glm::Vec3| glm::quat_angle_axis(angle.to_radians(), &axis);
+    axis_angle(degrees.z, glm::Vec3::z())
+        * axis_angle(degrees.y, glm::Vec3::y())
+        * axis_angle(degrees.x, glm::Vec3::x())
+}
+
+// `path` as seen from `dir`, going up with .. where needed so scene files can be shared. Paths
+// that don't exist stay as they are.
+fn relative_path(path: &std::path::Path, dir: &std::path::Path) -> std::path::PathBuf {
+    let dir = if dir.as_os_str().is_empty() {
+        std::path::Path::new(".")
+    } else {
+        dir
+    };
+    let (Ok(path_abs), Ok(dir_abs)) = (std::fs::canonicalize(path), std::fs::canonicalize(dir))
+    else {
+        return path.to_path_buf();
+    };
+
+    let path_components: std::vec::Vec<_> = path_abs.components().collect();
+    let dir_components: std::vec::Vec<_> = dir_abs.components().collect();
+    let common = std::iter::zip(&path_components, &dir_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // nothing in common but the root, e.g. another drive on windows
+    if common <= 1 {
+        return path_abs;
+    }
+
+    let mut relative = std::path::PathBuf::new();
+    for _ in common..dir_components.len() {
+        relative.push("..");
+    }
+    relative.extend(&path_components[common..]);
+    relative
+}
+
+fn invalid_value(option: &str, value: &str, expected: &'static str) -> ConfigError {
+    ConfigError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        expected,
+    }
+}
+
+fn parse_window_size(option: &str, value: &str) -> Result<[u32; 2], ConfigError> {
+    let size = value
+        .split_once('x')
+        .and_then(|(w, h)| Some([w.parse().ok()?, h.parse().ok()?]))
+        .filter(|size: &[u32; 2]| size[0] > 0 && size[1] > 0);
+    size.ok_or_else(|| invalid_value(option, value, "<width>x<height>, e.g. 1440x900"))
+}
+
+fn parse_target_render(option: &str, value: &str) -> Result<TargetRender, ConfigError> {
+    match value {
+        "scene" => Ok(TargetRender::Scene),
+        "scene-depth" => Ok(TargetRender::SceneDepth),
+        "shadow-map" => Ok(TargetRender::ShadowMap),
+        "meshlet" => Ok(TargetRender::Meshlet),
+        _ => Err(invalid_value(
+            option,
+            value,
+            "scene, scene-depth, shadow-map or meshlet",
+        )),
+    }
+}
+
+fn target_render_name(target_render: TargetRender) -> &'static str {
+    match target_render {
+        TargetRender::Scene => "scene",
+        TargetRender::SceneDepth => "scene-depth",
+        TargetRender::ShadowMap => "shadow-map",
+        TargetRender::Meshlet => "meshlet",
+    }
+}
+
+fn parse_position(option: &str, value: &str) -> Result<glm::Vec3, ConfigError> {
+    let components: std::vec::Vec<f32> = value
+        .split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid_value(option, value, "<x>,<y>,<z>"))?;
+    match components[..] {
+        [x, y, z] => Ok(glm::vec3(x, y, z)),
+        _ => Err(invalid_value(option, value, "<x>,<y>,<z>")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::testing::{temp_file, TestDir};
+
+    fn args(args: &[&str]) -> std::vec::Vec<std::string::String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn command_line_overrides_file() {
+        let (_dir, path) = temp_file("scene.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "assets": ["ground.gltf"],
+                "window_size": [800, 600],
+                "target_render": "scene",
+                "camera_position": [1, 2, 3],
+                "optimize_meshes": false,
+                "cluster_lod": false
+            }"#,
+        )
+        .unwrap();
+
+        let config = Config::from_args(args(&[
+            "--config",
+            path.to_str().unwrap(),
+            "--window-size",
+            "1024x768",
+            "--target-render",
+            "shadow-map",
+            "--camera-position",
+            "4,5,6",
+            "--optimize-meshes",
+            "house.glb",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        let paths: std::vec::Vec<_> = config.assets.iter().map(|a| a.path.clone()).collect();
+        assert_eq!(paths, [std::path::PathBuf::from("house.glb")]);
+        assert_eq!(config.window_size, [1024, 768]);
+        assert_eq!(config.target_render, TargetRender::ShadowMap);
+        assert_eq!(config.camera_position, Some(glm::vec3(4.0, 5.0, 6.0)));
+        assert!(config.optimize_meshes);
+        // not on the command line, the file's value stays
+        assert!(!config.cluster_lod);
+        assert_eq!(config.file.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn file_values_kept_without_command_line() {
+        let (_dir, path) = temp_file("scene.json");
+        std::fs::write(
+            &path,
+            r#"{"assets": ["ground.gltf"], "window_size": [800, 600], "target_render": "scene"}"#,
+        )
+        .unwrap();
+
+        let config = Config::from_args(args(&["--config", path.to_str().unwrap()]))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(config.assets.len(), 1);
+        assert_eq!(
+            config.assets[0].path,
+            path.parent().unwrap().join("ground.gltf")
+        );
+        assert_eq!(config.window_size, [800, 600]);
+        assert_eq!(config.target_render, TargetRender::Scene);
+        assert_eq!(config.camera_position, None);
+    }
+
+    #[test]
+    fn invalid_command_lines() {
+        assert!(Config::from_args(args(&["--help"])).unwrap().is_none());
+        assert!(matches!(
+            Config::from_args(args(&["--frobnicate"])),
+            Err(ConfigError::UnknownOption(_))
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--window-size"])),
+            Err(ConfigError::MissingValue(_))
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--window-size", "0x900"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--camera-position", "1,2"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn relative_path_round_trip() {
+        let dir = TestDir::new("relative-path");
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::create_dir_all(dir.join("scenes")).unwrap();
+        let asset = dir.join("assets").join("cube.gltf");
+        std::fs::write(&asset, "{}").unwrap();
+
+        let relative = relative_path(&asset, &dir.join("scenes"));
+        assert_eq!(relative, std::path::Path::new("../assets/cube.gltf"));
+        assert_eq!(
+            relative_path(&asset, &dir),
+            std::path::Path::new("assets/cube.gltf")
+        );
+        // missing files are left alone
+        let missing = dir.join("missing.gltf");
+        assert_eq!(relative_path(&missing, &dir), missing);
+
+        // written relative to the scene file, resolved back against its directory on load
+        let scene_path = dir.join("scenes").join("scene.json");
+        Config {
+            assets: vec![AssetConfig::new(asset.clone())],
+            ..Config::default()
+        }
+        .save(&scene_path)
+        .unwrap();
+        let text = std::fs::read_to_string(&scene_path).unwrap();
+        assert!(text.contains(r#""../assets/cube.gltf""#), "{}", text);
+        let loaded = Config::from_file(&scene_path).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(&loaded.assets[0].path).unwrap(),
+            std::fs::canonicalize(&asset).unwrap()
+        );
+    }
+
+    #[test]
+    fn scene_round_trip() {
+        let (_dir, path) = temp_file("scene.json");
+        let config = Config {
+            assets: vec![
+                AssetConfig::new("ground.gltf".into()),
+                AssetConfig {
+                    translation: glm::vec3(4.0, 0.0, 0.0),
+                    rotation: glm::vec3(0.0, 90.0, 0.0),
+                    scale: glm::vec3(0.5, 0.5, 0.5),
+                    scene: Some(1),
+                    ..AssetConfig::new("house.glb".into())
+                },
+            ],
+            window_size: [800, 600],
+            target_render: TargetRender::SceneDepth,
+            camera_position: Some(glm::vec3(1.0, 2.0, 3.0)),
+            camera_direction: Some(glm::vec3(0.0, 0.0, 1.0)),
+            directional_light: DirLightConfig {
+                azimuth: 10.0,
+                ..DirLightConfig::default()
+            },
+            lights: vec![LightConfig {
+                light_type: LightType::Spot,
+                intensity: 20.0,
+                ..LightConfig::default()
+            }],
+            skybox: 1,
+            cameras: vec![CameraConfig {
+                name: "Overview".to_string(),
+                ..CameraConfig::default()
+            }],
+            optimize_meshes: true,
+            lods: vec![LodConfig {
+                ratio: 0.5,
+                max_error: 0.01,
+            }],
+            cluster_lod: false,
+            file: None,
+        };
+        config.save(&path).unwrap();
+        let loaded = Config::from_file(&path).unwrap();
+
+        // the assets don't exist, their paths are kept as given and resolve against the file
+        let dir = path.parent().unwrap();
+        assert_eq!(loaded.assets.len(), 2);
+        assert_eq!(loaded.assets[0].path, dir.join("ground.gltf"));
+        assert_eq!(loaded.assets[0].scale, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(loaded.assets[1].path, dir.join("house.glb"));
+        assert_eq!(loaded.assets[1].translation, config.assets[1].translation);
+        assert_eq!(loaded.assets[1].rotation, config.assets[1].rotation);
+        assert_eq!(loaded.assets[1].scale, config.assets[1].scale);
+        assert_eq!(loaded.assets[1].scene, Some(1));
+        assert_eq!(loaded.window_size, config.window_size);
+        assert_eq!(loaded.target_render, config.target_render);
+        assert_eq!(loaded.camera_position, config.camera_position);
+        assert_eq!(loaded.camera_direction, config.camera_direction);
+        assert_eq!(loaded.directional_light.azimuth, 10.0);
+        assert_eq!(loaded.lights.len(), 1);
+        assert_eq!(loaded.lights[0].light_type, LightType::Spot);
+        assert_eq!(loaded.lights[0].intensity, 20.0);
+        assert_eq!(loaded.skybox, 1);
+        assert_eq!(loaded.cameras.len(), 1);
+        assert_eq!(loaded.cameras[0].name, "Overview");
+        assert!(loaded.optimize_meshes);
+        assert_eq!(loaded.lods.len(), 1);
+        assert_eq!(loaded.lods[0].ratio, 0.5);
+        assert_eq!(loaded.lods[0].max_error, 0.01);
+        assert!(!loaded.cluster_lod);
+        assert_eq!(loaded.file.as_deref(), Some(path.as_path()));
+    }
+}
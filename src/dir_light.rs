@@ -34,7 +34,12 @@ pub struct DirLight {
 }
 
 impl DirLight {
-    pub fn new(mut data: GPUDirLight, ctx: &vkutils::context::VulkanContext) -> Self {
+    // `data.dir` is derived from the angles in `gui_data`
+    pub fn new(
+        mut data: GPUDirLight,
+        gui_data: GuiData,
+        ctx: &vkutils::context::VulkanContext,
+    ) -> Self {
         let buffer = ctx.create_bar_buffer(
             std::mem::size_of::<GPUDirLight>(),
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
@@ -49,13 +54,6 @@ impl DirLight {
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
         );
 
-        let gui_data = GuiData {
-            azimuth_deg: [30.0],
-            inclination_deg: [45.0],
-            distance: [1.6],
-            xz_target: [0.0, 0.0],
-        };
-
         update_gpu_buffers(
             &buffer,
             &camera_buffer,
@@ -88,6 +86,10 @@ impl DirLight {
             h: ctx.swapchain.extent.height as f32,
         }
     }
+
+    pub fn gui_data(&self) -> &GuiData {
+        &self.gui_data
+    }
 }
 
 fn update_gpu_buffers(
@@ -58,6 +58,14 @@ impl Gui {
         }
     }
 
+    // nodes of assets that finished loading after the GUI was created
+    pub fn add_scene_nodes(
+        &mut self,
+        scene_nodes: std::vec::Vec<std::rc::Rc<std::cell::RefCell<dyn GuiSceneNode>>>,
+    ) {
+        self.scene_nodes.extend(scene_nodes);
+    }
+
     pub fn update_delta_time(self: &mut Self, delta: std::time::Duration) {
         self.imguictx.io_mut().update_delta_time(delta);
     }
@@ -4,6 +4,7 @@ use winit::event_loop::{ControlFlow, EventLoop};
 mod app;
 mod assets;
 mod camera;
+mod config;
 mod dir_light;
 mod fps_window;
 mod grid;
@@ -17,10 +18,22 @@ mod vkutils;
 extern crate nalgebra_glm as glm;
 
 fn main() {
+    let config = match config::Config::from_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", config::USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, config::USAGE);
+            std::process::exit(2);
+        }
+    };
+
     let event_loop = EventLoop::new().expect("Error creating event loop.");
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::new();
+    let mut app = App::new(config);
 
     event_loop.run_app(&mut app).expect("App failed");
 }
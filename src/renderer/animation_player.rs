@@ -0,0 +1,140 @@
+use crate::assets::animation::{Animation, Pose};
+use crate::assets::gltf_asset::Node;
+use crate::gui_scene_node;
+
+// Clock, clip selection and morph weights of one loaded asset
+pub struct AnimationPlayer {
+    label: std::string::String,
+    animations: std::vec::Vec<Animation>,
+    current_animation: Option<usize>,
+    time: f32,
+    playing: bool,
+    // (node, weights) of every morphed mesh node, starting from the glTF defaults
+    morph_weights: std::vec::Vec<(usize, std::vec::Vec<f32>)>,
+    morph_weight_labels: std::vec::Vec<std::string::String>,
+    // the pose has to be re-evaluated even if paused, e.g. after scrubbing
+    dirty: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new(label: &str, animations: std::vec::Vec<Animation>, nodes: &[Node]) -> Self {
+        let has_animations = !animations.is_empty();
+        let morphed_nodes: std::vec::Vec<(usize, &Node)> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.mesh_index.is_some() && !node.weights.is_empty())
+            .collect();
+        Self {
+            label: label.to_string(),
+            animations,
+            current_animation: has_animations.then_some(0),
+            time: 0.0,
+            playing: has_animations,
+            morph_weights: morphed_nodes
+                .iter()
+                .map(|(node_index, node)| (*node_index, node.weights.clone()))
+                .collect(),
+            morph_weight_labels: morphed_nodes
+                .iter()
+                .map(|(node_index, node)| {
+                    node._name
+                        .clone()
+                        .unwrap_or_else(|| format!("Node {}", node_index))
+                })
+                .collect(),
+            dirty: false,
+        }
+    }
+
+    pub fn has_controls(&self) -> bool {
+        !self.animations.is_empty() || !self.morph_weights.is_empty()
+    }
+
+    // Advances the clock, looping the current clip. Returns the pose for the asset if it
+    // changed, a None clip is the rest pose.
+    pub fn advance(&mut self, delta: std::time::Duration) -> Option<Pose<'_>> {
+        let animation = self.current_animation.map(|index| &self.animations[index]);
+        if let (true, Some(animation)) = (self.playing, animation) {
+            self.time = if animation.duration > 0.0 {
+                (self.time + delta.as_secs_f32()) % animation.duration
+            } else {
+                0.0
+            };
+            self.dirty = true;
+        }
+
+        if !std::mem::take(&mut self.dirty) {
+            return None;
+        }
+        Some(Pose {
+            animation,
+            time: self.time,
+            morph_weights: &self.morph_weights,
+        })
+    }
+}
+
+impl gui_scene_node::GuiSceneNode for AnimationPlayer {
+    fn update(&mut self, ui: &imgui::Ui) {
+        if !self.has_controls() {
+            return;
+        }
+        if ui
+            .tree_node_config(format!("Animation ({})", self.label))
+            .opened(true, imgui::Condition::Appearing)
+            .push()
+            .is_some()
+        {
+            ui.indent();
+            if !self.animations.is_empty() {
+                let mut clip_names = vec!["Rest pose".to_string()];
+                clip_names.extend(self.animations.iter().enumerate().map(|(i, animation)| {
+                    animation
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("Animation {}", i))
+                }));
+                let mut selected = self.current_animation.map_or(0, |index| index + 1);
+                if ui.combo_simple_string("Clip", &mut selected, &clip_names) {
+                    self.current_animation = selected.checked_sub(1);
+                    self.time = 0.0;
+                    self.dirty = true;
+                }
+            }
+
+            if let Some(index) = self.current_animation {
+                if ui.button(if self.playing { "Pause" } else { "Play" }) {
+                    self.playing = !self.playing;
+                }
+                let duration = self.animations[index].duration;
+                if ui.slider("Time", 0.0, duration, &mut self.time) {
+                    self.dirty = true;
+                }
+            }
+
+            // animated weights override these while a clip targets them
+            if !self.morph_weights.is_empty()
+                && ui
+                    .tree_node_config("Morph weights")
+                    .opened(false, imgui::Condition::Appearing)
+                    .push()
+                    .is_some()
+            {
+                ui.indent();
+                for ((node_index, weights), label) in
+                    std::iter::zip(&mut self.morph_weights, &self.morph_weight_labels)
+                {
+                    ui.text(label);
+                    for (target, weight) in weights.iter_mut().enumerate() {
+                        let slider_label = format!("Target {}##{}", target, node_index);
+                        if ui.slider(slider_label, 0.0, 1.0, weight) {
+                            self.dirty = true;
+                        }
+                    }
+                }
+                ui.unindent();
+            }
+            ui.unindent();
+        }
+    }
+}
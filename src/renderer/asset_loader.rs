@@ -0,0 +1,308 @@
+use crate::assets::error::AssetError;
+use crate::assets::gltf_asset::{GltfAssetData, LoadOptions};
+use crate::assets::meshlet_asset;
+use crate::assets::meshlet_cache::PrimitiveClusters;
+use crate::assets::meshlet_vertex::VertexMemory;
+use crate::config::AssetConfig;
+use crate::gui_scene_node;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// how often loaded files are checked for changes
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+// CPU side of one asset, everything left is uploading it
+pub struct LoadedAsset {
+    pub asset_data: GltfAssetData,
+    pub meshlets: std::vec::Vec<std::vec::Vec<PrimitiveClusters>>,
+}
+
+// A file an asset was read from, with its modification time from before reading it
+type Source = (std::path::PathBuf, Option<std::time::SystemTime>);
+
+// What a worker sends back
+struct LoadResult {
+    asset_index: usize,
+    reload: bool,
+    // what to watch, only the already known files if loading failed
+    sources: std::vec::Vec<Source>,
+    result: Result<LoadedAsset, AssetError>,
+}
+
+// Shared with the worker threads
+#[derive(Default)]
+struct Progress {
+    // index into the assets of the next one a worker picks up
+    next_asset: AtomicUsize,
+    meshes_parsed: AtomicUsize,
+    meshlets_built: AtomicUsize,
+}
+
+// Parses assets and builds their meshlets on worker threads. The renderer takes finished assets
+// one at a time and uploads them, drawing whatever is resident in the meantime. Assets whose
+// files change afterwards are loaded again the same way.
+pub struct AssetLoader {
+    assets: std::vec::Vec<AssetConfig>,
+    options: LoadOptions,
+    sender: std::sync::mpsc::Sender<LoadResult>,
+    receiver: std::sync::mpsc::Receiver<LoadResult>,
+    progress: std::sync::Arc<Progress>,
+    // per asset, empty until its first load finished
+    sources: std::vec::Vec<std::vec::Vec<Source>>,
+    // per asset, a worker is on it
+    loading: std::vec::Vec<bool>,
+    // per asset, why its last load failed
+    errors: std::vec::Vec<Option<std::string::String>>,
+    last_watch: std::time::Instant,
+    // first loads handed out or skipped
+    finished: usize,
+    start: std::time::Instant,
+    load_time: Option<std::time::Duration>,
+    // the context's upload counter when loading started and the bytes uploaded since
+    uploaded_bytes_start: u64,
+    uploaded_bytes: u64,
+    // of the resident assets, the renderer keeps it current
+    pub meshlet_vertex_memory: VertexMemory,
+}
+
+impl AssetLoader {
+    pub fn new(assets: &[AssetConfig], options: LoadOptions, uploaded_bytes: u64) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let progress = std::sync::Arc::new(Progress::default());
+        let paths: std::sync::Arc<[std::path::PathBuf]> =
+            assets.iter().map(|asset| asset.path.clone()).collect();
+
+        let worker_count = std::thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(assets.len());
+        for _ in 0..worker_count {
+            let sender = sender.clone();
+            let progress = progress.clone();
+            let paths = paths.clone();
+            let options = options.clone();
+            std::thread::spawn(move || loop {
+                let asset_index = progress.next_asset.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(asset_index) else {
+                    break;
+                };
+                let (sources, result) = load(path, std::slice::from_ref(path), &options, &progress);
+                let loaded = LoadResult {
+                    asset_index,
+                    reload: false,
+                    sources,
+                    result,
+                };
+                // the renderer is gone, nobody waits for the rest
+                if sender.send(loaded).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            assets: assets.to_vec(),
+            options,
+            sender,
+            receiver,
+            progress,
+            sources: vec![vec![]; assets.len()],
+            loading: vec![true; assets.len()],
+            errors: vec![None; assets.len()],
+            last_watch: std::time::Instant::now(),
+            finished: 0,
+            start: std::time::Instant::now(),
+            load_time: None,
+            uploaded_bytes_start: uploaded_bytes,
+            uploaded_bytes: 0,
+            meshlet_vertex_memory: VertexMemory::default(),
+        }
+    }
+
+    pub fn asset_count(&self) -> usize {
+        self.assets.len()
+    }
+
+    // The next asset a worker finished, first loaded or reloaded, with its index into the
+    // config's assets. Broken ones are reported and skipped, a failed reload keeps the previous
+    // version. Also starts reloading assets whose files changed.
+    pub fn next(&mut self) -> Option<(usize, AssetConfig, LoadedAsset)> {
+        if self.last_watch.elapsed() >= WATCH_INTERVAL {
+            self.last_watch = std::time::Instant::now();
+            self.reload_changed();
+        }
+
+        while let Ok(loaded) = self.receiver.try_recv() {
+            let asset_index = loaded.asset_index;
+            let asset_config = &self.assets[asset_index];
+            if !loaded.reload {
+                self.finished += 1;
+            }
+            self.loading[asset_index] = false;
+            self.sources[asset_index] = loaded.sources;
+            match loaded.result {
+                Ok(asset) => {
+                    if loaded.reload {
+                        println!("Reloaded {}", asset_config.path.display());
+                    }
+                    self.errors[asset_index] = None;
+                    return Some((asset_index, asset_config.clone(), asset));
+                }
+                Err(err) => self.report_error(asset_index, loaded.reload, &err),
+            }
+        }
+        None
+    }
+
+    // Logs and shows why loading or uploading an asset failed. A failed reload keeps the previous
+    // version, a failed first load skips the asset.
+    pub fn report_error(&mut self, asset_index: usize, reload: bool, err: &AssetError) {
+        let path = self.assets[asset_index].path.display();
+        if reload {
+            eprintln!(
+                "Failed to reload {}, keeping the previous version: {}",
+                path, err
+            );
+        } else {
+            eprintln!("Skipping asset {}: {}", path, err);
+        }
+        self.errors[asset_index] = Some(err.to_string());
+    }
+
+    // Spawns a worker for every asset with a file modified, created or removed since it was read
+    fn reload_changed(&mut self) {
+        for asset_index in 0..self.assets.len() {
+            let sources = &self.sources[asset_index];
+            let changed = sources
+                .iter()
+                .any(|(path, modified)| modified_time(path) != *modified);
+            if self.loading[asset_index] || !changed {
+                continue;
+            }
+            self.loading[asset_index] = true;
+
+            let path = self.assets[asset_index].path.clone();
+            let known_sources: std::vec::Vec<std::path::PathBuf> =
+                sources.iter().map(|(path, _)| path.clone()).collect();
+            let options = self.options.clone();
+            let progress = self.progress.clone();
+            let sender = self.sender.clone();
+            std::thread::spawn(move || {
+                let (sources, result) = load(&path, &known_sources, &options, &progress);
+                let _ = sender.send(LoadResult {
+                    asset_index,
+                    reload: true,
+                    sources,
+                    result,
+                });
+            });
+        }
+    }
+
+    // `uploaded_bytes` is the context's counter, called after uploading what `next` handed out
+    pub fn update(&mut self, uploaded_bytes: u64) {
+        self.uploaded_bytes = uploaded_bytes - self.uploaded_bytes_start;
+        if self.load_time.is_none() && self.finished == self.assets.len() {
+            let load_time = self.start.elapsed();
+            println!("Load time: {:?}", load_time);
+            self.load_time = Some(load_time);
+        }
+    }
+}
+
+impl gui_scene_node::GuiSceneNode for AssetLoader {
+    fn update(&mut self, ui: &imgui::Ui) {
+        if ui
+            .tree_node_config("Assets")
+            .opened(true, imgui::Condition::Appearing)
+            .push()
+            .is_some()
+        {
+            ui.indent();
+            let fraction = if self.assets.is_empty() {
+                1.0
+            } else {
+                self.finished as f32 / self.assets.len() as f32
+            };
+            imgui::ProgressBar::new(fraction)
+                .overlay_text(format!("{}/{} assets", self.finished, self.assets.len()))
+                .build(ui);
+            ui.text(format!(
+                "Meshes parsed: {}",
+                self.progress.meshes_parsed.load(Ordering::Relaxed)
+            ));
+            ui.text(format!(
+                "Meshlets built: {}",
+                self.progress.meshlets_built.load(Ordering::Relaxed)
+            ));
+            ui.text(format!(
+                "Uploaded: {:.1} MB",
+                self.uploaded_bytes as f64 / (1024.0 * 1024.0)
+            ));
+            let memory = self.meshlet_vertex_memory;
+            if memory.unpacked > 0 {
+                ui.text(format!(
+                    "Meshlet vertices: {:.1} MB, {:.1} MB as f32 ({:.0}% saved)",
+                    memory.packed as f64 / (1024.0 * 1024.0),
+                    memory.unpacked as f64 / (1024.0 * 1024.0),
+                    100.0 * (1.0 - memory.packed as f64 / memory.unpacked as f64)
+                ));
+            }
+            if let Some(load_time) = self.load_time {
+                ui.text(format!("Load time: {:.2?}", load_time));
+            }
+            for (asset_config, error) in std::iter::zip(&self.assets, &self.errors) {
+                if let Some(error) = error {
+                    ui.text_colored(
+                        [1.0, 0.4, 0.4, 1.0],
+                        format!("{}: {}", asset_config.path.display(), error),
+                    );
+                }
+            }
+            ui.unindent();
+        }
+    }
+}
+
+// Parses the asset at `path` and builds its meshlets. `known_sources` are the files it was read
+// from last time, their modification times are taken before reading so a file saved during the
+// load triggers another one.
+fn load(
+    path: &std::path::Path,
+    known_sources: &[std::path::PathBuf],
+    options: &LoadOptions,
+    progress: &Progress,
+) -> (std::vec::Vec<Source>, Result<LoadedAsset, AssetError>) {
+    let mut sources: std::vec::Vec<Source> = known_sources
+        .iter()
+        .map(|source| (source.clone(), modified_time(source)))
+        .collect();
+    let result = GltfAssetData::with_progress(path, options, &mut || {
+        progress.meshes_parsed.fetch_add(1, Ordering::Relaxed);
+    })
+    .map(|asset_data| {
+        let meshlets = meshlet_asset::load_or_build_meshlets(&asset_data, options, &mut |count| {
+            progress.meshlets_built.fetch_add(count, Ordering::Relaxed);
+        });
+        LoadedAsset {
+            asset_data,
+            meshlets,
+        }
+    });
+
+    if let Ok(loaded) = &result {
+        let asset_sources = &loaded.asset_data.sources;
+        sources.retain(|(source, _)| asset_sources.contains(source));
+        for source in asset_sources {
+            if !sources.iter().any(|(known, _)| known == source) {
+                sources.push((source.clone(), modified_time(source)));
+            }
+        }
+    }
+    (sources, result)
+}
+
+// None if the file is gone
+fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
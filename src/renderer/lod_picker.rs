@@ -0,0 +1,75 @@
+use crate::assets::lod::LodView;
+use crate::gui_scene_node;
+
+// LOD selection settings, and how many draws got each level in the last frame
+pub struct LodPicker {
+    // largest error on screen a level may have
+    pub threshold_pixels: f32,
+    // None picks by screen-space error
+    pub forced_level: Option<usize>,
+    // the meshlet path picks clusters of the hierarchy on the GPU instead of a level per draw,
+    // None if assets are loaded without one
+    pub cluster_hierarchy: Option<bool>,
+    // traditional draws per level, the full resolution one included
+    pub draw_counts: std::vec::Vec<usize>,
+    // meshlet draws of the cluster hierarchy, the others are at the level the traditional one got
+    pub hierarchy_draws: usize,
+}
+
+impl LodPicker {
+    // `level_count` counts the full resolution level
+    pub fn new(level_count: usize, cluster_hierarchy: bool) -> Self {
+        Self {
+            threshold_pixels: 1.0,
+            forced_level: None,
+            cluster_hierarchy: cluster_hierarchy.then_some(true),
+            draw_counts: vec![0; level_count],
+            hierarchy_draws: 0,
+        }
+    }
+
+    pub fn view(&self, projview: &glm::Mat4, viewport_height: f32) -> LodView {
+        LodView {
+            projview: *projview,
+            viewport_height,
+            threshold_pixels: self.threshold_pixels,
+            forced_level: self.forced_level,
+            cluster_hierarchy: self.cluster_hierarchy == Some(true),
+        }
+    }
+}
+
+impl gui_scene_node::GuiSceneNode for LodPicker {
+    fn update(&mut self, ui: &imgui::Ui) {
+        if ui
+            .tree_node_config("LOD")
+            .opened(false, imgui::Condition::Appearing)
+            .push()
+            .is_some()
+        {
+            ui.indent();
+            let mut level_names = vec!["Auto".to_string()];
+            level_names.extend((0..self.draw_counts.len()).map(|level| format!("LOD {}", level)));
+            let mut selected = self.forced_level.map_or(0, |level| level + 1);
+            if ui.combo_simple_string("Level", &mut selected, &level_names) {
+                self.forced_level = selected.checked_sub(1);
+            }
+            if self.forced_level.is_none() {
+                ui.slider("Max error (px)", 0.1, 16.0, &mut self.threshold_pixels);
+                if let Some(cluster_hierarchy) = &mut self.cluster_hierarchy {
+                    ui.checkbox("Cluster hierarchy", cluster_hierarchy);
+                    if *cluster_hierarchy {
+                        ui.text("Meshlet draws pick their clusters on the GPU");
+                    }
+                }
+            }
+            for (level, count) in self.draw_counts.iter().enumerate() {
+                ui.text(format!("LOD {}: {} draws", level, count));
+            }
+            if self.cluster_hierarchy.is_some() {
+                ui.text(format!("Hierarchy: {} meshlet draws", self.hierarchy_draws));
+            }
+            ui.unindent();
+        }
+    }
+}
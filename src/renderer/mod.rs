@@ -1,12 +1,17 @@
+mod animation_player;
+mod asset_loader;
 mod depth_map_render;
+mod lod_picker;
 mod meshlet_render;
 mod pass;
+mod scene_picker;
 mod scene_render;
 mod target_render_picker;
 
 use crate::{
     assets::{self, gltf_asset, MeshletAsset, TraditionalAsset},
-    camera::GPUCameraData,
+    camera::{self, GPUCameraData},
+    config,
     dir_light::{self, GPUDirLight},
     grid, gui,
     gui_scene_node::GuiSceneNode,
@@ -15,10 +20,10 @@ use crate::{
     vkutils::{self, vk_destroy::VkDestroy},
 };
 use ash::vk;
-use target_render_picker::TargetRender;
+pub use target_render_picker::TargetRender;
 
 struct Passes {
-    _shadow_map: pass::shadow_map::ShadowMapPass,
+    shadow_map: pass::shadow_map::ShadowMapPass,
     scene: pass::scene::SceneColorPass,
     scene_depth_map_display: pass::depth_map_display::DepthMapDisplayPass,
     shadow_map_display: pass::depth_map_display::DepthMapDisplayPass,
@@ -33,19 +38,46 @@ struct Submits {
     meshlet_render: meshlet_render::MeshletRender,
 }
 
+// What a scene of a loaded asset contributes besides its meshes
+struct SceneContents {
+    lights: std::vec::Vec<assets::lights::GPUPunctualLight>,
+    camera_presets: std::vec::Vec<camera::Preset>,
+}
+
 pub struct Renderer {
     pub camera_data_buffer: vkutils::buffer::Buffer,
     pub cull_camera_data_buffer: vkutils::buffer::Buffer,
 
     pub gui_scene_nodes: std::vec::Vec<std::rc::Rc<std::cell::RefCell<dyn GuiSceneNode>>>,
     _skybox_asset: TraditionalAsset,
-    _traditional_assets: std::vec::Vec<TraditionalAsset>,
-    _meshlet_assets: std::vec::Vec<MeshletAsset>,
+    skybox: std::rc::Rc<std::cell::RefCell<skybox::Skybox>>,
+    dir_light: std::rc::Rc<std::cell::RefCell<dir_light::DirLight>>,
+    traditional_assets: std::vec::Vec<TraditionalAsset>,
+    meshlet_assets: std::vec::Vec<MeshletAsset>,
+    // one per loaded asset, parallel to traditional_assets and meshlet_assets
+    animation_players:
+        std::vec::Vec<std::rc::Rc<std::cell::RefCell<animation_player::AnimationPlayer>>>,
+    scene_pickers: std::vec::Vec<std::rc::Rc<std::cell::RefCell<scene_picker::ScenePicker>>>,
+    // index into the config's assets of every loaded asset, in load order, broken ones are
+    // skipped
+    asset_indices: std::vec::Vec<usize>,
+    loader: std::rc::Rc<std::cell::RefCell<asset_loader::AssetLoader>>,
+    // lights and camera presets of the scene file, shown with every glTF scene
+    scene_file_contents: SceneContents,
+    // per loaded asset, one per glTF scene
+    scene_contents: std::vec::Vec<std::vec::Vec<SceneContents>>,
+    // the skybox's, then one per loaded asset
+    textures: std::vec::Vec<assets::Textures>,
+    // KHR_lights_punctual lights of the current scene of every loaded asset
+    punctual_lights: assets::lights::PunctualLights,
+    // glTF cameras of the current scene of every loaded asset
+    pub camera_presets: std::vec::Vec<camera::Preset>,
     passes: Passes,
     submits: Submits,
 
-    _grid: grid::Grid,
+    grid: grid::Grid,
     picker: std::rc::Rc<std::cell::RefCell<target_render_picker::TargetRenderPicker>>,
+    lod_picker: std::rc::Rc<std::cell::RefCell<lod_picker::LodPicker>>,
     common_sampler: vkutils::sampler::Sampler,
 }
 
@@ -58,7 +90,7 @@ impl std::ops::Drop for Renderer {
 }
 
 impl Renderer {
-    pub fn new(ctx: &mut vkutils::context::VulkanContext) -> Self {
+    pub fn new(ctx: &mut vkutils::context::VulkanContext, config: &config::Config) -> Self {
         let camera_data_buffer = ctx.create_bar_buffer(
             size_of::<GPUCameraData>(),
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
@@ -68,37 +100,79 @@ impl Renderer {
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
         );
 
-        let t1 = std::time::Instant::now();
-        let cube_asset_data = gltf_asset::GltfAssetData::new("assets/cube.gltf");
-        let cube_asset = TraditionalAsset::from_gltf(&ctx, &cube_asset_data);
-
-        let asset_path = std::str::from_utf8(
-            // b"/home/starielora/dev/repos/Vulkan-Assets/models/chinesedragon.gltf",
-            // b"/home/starielora/dev/repos/glTF-Sample-Assets/Models/Sponza/glTF/Sponza.gltf",
-            // b"/home/starielora/dev/repos/RTXDI-Assets/bistro/bistro.gltf",
-            b"/home/starielora/dev/repos/Vulkan-Assets/models/vulkanscenemodels.gltf",
-        )
-        .unwrap();
-
-        let asset_data = gltf_asset::GltfAssetData::new(asset_path);
-        let meshlet_asset = MeshletAsset::from_gltf(&ctx, &asset_data);
-        let traditional_asset = TraditionalAsset::from_gltf(&ctx, &asset_data);
-        println!("Load time: {:?}", t1.elapsed());
-
-        let mut traditional_assets = vec![];
-        traditional_assets.push(traditional_asset);
+        // the skybox can't do without it, it ships with the repo
+        let cube_asset_data = gltf_asset::GltfAssetData::new("assets/cube.gltf")
+            .expect("Failed to load assets/cube.gltf");
+        let cube_textures = assets::Textures::from_gltf(ctx, &cube_asset_data, 0..0)
+            .expect("Failed to upload the textures of assets/cube.gltf");
+        let cube_asset = TraditionalAsset::from_gltf(
+            ctx,
+            &cube_asset_data,
+            &cube_textures,
+            &glm::Mat4::identity(),
+        );
 
-        let mut meshlet_assets = vec![];
-        meshlet_assets.push(meshlet_asset);
+        if config.assets.is_empty() {
+            eprintln!("No assets given, see --help");
+        }
 
+        // assets are drawn as the loader finishes them, see update_loading
+        // scans and sculpts exported without normals read better smooth than spec-mandated flat
+        let load_options = gltf_asset::LoadOptions {
+            normals: assets::normals::NormalGeneration::Smooth,
+            optimize: config.optimize_meshes,
+            lods: config
+                .lods
+                .iter()
+                .map(|lod| assets::lod::LodLevel {
+                    ratio: lod.ratio,
+                    max_error: lod.max_error,
+                })
+                .collect(),
+            cluster_lod: config.cluster_lod,
+            cache_dir: assets::meshlet_cache::default_dir(),
+        };
+        let lod_picker = std::rc::Rc::new(std::cell::RefCell::new(lod_picker::LodPicker::new(
+            load_options.lods.len() + 1,
+            load_options.cluster_lod,
+        )));
+        let loader = std::rc::Rc::new(std::cell::RefCell::new(asset_loader::AssetLoader::new(
+            &config.assets,
+            load_options,
+            ctx.uploaded_bytes.get(),
+        )));
+        let traditional_assets = vec![];
+        let meshlet_assets = vec![];
+        let scene_contents = vec![];
+
+        let dir_light_config = &config.directional_light;
         let dir_light = dir_light::DirLight::new(
             GPUDirLight {
-                dir: glm::make_vec4(&[1.0, -0.5, 0.5, 0.0]),
-                color: glm::make_vec4(&[1.0, 1.0, 1.0, 0.0]),
+                dir: glm::Vec4::zeros(),
+                color: glm::vec4(
+                    dir_light_config.color[0],
+                    dir_light_config.color[1],
+                    dir_light_config.color[2],
+                    0.0,
+                ),
+            },
+            dir_light::GuiData {
+                azimuth_deg: [dir_light_config.azimuth],
+                inclination_deg: [dir_light_config.inclination],
+                distance: [dir_light_config.distance],
+                xz_target: dir_light_config.xz_target,
             },
-            &ctx,
+            ctx,
         );
 
+        let scene_file_contents = SceneContents {
+            lights: config.lights.iter().map(scene_file_light).collect(),
+            camera_presets: config.cameras.iter().map(scene_file_camera).collect(),
+        };
+        let (lights, camera_presets) =
+            current_scene_contents(&scene_file_contents, &scene_contents, &traditional_assets);
+        let punctual_lights = assets::lights::PunctualLights::new(ctx, &lights);
+
         let common_sampler = vkutils::sampler::Sampler::new(ctx.device.clone());
 
         let ui_pass = pass::ui::UiPass::new(ctx);
@@ -141,12 +215,13 @@ impl Renderer {
                 assets::mesh::Primitives::Meshlets(_) => unreachable!(),
             };
 
-        let skybox = skybox::Skybox::new(
-            &ctx,
+        let skybox = std::rc::Rc::new(std::cell::RefCell::new(skybox::Skybox::new(
+            ctx,
             skybox_vertex_buffer_handle,
             skybox_index_buffer_handle,
             skybox_indices_count,
-        );
+        )));
+        skybox.borrow_mut().select(config.skybox);
         let grid = grid::Grid::new(
             &ctx.device,
             &ctx.swapchain.extent,
@@ -158,8 +233,12 @@ impl Renderer {
 
         let scene_pass = pass::scene::SceneColorPass::new(
             ctx,
-            &[&skybox as &dyn OverlayDrawable],
-            &[&grid as &dyn OverlayDrawable],
+            &pass::Draws {
+                assets: traditional_assets.as_slice(),
+                lights: (punctual_lights.device_address(), punctual_lights.count),
+                pre_overlays: &[&*skybox.borrow() as &dyn OverlayDrawable],
+                post_overlays: &[&grid as &dyn OverlayDrawable],
+            },
             camera_data_buffer.device_address.unwrap(),
             dir_light.buffer_device_address,
             dir_light.camera_buffer.device_address.unwrap(),
@@ -168,7 +247,6 @@ impl Renderer {
                 shadow_map_pass.output_depth_image.view,
             ),
             common_sampler.handle,
-            traditional_assets.as_slice(),
         );
 
         let scene_render = scene_render::ColorSceneRender::new(
@@ -200,26 +278,32 @@ impl Renderer {
 
         let picker = std::rc::Rc::new(std::cell::RefCell::new(
             target_render_picker::TargetRenderPicker {
-                target_render: TargetRender::Meshlet,
+                target_render: config.target_render,
             },
         ));
 
         let meshlet_pass = pass::meshlet::MeshletPass::new(
             ctx,
-            meshlet_assets.as_slice(),
+            &pass::Draws {
+                assets: meshlet_assets.as_slice(),
+                lights: (punctual_lights.device_address(), punctual_lights.count),
+                pre_overlays: &[&*skybox.borrow() as &dyn OverlayDrawable],
+                post_overlays: &[&grid as &dyn OverlayDrawable],
+            },
             camera_data_buffer.device_address.unwrap(),
             cull_camera_data_buffer.device_address.unwrap(),
-            &[&skybox as &dyn OverlayDrawable],
-            &[&grid as &dyn OverlayDrawable],
         );
 
+        let dir_light = std::rc::Rc::new(std::cell::RefCell::new(dir_light));
         let mut gui_scene_nodes: std::vec::Vec<std::rc::Rc<std::cell::RefCell<dyn GuiSceneNode>>> =
             vec![];
 
         {
             gui_scene_nodes.push(picker.clone());
-            gui_scene_nodes.push(std::rc::Rc::new(std::cell::RefCell::new(dir_light)));
-            gui_scene_nodes.push(std::rc::Rc::new(std::cell::RefCell::new(skybox)));
+            gui_scene_nodes.push(dir_light.clone());
+            gui_scene_nodes.push(skybox.clone());
+            gui_scene_nodes.push(loader.clone());
+            gui_scene_nodes.push(lod_picker.clone());
         }
         let meshlet_render = meshlet_render::MeshletRender::new(
             ctx,
@@ -231,10 +315,21 @@ impl Renderer {
             camera_data_buffer,
             cull_camera_data_buffer,
             _skybox_asset: cube_asset,
-            _traditional_assets: traditional_assets,
-            _meshlet_assets: meshlet_assets,
+            skybox,
+            dir_light,
+            traditional_assets,
+            meshlet_assets,
+            animation_players: vec![],
+            scene_pickers: vec![],
+            asset_indices: vec![],
+            loader,
+            scene_file_contents,
+            scene_contents,
+            textures: vec![cube_textures],
+            punctual_lights,
+            camera_presets,
             passes: Passes {
-                _shadow_map: shadow_map_pass,
+                shadow_map: shadow_map_pass,
                 scene: scene_pass,
                 shadow_map_display: shadow_map_display_pass,
                 ui: ui_pass,
@@ -247,13 +342,266 @@ impl Renderer {
                 scene_depth_render,
                 meshlet_render,
             },
-            _grid: grid,
+            grid,
             picker,
+            lod_picker,
             gui_scene_nodes,
             common_sampler,
         }
     }
 
+    // per-frame CPU side of animation, re-poses assets whose player moved or weights were edited
+    pub fn update_animations(&mut self, delta: std::time::Duration) {
+        let assets = std::iter::zip(&mut self.traditional_assets, &mut self.meshlet_assets);
+        for (player, (traditional_asset, meshlet_asset)) in
+            std::iter::zip(&self.animation_players, assets)
+        {
+            let mut player = player.borrow_mut();
+            if let Some(pose) = player.advance(delta) {
+                traditional_asset.pose(&pose);
+                meshlet_asset.pose(&pose);
+            }
+        }
+    }
+
+    // Picks every drawn instance's LOD for the view camera, or the level forced in the GUI. Runs
+    // after update_animations, nodes may have moved. Nothing may be in flight.
+    pub fn update_lods(&mut self, projview: &glm::Mat4, viewport_height: f32) {
+        let mut lod_picker = self.lod_picker.borrow_mut();
+        let view = lod_picker.view(projview, viewport_height);
+        lod_picker.draw_counts.fill(0);
+        lod_picker.hierarchy_draws = 0;
+        // both paths draw the same instances, the traditional one always at a level
+        for (traditional_asset, meshlet_asset) in
+            std::iter::zip(&mut self.traditional_assets, &mut self.meshlet_assets)
+        {
+            traditional_asset.select_lods(&view, &mut lod_picker.draw_counts);
+            lod_picker.hierarchy_draws += meshlet_asset.select_lods(&view);
+        }
+    }
+
+    // Switches assets to the scenes picked in the GUI. Command buffers are prerecorded, so every
+    // pass drawing assets is re-recorded, nothing may be in flight.
+    pub fn update_scenes(&mut self, ctx: &vkutils::context::VulkanContext) {
+        let mut changed = false;
+        let assets = std::iter::zip(&mut self.traditional_assets, &mut self.meshlet_assets);
+        for (picker, (traditional_asset, meshlet_asset)) in
+            std::iter::zip(&self.scene_pickers, assets)
+        {
+            let mut picker = picker.borrow_mut();
+            if std::mem::take(&mut picker.changed) {
+                traditional_asset.current_scene = picker.selected;
+                meshlet_asset.current_scene = picker.selected;
+                changed = true;
+            }
+        }
+        if changed {
+            self.record_asset_passes(ctx);
+        }
+    }
+
+    // Uploads at most one asset the loader finished, so a frame never stalls on more than one
+    // upload, and re-records the passes to draw it. A reloaded asset replaces its previous
+    // version, keeping the picked scene. Nothing may be in flight. Returns the GUI nodes of a
+    // newly loaded asset.
+    pub fn update_loading(
+        &mut self,
+        ctx: &vkutils::context::VulkanContext,
+    ) -> std::vec::Vec<std::rc::Rc<std::cell::RefCell<dyn GuiSceneNode>>> {
+        let mut gui_scene_nodes: std::vec::Vec<std::rc::Rc<std::cell::RefCell<dyn GuiSceneNode>>> =
+            vec![];
+        let next = self.loader.borrow_mut().next();
+        if let Some((asset_index, asset_config, loaded)) = next {
+            // where the previous version of a reloaded asset lives
+            let slot = self
+                .asset_indices
+                .iter()
+                .position(|index| *index == asset_index);
+            let asset_data = loaded.asset_data;
+            let asset_path = &asset_config.path;
+            let transform = asset_config.transform();
+            // a reload takes over the texture slots of the previous version, nothing is in flight.
+            // textures[0] belongs to the skybox.
+            let replaced_slots = slot.map_or(0..0, |slot| self.textures[slot + 1].slot_range());
+            let asset_textures = assets::Textures::from_gltf(ctx, &asset_data, replaced_slots);
+            let asset_textures = match asset_textures {
+                Ok(textures) => textures,
+                Err(err) => {
+                    let mut loader = self.loader.borrow_mut();
+                    loader.report_error(asset_index, slot.is_some(), &err);
+                    loader.update(ctx.uploaded_bytes.get());
+                    return gui_scene_nodes;
+                }
+            };
+            let current_scene = slot
+                .map(|slot| self.scene_pickers[slot].borrow().selected)
+                .or(asset_config.scene)
+                .filter(|scene| *scene < asset_data.scenes.len())
+                .or(asset_data.default_scene)
+                .unwrap_or(0);
+            let mut meshlet_asset = MeshletAsset::from_gltf(
+                ctx,
+                &asset_data,
+                loaded.meshlets,
+                &asset_textures,
+                &transform,
+            );
+            meshlet_asset.current_scene = current_scene;
+            let mut traditional_asset =
+                TraditionalAsset::from_gltf(ctx, &asset_data, &asset_textures, &transform);
+            traditional_asset.current_scene = current_scene;
+            let contents = asset_data
+                .scenes
+                .iter()
+                .map(|scene| SceneContents {
+                    lights: assets::lights::scene_lights(
+                        &asset_data.nodes,
+                        &asset_data.lights,
+                        scene,
+                        &transform,
+                    ),
+                    camera_presets: assets::cameras::scene_presets(
+                        &asset_data.nodes,
+                        &asset_data.cameras,
+                        scene,
+                        &transform,
+                    ),
+                })
+                .collect();
+            let file_name = asset_path
+                .file_name()
+                .unwrap_or(asset_path.as_os_str())
+                .to_string_lossy();
+            // the same file may be placed several times
+            let label = if self.loader.borrow().asset_count() > 1 {
+                format!("{}: {}", asset_index, file_name)
+            } else {
+                file_name.to_string()
+            };
+            let scene_names = asset_data
+                .scenes
+                .iter()
+                .enumerate()
+                .map(|(scene_index, scene)| {
+                    let name = scene
+                        ._name
+                        .clone()
+                        .unwrap_or_else(|| format!("Scene {}", scene_index));
+                    if asset_data.default_scene == Some(scene_index) {
+                        format!("{} (default)##{}", name, scene_index)
+                    } else {
+                        format!("{}##{}", name, scene_index)
+                    }
+                })
+                .collect();
+            let picker = scene_picker::ScenePicker::new(&label, scene_names, current_scene);
+            let player = animation_player::AnimationPlayer::new(
+                &label,
+                asset_data.animations,
+                &asset_data.nodes,
+            );
+
+            match slot {
+                Some(slot) => {
+                    // the previous frame finished, the old buffers and images can go right away
+                    self.meshlet_assets[slot] = meshlet_asset;
+                    self.traditional_assets[slot] = traditional_asset;
+                    // its slots went to asset_textures already
+                    self.textures[slot + 1] = asset_textures;
+                    self.scene_contents[slot] = contents;
+                    // the GUI holds on to the nodes, only their contents are replaced
+                    *self.scene_pickers[slot].borrow_mut() = picker;
+                    *self.animation_players[slot].borrow_mut() = player;
+                }
+                None => {
+                    self.meshlet_assets.push(meshlet_asset);
+                    self.traditional_assets.push(traditional_asset);
+                    self.asset_indices.push(asset_index);
+                    self.textures.push(asset_textures);
+                    self.scene_contents.push(contents);
+                    let picker = std::rc::Rc::new(std::cell::RefCell::new(picker));
+                    gui_scene_nodes.push(picker.clone());
+                    self.scene_pickers.push(picker);
+                    let player = std::rc::Rc::new(std::cell::RefCell::new(player));
+                    gui_scene_nodes.push(player.clone());
+                    self.animation_players.push(player);
+                }
+            }
+            self.loader.borrow_mut().meshlet_vertex_memory = self
+                .meshlet_assets
+                .iter()
+                .map(|asset| asset.vertex_memory)
+                .fold(Default::default(), std::ops::Add::add);
+
+            self.record_asset_passes(ctx);
+        }
+        self.loader.borrow_mut().update(ctx.uploaded_bytes.get());
+        gui_scene_nodes
+    }
+
+    // Gathers the lights and camera presets of the current scenes and re-records every pass
+    // drawing assets, nothing may be in flight
+    fn record_asset_passes(&mut self, ctx: &vkutils::context::VulkanContext) {
+        let (lights, camera_presets) = current_scene_contents(
+            &self.scene_file_contents,
+            &self.scene_contents,
+            &self.traditional_assets,
+        );
+        self.punctual_lights = assets::lights::PunctualLights::new(ctx, &lights);
+        self.camera_presets = camera_presets;
+
+        let lights = (
+            self.punctual_lights.device_address(),
+            self.punctual_lights.count,
+        );
+        let skybox = self.skybox.borrow();
+        let pre_overlays = [&*skybox as &dyn OverlayDrawable];
+        let post_overlays = [&self.grid as &dyn OverlayDrawable];
+        self.passes
+            .shadow_map
+            .record(ctx, self.traditional_assets.as_slice());
+        self.passes.scene.record(
+            ctx,
+            &pass::Draws {
+                assets: self.traditional_assets.as_slice(),
+                lights,
+                pre_overlays: &pre_overlays,
+                post_overlays: &post_overlays,
+            },
+        );
+        self.passes.meshlet.record(
+            ctx,
+            &pass::Draws {
+                assets: self.meshlet_assets.as_slice(),
+                lights,
+                pre_overlays: &pre_overlays,
+                post_overlays: &post_overlays,
+            },
+        );
+    }
+
+    // Writes what the GUI changes back into `config`, to save the scene
+    pub fn store_settings(&self, config: &mut config::Config) {
+        config.target_render = self.picker.borrow().target_render;
+        config.skybox = self.skybox.borrow().selected();
+
+        let dir_light = self.dir_light.borrow();
+        let gui_data = dir_light.gui_data();
+        let color = dir_light.gpu_data.color;
+        config.directional_light = config::DirLightConfig {
+            azimuth: gui_data.azimuth_deg[0],
+            inclination: gui_data.inclination_deg[0],
+            distance: gui_data.distance[0],
+            xz_target: gui_data.xz_target,
+            color: [color.x, color.y, color.z],
+        };
+
+        for (asset_index, picker) in std::iter::zip(&self.asset_indices, &self.scene_pickers) {
+            let picker = picker.borrow();
+            config.assets[*asset_index].scene = picker.has_choices().then_some(picker.selected);
+        }
+    }
+
     pub fn record_imgui_pass(
         &self,
         image_index: u32,
@@ -286,7 +634,7 @@ impl Renderer {
 
         self.passes
             .ui
-            .record(image_index, &ctx, src_image, swapchain_image, gui)
+            .record(image_index, ctx, src_image, swapchain_image, gui)
     }
 
     pub fn submit(
@@ -334,25 +682,25 @@ impl Renderer {
     ) {
         match self.picker.borrow().target_render {
             TargetRender::Scene => (
-                self.passes._shadow_map.get_pass_total_time(true),
+                self.passes.shadow_map.get_pass_total_time(true),
                 self.passes.scene.get_pass_total_time(true),
                 self.passes.meshlet.get_pass_total_time(false),
                 self.passes.ui.get_pass_total_time(true),
             ),
             TargetRender::SceneDepth => (
-                self.passes._shadow_map.get_pass_total_time(false),
+                self.passes.shadow_map.get_pass_total_time(false),
                 self.passes.scene.get_pass_total_time(false),
                 self.passes.meshlet.get_pass_total_time(false),
                 self.passes.ui.get_pass_total_time(true),
             ),
             TargetRender::ShadowMap => (
-                self.passes._shadow_map.get_pass_total_time(true),
+                self.passes.shadow_map.get_pass_total_time(true),
                 self.passes.scene.get_pass_total_time(false),
                 self.passes.meshlet.get_pass_total_time(false),
                 self.passes.ui.get_pass_total_time(true),
             ),
             TargetRender::Meshlet => (
-                self.passes._shadow_map.get_pass_total_time(false),
+                self.passes.shadow_map.get_pass_total_time(false),
                 self.passes.scene.get_pass_total_time(false),
                 self.passes.meshlet.get_pass_total_time(true),
                 self.passes.ui.get_pass_total_time(true),
@@ -360,3 +708,63 @@ impl Renderer {
         }
     }
 }
+
+// Lights and camera presets of the scene file and the scene every asset currently draws
+fn current_scene_contents(
+    scene_file_contents: &SceneContents,
+    scene_contents: &[std::vec::Vec<SceneContents>],
+    assets: &[TraditionalAsset],
+) -> (
+    std::vec::Vec<assets::lights::GPUPunctualLight>,
+    std::vec::Vec<camera::Preset>,
+) {
+    let mut lights = scene_file_contents.lights.clone();
+    let mut camera_presets = scene_file_contents.camera_presets.clone();
+    for (contents, asset) in std::iter::zip(scene_contents, assets) {
+        if let Some(contents) = contents.get(asset.current_scene) {
+            lights.extend_from_slice(&contents.lights);
+            camera_presets.extend(contents.camera_presets.iter().cloned());
+        }
+    }
+    (lights, camera_presets)
+}
+
+fn scene_file_light(light: &config::LightConfig) -> assets::lights::GPUPunctualLight {
+    let kind = match light.light_type {
+        config::LightType::Directional => assets::lights::LightKind::Directional,
+        config::LightType::Point => assets::lights::LightKind::Point,
+        config::LightType::Spot => assets::lights::LightKind::Spot {
+            inner_cone_angle: light.inner_cone_angle.to_radians(),
+            outer_cone_angle: light.outer_cone_angle.to_radians(),
+        },
+    };
+    assets::lights::Light {
+        _name: None,
+        kind,
+        color: glm::make_vec3(&light.color),
+        intensity: light.intensity,
+        range: light.range,
+    }
+    .gpu_light(
+        &glm::make_vec3(&light.position),
+        &glm::make_vec3(&light.direction).normalize(),
+    )
+}
+
+fn scene_file_camera(camera: &config::CameraConfig) -> camera::Preset {
+    camera::Preset {
+        name: camera.name.clone(),
+        position: glm::make_vec3(&camera.position),
+        direction: glm::make_vec3(&camera.direction).normalize(),
+        // the aspect ratio is replaced by the viewport's when the preset is applied
+        projection: camera::projection::Projection::Perspective(
+            camera::projection::perspective::Properties::new(
+                1.0,
+                1.0,
+                camera.fov,
+                camera.near,
+                camera.far,
+            ),
+        ),
+    }
+}
@@ -1,5 +1,5 @@
+use super::Draws;
 use crate::assets::MeshletAsset;
-use crate::overlay_drawable::OverlayDrawable;
 use crate::vkutils::push_constants::{GPUPushConstantsMeshlet, GPUPushConstantsTraditional};
 use crate::vkutils::{self, vk_destroy::VkDestroy};
 use ash::vk;
@@ -11,6 +11,8 @@ pub struct MeshletPass {
 
     timestamp_query: vkutils::timestamp_query::TimestampQuery,
 
+    camera_data: vk::DeviceAddress,
+    cull_camera_data: vk::DeviceAddress,
     pipeline: vk::Pipeline,
     device: ash::Device,
 }
@@ -18,11 +20,9 @@ pub struct MeshletPass {
 impl MeshletPass {
     pub fn new(
         ctx: &mut vkutils::context::VulkanContext,
-        assets: &[MeshletAsset],
+        draws: &Draws<MeshletAsset>,
         camera_data: vk::DeviceAddress,
         cull_camera_data: vk::DeviceAddress,
-        pre_overlays: &[&dyn OverlayDrawable],
-        post_overlays: &[&dyn OverlayDrawable],
     ) -> Self {
         let command_buffers = ctx.graphics_command_pool.allocate_command_buffers(
             vk::CommandBufferLevel::PRIMARY,
@@ -63,33 +63,38 @@ impl MeshletPass {
 
         let timestamp_query = vkutils::timestamp_query::TimestampQuery::new(&ctx, 2);
 
-        for command_buffer in &command_buffers {
-            record(
-                &ctx.device,
-                &ctx.mesh_shader_device,
-                *command_buffer,
-                (render_target.handle, render_target.view),
-                (depth_image.handle, depth_image.view),
-                extent,
-                pipeline,
-                assets,
-                camera_data,
-                cull_camera_data,
-                pipeline_layout,
-                ctx.bindless_descriptor_set.handle,
-                &timestamp_query,
-                pre_overlays,
-                post_overlays,
-            );
-        }
-
-        Self {
+        let pass = Self {
             command_buffers,
             render_target,
             depth_image,
             pipeline,
             timestamp_query,
+            camera_data,
+            cull_camera_data,
             device: ctx.device.clone(),
+        };
+        pass.record(ctx, draws);
+        pass
+    }
+
+    // (Re)records every command buffer, none of them may be pending
+    pub fn record(&self, ctx: &vkutils::context::VulkanContext, draws: &Draws<MeshletAsset>) {
+        for command_buffer in &self.command_buffers {
+            record(
+                &ctx.device,
+                &ctx.mesh_shader_device,
+                *command_buffer,
+                (self.render_target.handle, self.render_target.view),
+                (self.depth_image.handle, self.depth_image.view),
+                ctx.swapchain.extent,
+                self.pipeline,
+                draws,
+                self.camera_data,
+                self.cull_camera_data,
+                ctx.bindless_descriptor_set.meshlet_pipeline_layout,
+                ctx.bindless_descriptor_set.handle,
+                &self.timestamp_query,
+            );
         }
     }
 
@@ -122,14 +127,12 @@ fn record(
     depth_image: (vk::Image, vk::ImageView),
     extent: vk::Extent2D,
     pipeline: vk::Pipeline,
-    assets: &[MeshletAsset],
+    draws: &Draws<MeshletAsset>,
     camera_buffer_address: vk::DeviceAddress,
     cull_camera_buffer_address: vk::DeviceAddress,
     pipeline_layout: vk::PipelineLayout,
     descriptor_set: vk::DescriptorSet,
     timestamp_query: &vkutils::timestamp_query::TimestampQuery,
-    pre_overlays: &[&dyn OverlayDrawable],
-    post_overlays: &[&dyn OverlayDrawable],
 ) {
     let begin_info = vk::CommandBufferBeginInfo {
         ..Default::default()
@@ -157,7 +160,7 @@ fn record(
     let mut trad_push_constants = GPUPushConstantsTraditional::default();
     trad_push_constants.camera = camera_buffer_address;
 
-    for overlay in pre_overlays {
+    for overlay in draws.pre_overlays {
         if overlay.enabled() {
             overlay.record(command_buffer, &mut trad_push_constants);
         }
@@ -183,10 +186,12 @@ fn record(
     let mut push_constants = GPUPushConstantsMeshlet::default();
     push_constants.camera = camera_buffer_address;
     push_constants.cull_camera = cull_camera_buffer_address;
+    push_constants.lights = draws.lights.0;
+    push_constants.light_count = draws.lights.1;
 
-    for asset in assets {
+    for asset in draws.assets {
         asset.draw_scene(
-            asset.default_scene.unwrap_or(0),
+            asset.current_scene,
             device,
             mesh_shader_device,
             command_buffer,
@@ -195,7 +200,7 @@ fn record(
         );
     }
 
-    for overlay in post_overlays {
+    for overlay in draws.post_overlays {
         if overlay.enabled() {
             overlay.record(command_buffer, &mut trad_push_constants);
         }
@@ -3,3 +3,15 @@ pub(super) mod meshlet;
 pub(super) mod scene;
 pub(super) mod shadow_map;
 pub(super) mod ui;
+
+use crate::overlay_drawable::OverlayDrawable;
+use ash::vk;
+
+// What a color pass records, on creation and every time the assets or lights change
+pub struct Draws<'a, A> {
+    pub assets: &'a [A],
+    // punctual lights buffer and light count
+    pub lights: (vk::DeviceAddress, u32),
+    pub pre_overlays: &'a [&'a dyn OverlayDrawable],
+    pub post_overlays: &'a [&'a dyn OverlayDrawable],
+}
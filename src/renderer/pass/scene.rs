@@ -1,12 +1,11 @@
+use crate::assets::vertex::Vertex;
 use crate::assets::TraditionalAsset;
 use ash::vk;
 
-use crate::{
-    overlay_drawable::OverlayDrawable,
-    vkutils::{
-        self, descriptor_set::bindless, push_constants::GPUPushConstantsTraditional,
-        vk_destroy::VkDestroy,
-    },
+use super::Draws;
+use crate::vkutils::{
+    self, descriptor_set::bindless, push_constants::GPUPushConstantsTraditional,
+    vk_destroy::VkDestroy,
 };
 
 pub struct SceneColorPass {
@@ -17,6 +16,11 @@ pub struct SceneColorPass {
 
     timestamp_query: vkutils::timestamp_query::TimestampQuery,
 
+    camera_data_buffer_address: vk::DeviceAddress,
+    dir_light_data_buffer_address: vk::DeviceAddress,
+    dir_light_camera_buffer_address: vk::DeviceAddress,
+    shadow_map: (vk::Image, vk::ImageView),
+    depth_sampler_index: u32,
     pipeline: vk::Pipeline,
     device: ash::Device,
 }
@@ -34,14 +38,12 @@ impl std::ops::Drop for SceneColorPass {
 impl SceneColorPass {
     pub fn new(
         ctx: &mut vkutils::context::VulkanContext,
-        pre_overlays: &[&dyn OverlayDrawable],
-        post_overlays: &[&dyn OverlayDrawable],
+        draws: &Draws<TraditionalAsset>,
         camera_data_buffer_address: vk::DeviceAddress,
         dir_light_data_buffer_address: vk::DeviceAddress,
         dir_light_camera_buffer_address: vk::DeviceAddress,
         shadow_map: (vk::Image, vk::ImageView),
         sampler: vk::Sampler,
-        assets: &[TraditionalAsset],
     ) -> Self {
         let command_buffers = ctx.graphics_command_pool.allocate_command_buffers(
             vk::CommandBufferLevel::PRIMARY,
@@ -89,35 +91,47 @@ impl SceneColorPass {
 
         let timestamp_query = vkutils::timestamp_query::TimestampQuery::new(&ctx, 2);
 
-        for command_buffer in &command_buffers {
-            record(
-                &ctx.device,
-                *command_buffer,
-                &ctx.bindless_descriptor_set,
-                (render_target.handle, render_target.view),
-                (depth_image.handle, depth_image.view),
-                (shadow_map.0, shadow_map.1),
-                extent,
-                pre_overlays,
-                post_overlays,
-                pipeline,
-                pipeline_layout,
-                camera_data_buffer_address,
-                dir_light_data_buffer_address,
-                dir_light_camera_buffer_address,
-                resource_id,
-                assets,
-                &timestamp_query,
-            );
-        }
-
-        Self {
+        let pass = Self {
             command_buffers,
             render_target,
             depth_image,
             pipeline,
             timestamp_query,
+            camera_data_buffer_address,
+            dir_light_data_buffer_address,
+            dir_light_camera_buffer_address,
+            shadow_map,
+            depth_sampler_index: resource_id,
             device: ctx.device.clone(),
+        };
+        pass.record(ctx, draws);
+        pass
+    }
+
+    // (Re)records every command buffer, none of them may be pending
+    pub fn record(
+        &self,
+        ctx: &vkutils::context::VulkanContext,
+        draws: &Draws<TraditionalAsset>,
+    ) {
+        for command_buffer in &self.command_buffers {
+            record(
+                &ctx.device,
+                *command_buffer,
+                &ctx.bindless_descriptor_set,
+                (self.render_target.handle, self.render_target.view),
+                (self.depth_image.handle, self.depth_image.view),
+                self.shadow_map,
+                ctx.swapchain.extent,
+                draws,
+                self.pipeline,
+                ctx.bindless_descriptor_set.traditional_pipeline_layout,
+                self.camera_data_buffer_address,
+                self.dir_light_data_buffer_address,
+                self.dir_light_camera_buffer_address,
+                self.depth_sampler_index,
+                &self.timestamp_query,
+            );
         }
     }
 
@@ -140,15 +154,13 @@ fn record(
     depth_image: (vk::Image, vk::ImageView),
     shadow_map_image: (vk::Image, vk::ImageView),
     extent: vk::Extent2D,
-    pre_overlays: &[&dyn OverlayDrawable],
-    post_overlays: &[&dyn OverlayDrawable],
+    draws: &Draws<TraditionalAsset>,
     pipeline: vk::Pipeline,
     pipeline_layout: vk::PipelineLayout,
     camera_buffer_address: vk::DeviceAddress,
     dir_light_buffer_address: vk::DeviceAddress,
     dir_light_camera_buffer_address: vk::DeviceAddress,
     depth_sampler_index: u32,
-    assets: &[TraditionalAsset],
     timestamp_query: &vkutils::timestamp_query::TimestampQuery,
 ) {
     let begin_info = vk::CommandBufferBeginInfo {
@@ -185,9 +197,11 @@ fn record(
     push_constants.camera = camera_buffer_address;
     push_constants.dir_light = dir_light_buffer_address;
     push_constants.dir_light_camera = dir_light_camera_buffer_address;
+    push_constants.lights = draws.lights.0;
+    push_constants.light_count = draws.lights.1;
     push_constants.depth_sampler_index = depth_sampler_index;
 
-    for overlay in pre_overlays {
+    for overlay in draws.pre_overlays {
         if overlay.enabled() {
             overlay.record(command_buffer, &mut push_constants);
         }
@@ -197,9 +211,9 @@ fn record(
         device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
     }
 
-    for asset in assets {
+    for asset in draws.assets {
         asset.draw_scene(
-            asset.default_scene.unwrap_or(0),
+            asset.current_scene,
             device,
             command_buffer,
             pipeline_layout,
@@ -207,7 +221,7 @@ fn record(
         );
     }
 
-    for overlay in post_overlays {
+    for overlay in draws.post_overlays {
         if overlay.enabled() {
             overlay.record(command_buffer, &mut push_constants);
         }
@@ -377,28 +391,8 @@ fn create_pipeline(
         },
     ];
 
-    let vertex_binding_desciptions = [vk::VertexInputBindingDescription::default()
-        .binding(0)
-        .stride((std::mem::size_of::<f32>() * 8) as u32)
-        .input_rate(vk::VertexInputRate::VERTEX)];
-
-    let vertex_attribute_descriptions = [
-        vk::VertexInputAttributeDescription::default()
-            .location(0)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(0),
-        vk::VertexInputAttributeDescription::default()
-            .location(1)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 3) as u32),
-        vk::VertexInputAttributeDescription::default()
-            .location(2)
-            .binding(0)
-            .format(vk::Format::R32G32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 6) as u32),
-    ];
+    let vertex_binding_desciptions = [Vertex::binding_description(0)];
+    let vertex_attribute_descriptions = Vertex::attribute_descriptions(0);
 
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
         .vertex_binding_descriptions(&vertex_binding_desciptions)
@@ -1,3 +1,4 @@
+use crate::assets::vertex::Vertex;
 use crate::assets::TraditionalAsset;
 use crate::vkutils::descriptor_set::bindless;
 use crate::vkutils::push_constants::GPUPushConstantsTraditional;
@@ -11,6 +12,7 @@ pub struct ShadowMapPass {
 
     timestamp_query: vkutils::timestamp_query::TimestampQuery,
 
+    light_pov_camera_buffer_device_address: vk::DeviceAddress,
     pipeline: vk::Pipeline,
     device: ash::Device,
 }
@@ -51,28 +53,34 @@ impl ShadowMapPass {
 
         let timestamp_query = vkutils::timestamp_query::TimestampQuery::new(&ctx, 2);
 
-        for command_buffer in &command_buffers {
+        let pass = Self {
+            command_buffers,
+            output_depth_image: depth_image,
+            pipeline,
+            timestamp_query,
+            light_pov_camera_buffer_device_address,
+            device: ctx.device.clone(),
+        };
+        pass.record(ctx, assets);
+        pass
+    }
+
+    // (Re)records every command buffer, none of them may be pending
+    pub fn record(&self, ctx: &vkutils::context::VulkanContext, assets: &[TraditionalAsset]) {
+        for command_buffer in &self.command_buffers {
             record(
                 &ctx.device,
                 *command_buffer,
                 &ctx.bindless_descriptor_set,
-                pipeline,
-                pipeline_layout,
-                extent,
-                (depth_image.handle, depth_image.view),
-                light_pov_camera_buffer_device_address,
+                self.pipeline,
+                ctx.bindless_descriptor_set.traditional_pipeline_layout,
+                ctx.swapchain.extent,
+                (self.output_depth_image.handle, self.output_depth_image.view),
+                self.light_pov_camera_buffer_device_address,
                 assets,
-                &timestamp_query,
+                &self.timestamp_query,
             );
         }
-
-        Self {
-            command_buffers,
-            output_depth_image: depth_image,
-            pipeline,
-            timestamp_query,
-            device: ctx.device.clone(),
-        }
     }
 
     pub fn get_pass_total_time(&mut self, refresh: bool) -> std::time::Duration {
@@ -160,7 +168,7 @@ fn record(
 
     for asset in assets {
         asset.draw_scene(
-            asset.default_scene.unwrap_or(0),
+            asset.current_scene,
             device,
             command_buffer,
             pipeline_layout,
@@ -202,28 +210,8 @@ fn create_pipeline(
         ..Default::default()
     }];
 
-    let vertex_binding_desciptions = [vk::VertexInputBindingDescription::default()
-        .binding(0)
-        .stride((std::mem::size_of::<f32>() * 8) as u32)
-        .input_rate(vk::VertexInputRate::VERTEX)];
-
-    let vertex_attribute_descriptions = [
-        vk::VertexInputAttributeDescription::default()
-            .location(0)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(0),
-        vk::VertexInputAttributeDescription::default()
-            .location(1)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 3) as u32),
-        vk::VertexInputAttributeDescription::default()
-            .location(2)
-            .binding(0)
-            .format(vk::Format::R32G32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 6) as u32),
-    ];
+    let vertex_binding_desciptions = [Vertex::binding_description(0)];
+    let vertex_attribute_descriptions = Vertex::attribute_descriptions(0);
 
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
         .vertex_binding_descriptions(&vertex_binding_desciptions)
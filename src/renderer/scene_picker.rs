@@ -0,0 +1,56 @@
+use crate::gui_scene_node;
+
+// Scene selection of one loaded asset, the renderer re-records its passes when it changes
+pub struct ScenePicker {
+    label: std::string::String,
+    scene_names: std::vec::Vec<std::string::String>,
+    pub selected: usize,
+    pub changed: bool,
+}
+
+impl ScenePicker {
+    pub fn new(
+        label: &str,
+        scene_names: std::vec::Vec<std::string::String>,
+        selected: usize,
+    ) -> Self {
+        Self {
+            label: label.to_string(),
+            scene_names,
+            selected,
+            changed: false,
+        }
+    }
+
+    pub fn has_choices(&self) -> bool {
+        self.scene_names.len() > 1
+    }
+}
+
+impl gui_scene_node::GuiSceneNode for ScenePicker {
+    fn update(&mut self, ui: &imgui::Ui) {
+        if !self.has_choices() {
+            return;
+        }
+        if ui
+            .tree_node_config(format!("Scene ({})", self.label))
+            .opened(true, imgui::Condition::Appearing)
+            .push()
+            .is_some()
+        {
+            ui.indent();
+            for (scene_index, name) in self.scene_names.iter().enumerate() {
+                if ui
+                    .selectable_config(name)
+                    .selected(scene_index == self.selected)
+                    .build()
+                    && scene_index != self.selected
+                {
+                    self.selected = scene_index;
+                    self.changed = true;
+                }
+            }
+            ui.unindent();
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::gui_scene_node;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum TargetRender {
     Scene,
     SceneDepth,
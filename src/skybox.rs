@@ -1,3 +1,4 @@
+use crate::assets::vertex::Vertex;
 use crate::gui_scene_node::GuiSceneNode;
 use crate::overlay_drawable::OverlayDrawable;
 use crate::vkutils;
@@ -6,6 +7,9 @@ use crate::vkutils::vk_destroy::VkDestroy;
 use ash::vk;
 use std::io::prelude::*;
 
+// Cube maps in the order they're loaded, the same names as the GUI and scene files use
+pub const NAMES: [&str; 2] = ["daylight", "learnopengl"];
+
 pub struct Skybox {
     device: ash::Device,
     pipeline_layout: vk::PipelineLayout,
@@ -63,28 +67,8 @@ fn create_graphics_pipeline(
         },
     ];
 
-    let vertex_binding_desciptions = [vk::VertexInputBindingDescription::default()
-        .binding(0)
-        .stride((std::mem::size_of::<f32>() * 8) as u32)
-        .input_rate(vk::VertexInputRate::VERTEX)];
-
-    let vertex_attribute_descriptions = [
-        vk::VertexInputAttributeDescription::default()
-            .location(0)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(0),
-        vk::VertexInputAttributeDescription::default()
-            .location(1)
-            .binding(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 3) as u32),
-        vk::VertexInputAttributeDescription::default()
-            .location(2)
-            .binding(0)
-            .format(vk::Format::R32G32_SFLOAT)
-            .offset((std::mem::size_of::<f32>() * 6) as u32),
-    ];
+    let vertex_binding_desciptions = [Vertex::binding_description(0)];
+    let vertex_attribute_descriptions = Vertex::attribute_descriptions(0);
 
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
         .vertex_binding_descriptions(&vertex_binding_desciptions)
@@ -489,6 +473,16 @@ impl Skybox {
         }
     }
 
+    // index into NAMES
+    pub fn selected(&self) -> usize {
+        self.current_resource_id as usize
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.current_resource_id = index as u32;
+        self.refresh_per_frame_buffer();
+    }
+
     fn refresh_per_frame_buffer(&self) {
         self.buffer.update_contents(&[self.current_resource_id]);
     }
@@ -570,13 +564,11 @@ impl GuiSceneNode for Skybox {
             .is_some()
         {
             ui.indent();
-            if ui.selectable("daylight") {
-                self.current_resource_id = 0;
-                refresh = true;
-            }
-            if ui.selectable("learnopengl") {
-                self.current_resource_id = 1;
-                refresh = true;
+            for (index, name) in NAMES.iter().enumerate() {
+                if ui.selectable(name) {
+                    self.current_resource_id = index as u32;
+                    refresh = true;
+                }
             }
             ui.unindent();
         }
@@ -22,6 +22,8 @@ pub struct VulkanContext {
     pub transient_transfer_command_pool: command_pool::CommandPool,
     pub transient_graphics_command_pool: command_pool::CommandPool,
     pub depth_format: vk::Format,
+    // staged into device local memory so far, for the loading progress
+    pub uploaded_bytes: std::cell::Cell<u64>,
 }
 
 impl VulkanContext {
@@ -95,6 +97,7 @@ impl VulkanContext {
             transient_transfer_command_pool,
             transient_graphics_command_pool,
             depth_format: vk::Format::D32_SFLOAT, // TODO query from device,
+            uploaded_bytes: std::cell::Cell::new(0),
         }
     }
 
@@ -214,6 +217,8 @@ impl VulkanContext {
 
         staging_buffer.update_contents(&data.as_slice());
         staging_buffer.unmap_memory();
+        self.uploaded_bytes
+            .set(self.uploaded_bytes.get() + buffer_size as u64);
 
         self.transient_transfer_command_pool
             .execute_short_lived_command_buffer(self.transfer_queue, |device, command_buffer| {
@@ -232,6 +237,105 @@ impl VulkanContext {
 
         device_buffer
     }
+
+    // uploads tightly packed pixels into a sampled, single mip 2D image
+    // TODO mip levels
+    pub fn upload_image2d(
+        &self,
+        data: &[u8],
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> image::Image {
+        let mut staging_buffer = self.create_buffer(
+            data.len(),
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        );
+        staging_buffer.update_contents(data);
+        staging_buffer.unmap_memory();
+        self.uploaded_bytes
+            .set(self.uploaded_bytes.get() + data.len() as u64);
+
+        let image = self.create_image(
+            format,
+            extent,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            vk::ImageAspectFlags::COLOR,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+
+        let subresource_range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .layer_count(1);
+
+        self.transient_graphics_command_pool
+            .transition_image_layout(
+                self.graphics_present_queue,
+                image.handle,
+                (
+                    vk::ImageLayout::UNDEFINED,
+                    vk::AccessFlags::NONE,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                ),
+                (
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::PipelineStageFlags::TRANSFER,
+                ),
+                subresource_range,
+            );
+
+        self.transient_transfer_command_pool
+            .execute_short_lived_command_buffer(self.transfer_queue, |device, command_buffer| {
+                let region = [vk::BufferImageCopy::default()
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .mip_level(0)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    )
+                    .image_extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })];
+                unsafe {
+                    device.cmd_copy_buffer_to_image(
+                        command_buffer,
+                        staging_buffer.handle,
+                        image.handle,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &region,
+                    );
+                }
+            });
+
+        self.transient_graphics_command_pool
+            .transition_image_layout(
+                self.graphics_present_queue,
+                image.handle,
+                (
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::PipelineStageFlags::TRANSFER,
+                ),
+                (
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::SHADER_READ,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                ),
+                subresource_range,
+            );
+
+        staging_buffer.vk_destroy();
+
+        image
+    }
 }
 
 impl std::ops::Drop for VulkanContext {
@@ -7,9 +7,11 @@ pub mod bindless {
 
     pub const CUBE_SAMPLER_BINDING: u32 = 0;
     pub const DEPTH_SAMPLER_BINDING: u32 = 1;
+    pub const TEXTURE_SAMPLER_BINDING: u32 = 2;
 
     const CUBE_SAMPLER_COUNT: u32 = 2;
     const DEPTH_SAMPLER_COUNT: u32 = 4;
+    const TEXTURE_SAMPLER_COUNT: u32 = 4096;
 
     pub struct DescriptorSet {
         pool: vk::DescriptorPool,
@@ -17,6 +19,11 @@ pub mod bindless {
         pub handle: vk::DescriptorSet,
         pub traditional_pipeline_layout: vk::PipelineLayout,
         pub meshlet_pipeline_layout: vk::PipelineLayout,
+        // next never used slot in TEXTURE_SAMPLER_BINDING
+        next_texture_slot: std::sync::atomic::AtomicU32,
+        // slots given back by reloaded assets, reused before next_texture_slot. Sorted, adjacent
+        // ranges are merged.
+        free_texture_slots: std::sync::Mutex<std::vec::Vec<std::ops::Range<u32>>>,
         device: ash::Device,
     }
 
@@ -37,6 +44,8 @@ pub mod bindless {
                 handle: descriptor_set,
                 traditional_pipeline_layout,
                 meshlet_pipeline_layout,
+                next_texture_slot: std::sync::atomic::AtomicU32::new(0),
+                free_texture_slots: std::sync::Mutex::new(vec![]),
                 device,
             }
         }
@@ -47,6 +56,57 @@ pub mod bindless {
             sampler: vk::Sampler,
             image_layout: vk::ImageLayout,
             dst_array_element: u32,
+        ) {
+            self.write_combined_image_sampler(
+                DEPTH_SAMPLER_BINDING,
+                image_view,
+                sampler,
+                image_layout,
+                dst_array_element,
+            );
+        }
+
+        // Reserves `count` consecutive slots in TEXTURE_SAMPLER_BINDING, returns the first one.
+        // `replaced` are slots of an earlier reservation whose textures the new ones replace, they
+        // count as free and are given back wherever the new slots land, so the textures in them
+        // must not be in use anymore. None if nothing is long enough, `replaced` stays reserved.
+        pub fn reserve_texture_slots(
+            &self,
+            count: u32,
+            replaced: std::ops::Range<u32>,
+        ) -> Option<u32> {
+            let mut free_slots = self.free_texture_slots.lock().unwrap();
+            insert_free_slots(&mut free_slots, replaced.clone());
+            let first_slot = take_free_slots(&mut free_slots, count)
+                .or_else(|| take_unused_slots(&self.next_texture_slot, count));
+            if first_slot.is_none() {
+                remove_free_slots(&mut free_slots, replaced);
+            }
+            first_slot
+        }
+
+        pub fn update_texture2d(
+            &self,
+            image_view: vk::ImageView,
+            sampler: vk::Sampler,
+            dst_array_element: u32,
+        ) {
+            self.write_combined_image_sampler(
+                TEXTURE_SAMPLER_BINDING,
+                image_view,
+                sampler,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                dst_array_element,
+            );
+        }
+
+        fn write_combined_image_sampler(
+            &self,
+            binding: u32,
+            image_view: vk::ImageView,
+            sampler: vk::Sampler,
+            image_layout: vk::ImageLayout,
+            dst_array_element: u32,
         ) {
             let descriptor_image_info = [vk::DescriptorImageInfo::default()
                 .sampler(sampler)
@@ -55,7 +115,7 @@ pub mod bindless {
 
             let descriptor_writes = [vk::WriteDescriptorSet::default()
                 .dst_set(self.handle)
-                .dst_binding(DEPTH_SAMPLER_BINDING)
+                .dst_binding(binding)
                 .descriptor_count(1)
                 .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
                 .dst_array_element(dst_array_element)
@@ -108,6 +168,9 @@ pub mod bindless {
             vk::DescriptorPoolSize::default()
                 .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
                 .descriptor_count(DEPTH_SAMPLER_COUNT),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(TEXTURE_SAMPLER_COUNT),
         ];
 
         let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::default()
@@ -136,6 +199,11 @@ pub mod bindless {
                 .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
                 .descriptor_count(DEPTH_SAMPLER_COUNT)
                 .stage_flags(vk::ShaderStageFlags::ALL),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(TEXTURE_SAMPLER_BINDING)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(TEXTURE_SAMPLER_COUNT)
+                .stage_flags(vk::ShaderStageFlags::ALL),
         ];
 
         let binding_flags = [
@@ -143,6 +211,8 @@ pub mod bindless {
                 | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
             vk::DescriptorBindingFlags::PARTIALLY_BOUND
                 | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
         ];
         let mut binding_flags_create_info =
             vk::DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
@@ -197,6 +267,78 @@ pub mod bindless {
         }
     }
 
+    // Takes `count` slots off the start of the first free range long enough
+    fn take_free_slots(
+        free_slots: &mut std::vec::Vec<std::ops::Range<u32>>,
+        count: u32,
+    ) -> Option<u32> {
+        let index = free_slots
+            .iter()
+            .position(|range| range.len() >= count as usize)?;
+        let first_slot = free_slots[index].start;
+        free_slots[index].start += count;
+        if free_slots[index].is_empty() {
+            free_slots.remove(index);
+        }
+        Some(first_slot)
+    }
+
+    // Takes `count` slots off the never used ones, leaves `next_slot` as it is if they don't fit
+    fn take_unused_slots(next_slot: &std::sync::atomic::AtomicU32, count: u32) -> Option<u32> {
+        next_slot
+            .fetch_update(
+                std::sync::atomic::Ordering::Relaxed,
+                std::sync::atomic::Ordering::Relaxed,
+                |next| {
+                    next.checked_add(count)
+                        .filter(|&end| end <= TEXTURE_SAMPLER_COUNT)
+                },
+            )
+            .ok()
+    }
+
+    // Adds `slots` to the sorted free list, merged with the ranges right before and after them
+    fn insert_free_slots(
+        free_slots: &mut std::vec::Vec<std::ops::Range<u32>>,
+        slots: std::ops::Range<u32>,
+    ) {
+        if slots.is_empty() {
+            return;
+        }
+        let index = free_slots.partition_point(|range| range.start < slots.start);
+        let merges_previous = index > 0 && free_slots[index - 1].end == slots.start;
+        let merges_next = free_slots
+            .get(index)
+            .is_some_and(|range| range.start == slots.end);
+        match (merges_previous, merges_next) {
+            (true, true) => {
+                free_slots[index - 1].end = free_slots[index].end;
+                free_slots.remove(index);
+            }
+            (true, false) => free_slots[index - 1].end = slots.end,
+            (false, true) => free_slots[index].start = slots.start,
+            (false, false) => free_slots.insert(index, slots),
+        }
+    }
+
+    // Takes `slots` out of the free range holding them, splitting it around them
+    fn remove_free_slots(
+        free_slots: &mut std::vec::Vec<std::ops::Range<u32>>,
+        slots: std::ops::Range<u32>,
+    ) {
+        if slots.is_empty() {
+            return;
+        }
+        let index = free_slots.partition_point(|range| range.end <= slots.start);
+        let range = free_slots[index].clone();
+        debug_assert!(range.start <= slots.start && slots.end <= range.end);
+        let rest = [range.start..slots.start, slots.end..range.end];
+        free_slots.splice(
+            index..=index,
+            rest.into_iter().filter(|range| !range.is_empty()),
+        );
+    }
+
     fn create_meshlet_pipeline_layout(
         device: &ash::Device,
         set_layout: vk::DescriptorSetLayout,
@@ -212,4 +354,65 @@ pub mod bindless {
                 .expect("Failed to create meshlet pipeline layout")
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn free_slots_stay_sorted_and_merge_neighbors() {
+            let mut free_slots = vec![];
+            insert_free_slots(&mut free_slots, 10..12);
+            insert_free_slots(&mut free_slots, 0..4);
+            insert_free_slots(&mut free_slots, 20..20);
+            assert_eq!(free_slots, vec![0..4, 10..12]);
+
+            // touches the previous range
+            insert_free_slots(&mut free_slots, 4..6);
+            assert_eq!(free_slots, vec![0..6, 10..12]);
+            // touches the next range
+            insert_free_slots(&mut free_slots, 8..10);
+            assert_eq!(free_slots, vec![0..6, 8..12]);
+            // closes the gap between both
+            insert_free_slots(&mut free_slots, 6..8);
+            assert_eq!(free_slots, vec![0..12]);
+        }
+
+        #[test]
+        fn free_slots_are_taken_first_fit_and_put_back() {
+            let mut free_slots = vec![0..2, 10..16];
+            assert_eq!(take_free_slots(&mut free_slots, 4), Some(10));
+            assert_eq!(free_slots, vec![0..2, 14..16]);
+            assert_eq!(take_free_slots(&mut free_slots, 2), Some(0));
+            assert_eq!(free_slots, vec![14..16]);
+            assert_eq!(take_free_slots(&mut free_slots, 3), None);
+
+            // a replaced reservation that doesn't fit its replacement goes back to being taken
+            insert_free_slots(&mut free_slots, 10..14);
+            assert_eq!(take_free_slots(&mut free_slots, 7), None);
+            remove_free_slots(&mut free_slots, 10..14);
+            assert_eq!(free_slots, vec![14..16]);
+            insert_free_slots(&mut free_slots, 4..6);
+            insert_free_slots(&mut free_slots, 6..8);
+            remove_free_slots(&mut free_slots, 5..7);
+            assert_eq!(free_slots, vec![4..5, 7..8, 14..16]);
+        }
+
+        #[test]
+        fn unused_slots_run_out_without_moving_the_counter() {
+            let next_slot = std::sync::atomic::AtomicU32::new(0);
+            assert_eq!(take_unused_slots(&next_slot, 10), Some(0));
+            assert_eq!(take_unused_slots(&next_slot, TEXTURE_SAMPLER_COUNT), None);
+            assert_eq!(take_unused_slots(&next_slot, u32::MAX), None);
+            assert_eq!(
+                take_unused_slots(&next_slot, TEXTURE_SAMPLER_COUNT - 10),
+                Some(10)
+            );
+            assert_eq!(take_unused_slots(&next_slot, 1), None);
+            assert_eq!(
+                take_unused_slots(&next_slot, 0),
+                Some(TEXTURE_SAMPLER_COUNT)
+            );
+        }
+    }
 }
@@ -10,7 +10,10 @@ pub struct GPUPushConstantsTraditional {
     pub skybox: vk::DeviceAddress,         // SkyboxBuf
     pub instances: vk::DeviceAddress,      // TraditionalInstanceBuf
     pub instance_offsets: vk::DeviceAddress, // TraditionalOffsetBuf
+    pub materials: vk::DeviceAddress,      // MaterialBuf
+    pub lights: vk::DeviceAddress,         // PunctualLightBuf
     pub depth_sampler_index: u32,
+    pub light_count: u32,
 }
 
 #[derive(Clone, Default)]
@@ -19,6 +22,9 @@ pub struct GPUPushConstantsMeshlet {
     pub camera: vk::DeviceAddress,      // CameraDataBuf (view: vertex transform)
     pub cull_camera: vk::DeviceAddress, // CameraDataBuf (cull: cone/frustum culling)
     pub meshlet_draws: vk::DeviceAddress, // MeshletDrawBuf
+    pub materials: vk::DeviceAddress,   // MaterialBuf
+    pub lights: vk::DeviceAddress,      // PunctualLightBuf
+    pub light_count: u32,
 }
 
 // TODO why I cannot define this as static or const array is beyond me. It says I cannot use
@@ -33,7 +39,9 @@ pub fn get_range_traditional() -> [vk::PushConstantRange; 1] {
 
 pub fn get_range_meshlet() -> [vk::PushConstantRange; 1] {
     [vk::PushConstantRange {
-        stage_flags: vk::ShaderStageFlags::TASK_EXT | vk::ShaderStageFlags::MESH_EXT,
+        stage_flags: vk::ShaderStageFlags::TASK_EXT
+            | vk::ShaderStageFlags::MESH_EXT
+            | vk::ShaderStageFlags::FRAGMENT,
         offset: 0,
         size: std::mem::size_of::<GPUPushConstantsMeshlet>() as u32,
     }]
@@ -22,9 +22,13 @@ impl Sampler {
             .max_lod(1.0)
             .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE);
 
+        Self::from_create_info(device, &create_info)
+    }
+
+    pub fn from_create_info(device: ash::Device, create_info: &vk::SamplerCreateInfo) -> Self {
         let sampler = unsafe {
             device
-                .create_sampler(&create_info, None)
+                .create_sampler(create_info, None)
                 .expect("Failed to create sampler.")
         };
 